@@ -6,25 +6,79 @@ use std::path::PathBuf;
 use std::collections::HashSet;
 
 use crate::runtime::RuntimeManager;
-use crate::shell::parser::Command as ShellCommand;
+use crate::language::registry::LanguageDefinition;
+use crate::shell::parser::{Command as ShellCommand, RedirectFiles};
 use crate::executor::dependency_detector::{DependencyDetector, MissingDependency};
+use crate::utils::error::PieBashError;
+use crate::shell::config::{Config, Verbosity};
+use crate::platform::{ChildMemory, JobSignal, PeakMemory};
+use crate::shell::builtins::filesystem::format_size;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SandboxMode {
+    Off,
+    Env,
+    TempDir,
+}
+
+/// What a finished program left behind.
+struct ProgramOutput {
+    status: std::process::ExitStatus,
+    stdout: Option<String>,
+    stderr: String,
+    elapsed: std::time::Duration,
+    peak_memory: Option<PeakMemory>,
+}
 
 #[derive(Clone)]
 pub struct CodeExecutor {
     runtime_manager: RuntimeManager,
     detector: DependencyDetector,
+    autoinstall: bool,
+    timeout: Option<std::time::Duration>,
+    verbosity: Verbosity,
 }
 
 impl CodeExecutor {
-    pub fn new(runtime_manager: RuntimeManager) -> Self {
+    pub fn new(runtime_manager: RuntimeManager, config: &Config) -> Self {
         Self {
             runtime_manager,
             detector: DependencyDetector::new(),
+            autoinstall: config.autoinstall,
+            timeout: (config.timeout > 0).then(|| std::time::Duration::from_secs(config.timeout)),
+            verbosity: config.verbosity,
         }
     }
 
-    pub async fn execute(&self, language: &str, command: &ShellCommand) -> Result<()> {
+    /// Runs the program, its output going to `outputs` where a redirect was given.
+    pub async fn execute(&self, language: &str, command: &ShellCommand, outputs: &RedirectFiles) -> Result<()> {
+        self.run(language, command, false, outputs).await.map(|_| ())
+    }
+
+    /// Runs `@lang` inline code and returns its stdout instead of printing it.
+    pub async fn capture(&self, language: &str, command: &ShellCommand) -> Result<String> {
+        Ok(self.run(language, command, true, &RedirectFiles::default()).await?.unwrap_or_default())
+    }
+
+    async fn run(&self, language: &str, command: &ShellCommand, capture: bool, outputs: &RedirectFiles) -> Result<Option<String>> {
+        // Leading `--stats`, and `-f` after `@lang`, are ours, not the program's
+        let mut command = command.clone();
+        let mut stats = Self::stats_enabled();
+        let mut from_file = !command.name.starts_with('@');
+        while let Some(flag) = command.args.first() {
+            match flag.as_str() {
+                "--stats" => stats = true,
+                "-f" if !from_file => from_file = true,
+                _ => break,
+            }
+            command.args.remove(0);
+        }
+        if from_file && command.args.is_empty() && command.stdin_input.is_none() {
+            anyhow::bail!("{}: missing file operand", command.name);
+        }
+
         let runtime = self.runtime_manager.ensure_runtime(language).await?;
+        let lang_def = self.runtime_manager.get_language(language)?;
 
         // Setup isolated environment
         let env_path = if language == "python" {
@@ -42,93 +96,165 @@ impl CodeExecutor {
         loop {
             attempt += 1;
 
-            if attempt > 1 {
+            if attempt > 1 && self.shows_progress() {
                 println!("\n{} Retry attempt {}...", "[RETRY]".yellow(), attempt);
             }
 
-            let result = if command.name.starts_with('@') {
-                let code = command.args.join(" ");
-                self.execute_inline(&runtime.executable, &env_path, language, &code).await
+            let output = if let (true, Some(input)) = (command.args.is_empty(), &command.stdin_input) {
+                self.execute_stdin(&runtime.executable, &env_path, language, input, outputs).await?
+            } else if !from_file {
+                // `@python 'code' -- a b`: what follows `--` is argv, not code
+                let (code, program_args) = match command.args.iter().position(|arg| arg == "--") {
+                    Some(pos) => (&command.args[..pos], &command.args[pos + 1..]),
+                    None => (&command.args[..], &[][..]),
+                };
+                let launch_args = lang_def.inline_command(&code.join(" "), program_args)?;
+                self.execute_inline(&runtime.executable, &env_path, language, &launch_args, capture, outputs).await?
             } else if !command.args.is_empty() {
-                let file = &command.args[0];
-                let args = &command.args[1..];
-                self.execute_file(&runtime.executable, &env_path, language, file, args).await
+                let mut file = command.args[0].clone();
+                // The sandbox working directory is elsewhere, so pin the script path first
+                if Self::sandbox_mode() == SandboxMode::TempDir {
+                    if let Ok(absolute) = std::fs::canonicalize(&file) {
+                        file = absolute.to_string_lossy().to_string();
+                    }
+                }
+                let input = command.stdin_input.as_deref();
+                if lang_def.compile_template.is_empty() {
+                    let launch_args = lang_def.run_command(&file, &command.args[1..]);
+                    self.execute_file(&runtime.executable, &env_path, language, &file, &launch_args, input, outputs).await?
+                } else {
+                    // Compiled languages build the file first; the binary is the program
+                    let (_build_dir, program) = self.compile(&runtime.executable, &env_path, language, &lang_def, &file).await?;
+                    self.execute_file(&program, &env_path, language, &file, &command.args[1..], input, outputs).await?
+                }
             } else {
                 anyhow::bail!("No code to execute");
             };
 
-            match result {
-                Ok(_) => {
-                    // Success! Code ran without errors
-                    if attempt > 1 {
-                        println!("\n{} Execution successful after installing {} dependencies", 
-                            "[SUCCESS]".green().bold(), installed_packages.len());
+            if output.status.success() {
+                // Success! Code ran without errors
+                if attempt > 1 && self.shows_outcome() {
+                    println!("\n{} Execution successful after installing {} dependencies", 
+                        "[SUCCESS]".green().bold(), installed_packages.len());
+                }
+                if stats {
+                    Self::print_stats(&output);
+                }
+                return Ok(output.stdout);
+            }
+
+            let e: anyhow::Error = PieBashError::from_status(&output.status).into();
+
+            // Try to detect and install missing dependencies from the program's own stderr
+            if let Some(deps) = DependencyDetector::parse_error(language, &output.stderr, "") {
+                if !self.autoinstall {
+                    let names: Vec<&str> = deps.iter().map(|dep| dep.package.as_str()).collect();
+                    if self.shows_outcome() {
+                        println!("\n{} Missing {} (autoinstall is off)", "[HINT]".yellow(), names.join(", "));
                     }
-                    return Ok(());
+                    return Err(e);
                 }
-                Err(e) => {
-                    let error_msg = format!("{:?}", e);
-                    
-                    // Try to detect and install missing dependencies
-                    if let Some(deps) = DependencyDetector::parse_error(language, &error_msg, "") {
-                        let mut any_new = false;
-                        
-                        for dep in &deps {
-                            // Check if we're stuck on the same package
-                            if let Some(ref last_pkg) = last_error_package {
-                                if last_pkg == &dep.package {
-                                    stuck_count += 1;
-                                    if stuck_count >= 2 {
-                                        println!("\n{} Unable to install {} after multiple attempts", 
-                                            "[FAILED]".red().bold(), dep.package);
-                                        return Err(e);
-                                    }
-                                } else {
-                                    stuck_count = 0;
-                                }
-                            }
-                            last_error_package = Some(dep.package.clone());
-                            
-                            // Skip if already installed
-                            if installed_packages.contains(&dep.package) {
-                                println!("{} Skipping {} (already installed)", "[SKIP]".yellow(), dep.package);
-                                continue;
-                            }
-                            
-                            any_new = true;
-                            
-                            match self.auto_install_dependency(dep, &env_path, &runtime.executable).await {
-                                Ok(_) => {
-                                    installed_packages.insert(dep.package.clone());
-                                }
-                                Err(install_err) => {
-                                    eprintln!("{} Failed to install {}: {}", 
-                                        "[ERROR]".red(), dep.package, install_err);
-                                }
-                            }
-                        }
-                        
-                        if !any_new {
-                            // No new packages to install, but still failing
+
+                let mut any_new = false;
+                
+                for dep in &deps {
+                    // Check if we're stuck on the same package
+                    if let Some(ref last_pkg) = last_error_package {
+                        if last_pkg == &dep.package {
                             stuck_count += 1;
                             if stuck_count >= 2 {
-                                println!("\n{} No new dependencies detected but still failing", 
-                                    "[FAILED]".red().bold());
+                                println!("\n{} Unable to install {} after multiple attempts", 
+                                    "[FAILED]".red().bold(), dep.package);
                                 return Err(e);
                             }
+                        } else {
+                            stuck_count = 0;
+                        }
+                    }
+                    last_error_package = Some(dep.package.clone());
+                    
+                    // Skip if already installed
+                    if installed_packages.contains(&dep.package) {
+                        if self.shows_progress() {
+                            println!("{} Skipping {} (already installed)", "[SKIP]".yellow(), dep.package);
                         }
-                        
-                        // Continue loop to retry
                         continue;
-                    } else {
-                        // Not a dependency error - this is a real error
+                    }
+
+                    // Installed by a previous session into the persistent env
+                    if Self::is_dependency_present(dep, &env_path) {
+                        if self.shows_progress() {
+                            println!("{} {} already present in environment", "[CACHED]".yellow(), dep.package);
+                        }
+                        installed_packages.insert(dep.package.clone());
+                        continue;
+                    }
+
+                    any_new = true;
+                    
+                    match self.auto_install_dependency(dep, &env_path, &runtime.executable).await {
+                        Ok(_) => {
+                            crate::shell::builtins::packages::record_installed(language, [dep.package.as_str()]);
+                            installed_packages.insert(dep.package.clone());
+                        }
+                        Err(install_err) => {
+                            eprintln!("{} Failed to install {}: {}", 
+                                "[ERROR]".red(), dep.package, install_err);
+                            // No package manager to retry with; the program's own failure stands
+                            if let Some(PieBashError::CommandNotFound(_)) = install_err.downcast_ref() {
+                                return Err(e);
+                            }
+                        }
+                    }
+                }
+                
+                if RuntimeManager::is_dry_run() {
+                    // Nothing was installed, so retrying would fail the same way
+                    return Err(e);
+                }
+
+                if !any_new {
+                    // No new packages to install, but still failing
+                    stuck_count += 1;
+                    if stuck_count >= 2 {
+                        println!("\n{} No new dependencies detected but still failing", 
+                            "[FAILED]".red().bold());
                         return Err(e);
                     }
                 }
+                
+                // Continue loop to retry
+                continue;
+            } else {
+                // Not a dependency error - this is a real error
+                return Err(e);
             }
         }
     }
 
+    /// Runs the managed toolchain with `args` verbatim (e.g. `go build`, `go mod tidy`).
+    pub async fn execute_toolchain(&self, language: &str, args: &[String], outputs: &RedirectFiles) -> Result<()> {
+        let runtime = self.runtime_manager.ensure_runtime(language).await?;
+
+        let mut cmd = Command::new(&runtime.executable);
+        cmd.args(args);
+
+        self.set_runtime_env(&mut cmd, &runtime.path, language);
+
+        let outputs = outputs.try_clone()?;
+        cmd.stdin(Stdio::inherit());
+        cmd.stdout(outputs.stdout.map_or_else(Stdio::inherit, Stdio::from));
+        cmd.stderr(outputs.stderr.map_or_else(Stdio::inherit, Stdio::from));
+
+        let status = cmd.status().await?;
+
+        if !status.success() {
+            return Err(PieBashError::from_status(&status).into());
+        }
+
+        Ok(())
+    }
+
     async fn ensure_python_env(&self, runtime_path: &PathBuf) -> Result<PathBuf> {
         let env_path = runtime_path.join("piebash_env");
         let site_packages = if cfg!(windows) {
@@ -138,7 +264,9 @@ impl CodeExecutor {
         };
 
         if !site_packages.exists() {
-            println!("{} Creating isolated environment (like Docker container)...", "[ENV]".cyan().bold());
+            if self.shows_progress() {
+                println!("{} Creating isolated environment (like Docker container)...", "[ENV]".cyan().bold());
+            }
             std::fs::create_dir_all(&site_packages)?;
             
             let pth_file = if cfg!(windows) {
@@ -155,36 +283,65 @@ impl CodeExecutor {
                 std::fs::write(&pth_file, pth_content)?;
             }
             
-            println!("{} Isolated environment created", "[OK]".green().bold());
+            if self.shows_progress() {
+                println!("{} Isolated environment created", "[OK]".green().bold());
+            }
         }
 
         Ok(env_path)
     }
 
-    async fn execute_inline(&self, executable: &PathBuf, env_path: &PathBuf, language: &str, code: &str) -> Result<()> {
-        println!("{} Executing inline code...\n", "[RUN]".cyan());
+    async fn execute_inline(&self, executable: &PathBuf, env_path: &PathBuf, language: &str, launch_args: &[String], capture: bool, outputs: &RedirectFiles) -> Result<ProgramOutput> {
+        if !capture {
+            self.print_run_banner("inline code");
+        }
 
         let mut cmd = Command::new(executable);
-        cmd.arg("-c");
-        cmd.arg(code);
+        cmd.args(launch_args);
         
+        let _sandbox_dir = self.apply_sandbox(&mut cmd)?;
         self.set_runtime_env(&mut cmd, env_path, language);
-        
-        cmd.stdin(Stdio::inherit());
-        cmd.stdout(Stdio::inherit());
-        cmd.stderr(Stdio::inherit());
 
-        let status = cmd.status().await?;
+        self.run_program(cmd, None, capture, outputs).await
+    }
 
-        if !status.success() {
-            anyhow::bail!("Execution failed with exit code: {:?}", status.code());
+    async fn execute_stdin(&self, executable: &PathBuf, env_path: &PathBuf, language: &str, input: &str, outputs: &RedirectFiles) -> Result<ProgramOutput> {
+        self.print_run_banner("code from stdin");
+
+        let mut cmd = Command::new(executable);
+        cmd.arg("-");
+
+        let _sandbox_dir = self.apply_sandbox(&mut cmd)?;
+        self.set_runtime_env(&mut cmd, env_path, language);
+
+        self.run_program(cmd, Some(input), false, outputs).await
+    }
+
+    /// Builds `file` into a temporary binary; the returned directory must outlive the run.
+    async fn compile(&self, compiler: &PathBuf, env_path: &PathBuf, language: &str, lang_def: &LanguageDefinition, file: &str) -> Result<(tempfile::TempDir, PathBuf)> {
+        if !std::path::Path::new(file).exists() {
+            anyhow::bail!("File not found: {}", file);
         }
 
-        Ok(())
+        let dir = tempfile::tempdir_in(crate::platform::native().get_temp_dir())?;
+        let program = dir.path().join(format!("program{}", std::env::consts::EXE_SUFFIX));
+
+        let mut cmd = Command::new(compiler);
+        cmd.args(lang_def.compile_command(file, &program.to_string_lossy()));
+        cmd.stdin(Stdio::null());
+        self.set_runtime_env(&mut cmd, env_path, language);
+
+        let status = cmd.status().await?;
+        if !status.success() {
+            return Err(PieBashError::from_status(&status).into());
+        }
+        Ok((dir, program))
     }
 
-    async fn execute_file(&self, executable: &PathBuf, env_path: &PathBuf, language: &str, file: &str, args: &[String]) -> Result<()> {
-        println!("{} Executing {}...\n", "[RUN]".cyan(), file);
+    /// Runs a script file; `input` (a heredoc, here-string or piped text) is its stdin.
+    #[allow(clippy::too_many_arguments)]
+    async fn execute_file(&self, executable: &PathBuf, env_path: &PathBuf, language: &str, file: &str, launch_args: &[String], input: Option<&str>, outputs: &RedirectFiles) -> Result<ProgramOutput> {
+        self.print_run_banner(file);
 
         let file_path = std::path::Path::new(file);
         if !file_path.exists() {
@@ -192,22 +349,188 @@ impl CodeExecutor {
         }
 
         let mut cmd = Command::new(executable);
-        cmd.arg(file_path);
-        cmd.args(args);
+        cmd.args(launch_args);
         
+        let _sandbox_dir = self.apply_sandbox(&mut cmd)?;
         self.set_runtime_env(&mut cmd, env_path, language);
-        
-        cmd.stdin(Stdio::inherit());
-        cmd.stdout(Stdio::inherit());
-        cmd.stderr(Stdio::inherit());
 
-        let status = cmd.status().await?;
+        self.run_program(cmd, input, false, outputs).await
+    }
 
-        if !status.success() {
-            anyhow::bail!("Execution failed with exit code: {:?}", status.code());
+    /// Spawns the program and waits for it. Stderr is echoed live (to the terminal or
+    /// its redirect file) and also kept so the dependency detector sees the real error text.
+    async fn run_program(&self, mut cmd: Command, stdin_input: Option<&str>, capture_stdout: bool, outputs: &RedirectFiles) -> Result<ProgramOutput> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // A retry writes through the same files, so each run gets its own handles
+        let outputs = outputs.try_clone()?;
+        cmd.stdin(if stdin_input.is_some() { Stdio::piped() } else { Stdio::inherit() });
+        cmd.stdout(match (capture_stdout, outputs.stdout) {
+            (true, _) => Stdio::piped(),
+            (false, Some(file)) => file.into(),
+            (false, None) => Stdio::inherit(),
+        });
+        cmd.stderr(Stdio::piped());
+        let stderr_sink = outputs.stderr;
+
+        let started = std::time::Instant::now();
+        let mut child = cmd.spawn()?;
+        let memory = ChildMemory::attach(&child);
+
+        if let (Some(input), Some(mut stdin)) = (stdin_input, child.stdin.take()) {
+            // Feed stdin from a task so a chatty child can't deadlock us
+            let input = input.to_string();
+            tokio::spawn(async move {
+                let _ = stdin.write_all(input.as_bytes()).await;
+            });
         }
 
-        Ok(())
+        let stderr_task = child.stderr.take().map(|mut pipe| {
+            tokio::spawn(async move {
+                use std::io::Write;
+
+                let mut sink: Box<dyn Write + Send> = match stderr_sink {
+                    Some(file) => Box::new(file),
+                    None => Box::new(std::io::stderr()),
+                };
+                let mut captured = Vec::new();
+                let mut buf = [0u8; 4096];
+                while let Ok(n) = pipe.read(&mut buf).await {
+                    if n == 0 {
+                        break;
+                    }
+                    let _ = sink.write_all(&buf[..n]);
+                    let _ = sink.flush();
+                    captured.extend_from_slice(&buf[..n]);
+                }
+                captured
+            })
+        });
+
+        let pid = child.id();
+        let finished = async {
+            let mut stdout = Vec::new();
+            if let Some(mut pipe) = child.stdout.take() {
+                pipe.read_to_end(&mut stdout).await?;
+            }
+            let status = child.wait().await?;
+            Ok::<_, anyhow::Error>((status, stdout))
+        };
+        let finished = async {
+            tokio::select! {
+                result = finished => result,
+                _ = Self::resume_on_stop(pid) => unreachable!("resume_on_stop never returns"),
+            }
+        };
+
+        let (status, stdout) = match self.timeout {
+            Some(limit) => match tokio::time::timeout(limit, finished).await {
+                Ok(result) => result?,
+                Err(_) => {
+                    child.kill().await.ok();
+                    eprintln!("{} Killed after {}s", "[TIMEOUT]".red().bold(), limit.as_secs());
+                    // Same status as timeout(1)
+                    return Err(PieBashError::ExitStatus(124).into());
+                }
+            },
+            None => finished.await?,
+        };
+        let elapsed = started.elapsed();
+        let stderr = match stderr_task {
+            Some(task) => task.await.unwrap_or_default(),
+            None => Vec::new(),
+        };
+
+        Ok(ProgramOutput {
+            status,
+            stdout: capture_stdout.then(|| String::from_utf8_lossy(&stdout).to_string()),
+            stderr: String::from_utf8_lossy(&stderr).to_string(),
+            elapsed,
+            peak_memory: memory.peak(),
+        })
+    }
+
+    /// A code run can't become a job (the install-and-retry loop is waiting on it),
+    /// so when Ctrl-Z stops the program it is carried on straight away.
+    async fn resume_on_stop(pid: Option<u32>) {
+        loop {
+            crate::platform::stop_requested().await;
+            if let Some(pid) = pid {
+                crate::platform::signal_process(pid, JobSignal::Continue).ok();
+            }
+            eprintln!("\n{} Code runs can't be suspended; it keeps running", "[WARN]".yellow());
+        }
+    }
+
+    /// `PIEBASH_EXEC_STATS=1` turns on `--stats` for every run.
+    fn stats_enabled() -> bool {
+        std::env::var("PIEBASH_EXEC_STATS").is_ok_and(|v| !v.is_empty() && v != "0")
+    }
+
+    /// Wall time and peak memory, on stderr so redirected output stays clean.
+    fn print_stats(output: &ProgramOutput) {
+        let memory = match &output.peak_memory {
+            Some(peak) if peak.exact => format!("{} peak RSS", format_size(peak.bytes)),
+            Some(peak) => format!("<= {} peak RSS", format_size(peak.bytes)),
+            None => "peak RSS unavailable".to_string(),
+        };
+        eprintln!("{} {:.3}s wall, {}", "[STATS]".cyan(), output.elapsed.as_secs_f64(), memory);
+    }
+
+    /// Reads `PIEBASH_SANDBOX`: `1` strips the inherited environment, `tmp` also
+    /// runs the program in a throwaway working directory.
+    fn sandbox_mode() -> SandboxMode {
+        match std::env::var("PIEBASH_SANDBOX").as_deref() {
+            Ok("tmp") => SandboxMode::TempDir,
+            Ok(v) if !v.is_empty() && v != "0" => SandboxMode::Env,
+            _ => SandboxMode::Off,
+        }
+    }
+
+    /// Every step of the install-and-retry loop, at `verbosity = "verbose"`.
+    fn shows_progress(&self) -> bool {
+        self.verbosity >= Verbosity::Verbose
+    }
+
+    /// How a run turned out (and the `[RUN]` banner); everything but `quiet`.
+    fn shows_outcome(&self) -> bool {
+        self.verbosity >= Verbosity::Normal
+    }
+
+    fn print_run_banner(&self, what: &str) {
+        if !self.shows_outcome() {
+            return;
+        }
+        match Self::sandbox_mode() {
+            SandboxMode::Off => println!("{} Executing {}...\n", "[RUN]".cyan(), what),
+            _ => println!("{} Executing {} {}...\n", "[RUN]".cyan(), what, "[SANDBOX]".yellow().bold()),
+        }
+    }
+
+    /// Starts the child from a minimal environment when sandboxed. The returned
+    /// temp dir must be kept alive until the child exits.
+    fn apply_sandbox(&self, cmd: &mut Command) -> Result<Option<tempfile::TempDir>> {
+        let mode = Self::sandbox_mode();
+        if mode == SandboxMode::Off {
+            return Ok(None);
+        }
+
+        cmd.env_clear();
+        if let Ok(path) = std::env::var("PATH") {
+            cmd.env("PATH", path);
+        }
+        #[cfg(windows)]
+        if let Ok(root) = std::env::var("SystemRoot") {
+            cmd.env("SystemRoot", root);
+        }
+
+        if mode == SandboxMode::TempDir {
+            let dir = tempfile::tempdir_in(crate::platform::native().get_temp_dir())?;
+            cmd.current_dir(dir.path());
+            return Ok(Some(dir));
+        }
+
+        Ok(None)
     }
 
     fn set_runtime_env(&self, cmd: &mut Command, env_path: &PathBuf, language: &str) {
@@ -220,7 +543,11 @@ impl CodeExecutor {
                 };
                 
                 if site_packages.exists() {
-                    let current_path = std::env::var("PYTHONPATH").unwrap_or_default();
+                    let current_path = if Self::sandbox_mode() == SandboxMode::Off {
+                        std::env::var("PYTHONPATH").unwrap_or_default()
+                    } else {
+                        String::new()
+                    };
                     let new_path = if current_path.is_empty() {
                         site_packages.to_string_lossy().to_string()
                     } else {
@@ -248,9 +575,94 @@ impl CodeExecutor {
         }
     }
 
+    fn is_dependency_present(dep: &MissingDependency, env_path: &std::path::Path) -> bool {
+        match dep.language.as_str() {
+            "python" => {
+                // lib/python3.X/site-packages, whichever 3.X the venv was made with
+                let site_packages: Vec<std::path::PathBuf> = if cfg!(windows) {
+                    vec![env_path.join("Lib").join("site-packages")]
+                } else {
+                    std::fs::read_dir(env_path.join("lib"))
+                        .map(|entries| {
+                            entries
+                                .filter_map(|e| e.ok())
+                                .filter(|entry| entry.file_name().to_string_lossy().starts_with("python3."))
+                                .map(|entry| entry.path().join("site-packages"))
+                                .collect()
+                        })
+                        .unwrap_or_default()
+                };
+
+                // pip records every install as <name>-<version>.dist-info
+                let normalize = |name: &str| name.to_lowercase().replace(['-', '.'], "_");
+                let wanted = normalize(&dep.package);
+
+                site_packages.iter().any(|dir| {
+                    std::fs::read_dir(dir)
+                        .map(|entries| {
+                            entries.filter_map(|e| e.ok()).any(|entry| {
+                                let name = entry.file_name().to_string_lossy().to_string();
+                                match name.strip_suffix(".dist-info") {
+                                    Some(stem) => normalize(stem.split('-').next().unwrap_or(stem)) == wanted,
+                                    None => false,
+                                }
+                            })
+                        })
+                        .unwrap_or(false)
+                })
+            }
+            "node" => {
+                env_path.join("node_modules").join(&dep.package).join("package.json").exists()
+            }
+            "ruby" => {
+                // <name>-<version>.gemspec; the digit keeps `rails` from matching `rails-html-sanitizer`
+                let prefix = format!("{}-", dep.package);
+                std::fs::read_dir(env_path.join("gems").join("specifications"))
+                    .map(|entries| {
+                        entries.filter_map(|e| e.ok()).any(|entry| {
+                            let name = entry.file_name().to_string_lossy().to_string();
+                            name.ends_with(".gemspec")
+                                && name
+                                    .strip_prefix(&prefix)
+                                    .is_some_and(|version| version.starts_with(|c: char| c.is_ascii_digit()))
+                        })
+                    })
+                    .unwrap_or(false)
+            }
+            "go" => {
+                // Modules land in $GOPATH/pkg/mod/<path>@<version>
+                let module_path = std::path::Path::new(&dep.package);
+                let (parent, last) = match (module_path.parent(), module_path.file_name()) {
+                    (Some(parent), Some(last)) => (parent, last.to_string_lossy().to_string()),
+                    _ => return false,
+                };
+                let prefix = format!("{}@", last);
+
+                std::fs::read_dir(env_path.join("pkg").join("mod").join(parent))
+                    .map(|entries| {
+                        entries.filter_map(|e| e.ok())
+                            .any(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+                    })
+                    .unwrap_or(false)
+            }
+            _ => false,
+        }
+    }
+
     async fn auto_install_dependency(&self, dep: &MissingDependency, env_path: &PathBuf, python_exe: &PathBuf) -> Result<()> {
-        println!("\n{} Missing dependency: {}", "[AUTO-INSTALL]".magenta().bold(), dep.package.green());
-        println!("{} Installing {}...", "[PIP]".cyan(), dep.package);
+        if self.shows_progress() {
+            println!("\n{} Missing dependency: {}", "[AUTO-INSTALL]".magenta().bold(), dep.package.green());
+        }
+
+        if RuntimeManager::is_dry_run() {
+            println!("{} Would run: {} {}", "[DRY-RUN]".yellow().bold(),
+                dep.package_manager, dep.install_command.join(" "));
+            return Ok(());
+        }
+
+        if self.shows_progress() {
+            println!("{} Installing {}...", "[PIP]".cyan(), dep.package);
+        }
 
         match dep.language.as_str() {
             "python" => self.install_python_package(dep, env_path, python_exe).await,
@@ -289,7 +701,9 @@ impl CodeExecutor {
             anyhow::bail!("pip install failed for {}", dep.package);
         }
 
-        println!("{} Installed {}", "[OK]".green().bold(), dep.package.green());
+        if self.shows_progress() {
+            println!("{} Installed {}", "[OK]".green().bold(), dep.package.green());
+        }
         Ok(())
     }
 
@@ -312,13 +726,14 @@ impl CodeExecutor {
             return Ok(());
         }
 
-        println!("{} Bootstrapping pip...", "[BOOTSTRAP]".yellow().bold());
+        if self.shows_progress() {
+            println!("{} Bootstrapping pip...", "[BOOTSTRAP]".yellow().bold());
+        }
 
         let get_pip_url = "https://bootstrap.pypa.io/get-pip.py";
         let get_pip_path = env_path.join("get-pip.py");
 
-        let response = reqwest::get(get_pip_url).await?;
-        let content = response.bytes().await?;
+        let content = self.fetch_get_pip(get_pip_url).await?;
         std::fs::write(&get_pip_path, &content)?;
 
         let mut cmd = Command::new(python_exe);
@@ -337,10 +752,47 @@ impl CodeExecutor {
             anyhow::bail!("Failed to bootstrap pip");
         }
 
-        println!("{} pip ready", "[OK]".green().bold());
+        if self.shows_progress() {
+            println!("{} pip ready", "[OK]".green().bold());
+        }
         Ok(())
     }
 
+    async fn fetch_get_pip(&self, url: &str) -> Result<Vec<u8>> {
+        const ATTEMPTS: u32 = 3;
+
+        let client = reqwest::Client::builder()
+            .connect_timeout(std::time::Duration::from_secs(10))
+            .timeout(std::time::Duration::from_secs(60))
+            .build()?;
+
+        let mut last_error = anyhow::anyhow!("no download attempted");
+
+        for attempt in 1..=ATTEMPTS {
+            let result = async {
+                let response = client.get(url).send().await?.error_for_status()?;
+                let content = response.bytes().await?;
+                if content.is_empty() {
+                    anyhow::bail!("downloaded get-pip.py is empty");
+                }
+                Ok(content.to_vec())
+            }.await;
+
+            match result {
+                Ok(content) => return Ok(content),
+                Err(e) => {
+                    if attempt < ATTEMPTS {
+                        eprintln!("{} get-pip.py download failed ({}), retrying...", "[RETRY]".yellow(), e);
+                        tokio::time::sleep(std::time::Duration::from_secs(2 * attempt as u64)).await;
+                    }
+                    last_error = e;
+                }
+            }
+        }
+
+        Err(last_error.context(format!("Failed to download {} after {} attempts", url, ATTEMPTS)))
+    }
+
     async fn install_node_package(&self, dep: &MissingDependency, env_path: &PathBuf) -> Result<()> {
         let npm_path = if cfg!(windows) {
             env_path.parent().unwrap().join("npm.cmd")
@@ -349,7 +801,7 @@ impl CodeExecutor {
         };
 
         if !npm_path.exists() {
-            anyhow::bail!("npm not found");
+            return Err(PieBashError::CommandNotFound("npm".to_string()).into());
         }
 
         let mut cmd = Command::new(&npm_path);
@@ -367,7 +819,9 @@ impl CodeExecutor {
             anyhow::bail!("npm install failed for {}", dep.package);
         }
 
-        println!("{} Installed {}", "[OK]".green().bold(), dep.package.green());
+        if self.shows_progress() {
+            println!("{} Installed {}", "[OK]".green().bold(), dep.package.green());
+        }
         Ok(())
     }
 
@@ -379,7 +833,7 @@ impl CodeExecutor {
         };
 
         if !gem_path.exists() {
-            anyhow::bail!("gem not found");
+            return Err(PieBashError::CommandNotFound("gem".to_string()).into());
         }
 
         let gem_home = env_path.join("gems");
@@ -400,7 +854,9 @@ impl CodeExecutor {
             anyhow::bail!("gem install failed for {}", dep.package);
         }
 
-        println!("{} Installed {}", "[OK]".green().bold(), dep.package.green());
+        if self.shows_progress() {
+            println!("{} Installed {}", "[OK]".green().bold(), dep.package.green());
+        }
         Ok(())
     }
 
@@ -412,7 +868,7 @@ impl CodeExecutor {
         };
 
         if !go_path.exists() {
-            anyhow::bail!("go not found");
+            return Err(PieBashError::CommandNotFound("go".to_string()).into());
         }
 
         let mut cmd = Command::new(&go_path);
@@ -429,7 +885,46 @@ impl CodeExecutor {
             anyhow::bail!("go get failed for {}", dep.package);
         }
 
-        println!("{} Installed {}", "[OK]".green().bold(), dep.package.green());
+        if self.shows_progress() {
+            println!("{} Installed {}", "[OK]".green().bold(), dep.package.green());
+        }
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dep(language: &str, package: &str) -> MissingDependency {
+        MissingDependency {
+            language: language.to_string(),
+            package: package.to_string(),
+            package_manager: String::new(),
+            install_command: Vec::new(),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn python_packages_are_found_under_any_3_x() {
+        let env = tempfile::tempdir().unwrap();
+        let site_packages = env.path().join("lib/python3.12/site-packages");
+        std::fs::create_dir_all(site_packages.join("Flask_Login-0.6.3.dist-info")).unwrap();
+
+        assert!(CodeExecutor::is_dependency_present(&dep("python", "flask-login"), env.path()));
+        assert!(!CodeExecutor::is_dependency_present(&dep("python", "flask"), env.path()));
+    }
+
+    #[test]
+    fn gem_name_must_be_followed_by_a_version() {
+        let env = tempfile::tempdir().unwrap();
+        let specifications = env.path().join("gems/specifications");
+        std::fs::create_dir_all(&specifications).unwrap();
+        std::fs::write(specifications.join("rails-html-sanitizer-1.6.0.gemspec"), "").unwrap();
+
+        assert!(!CodeExecutor::is_dependency_present(&dep("ruby", "rails"), env.path()));
+        std::fs::write(specifications.join("rails-7.1.3.gemspec"), "").unwrap();
+        assert!(CodeExecutor::is_dependency_present(&dep("ruby", "rails"), env.path()));
+    }
+}
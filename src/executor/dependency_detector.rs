@@ -34,6 +34,9 @@ impl DependencyDetector {
     fn parse_python_error(output: &str) -> Option<Vec<MissingDependency>> {
         let mut deps = Vec::new();
 
+        // Only the exception that ended the program counts, not earlier log lines
+        let output = output.lines().rev().find(|l| !l.trim().is_empty())?;
+
         // ModuleNotFoundError: No module named 'streamlit'
         let re1 = Regex::new(r"ModuleNotFoundError: No module named '([^']+)'").ok()?;
         for cap in re1.captures_iter(output) {
@@ -71,12 +74,17 @@ impl DependencyDetector {
     fn parse_node_error(output: &str) -> Option<Vec<MissingDependency>> {
         let mut deps = Vec::new();
 
+        // A real resolution failure carries an error code; a logged message doesn't
+        if !output.contains("MODULE_NOT_FOUND") && !output.contains("Module not found") {
+            return None;
+        }
+
         // Error: Cannot find module 'express'
         let re1 = Regex::new(r"Cannot find module '([^']+)'").ok()?;
         for cap in re1.captures_iter(output) {
             if let Some(module) = cap.get(1) {
                 let module_name = module.as_str();
-                if !Self::is_node_core_module(module_name) {
+                if !Self::is_node_core_module(module_name) && !Self::is_relative_module(module_name) {
                     deps.push(MissingDependency {
                         language: "node".to_string(),
                         package: module_name.to_string(),
@@ -92,7 +100,10 @@ impl DependencyDetector {
         for cap in re2.captures_iter(output) {
             if let Some(module) = cap.get(1) {
                 let module_name = module.as_str();
-                if !Self::is_node_core_module(module_name) && !deps.iter().any(|d| d.package == module_name) {
+                if !Self::is_node_core_module(module_name)
+                    && !Self::is_relative_module(module_name)
+                    && !deps.iter().any(|d| d.package == module_name)
+                {
                     deps.push(MissingDependency {
                         language: "node".to_string(),
                         package: module_name.to_string(),
@@ -110,8 +121,8 @@ impl DependencyDetector {
     fn parse_ruby_error(output: &str) -> Option<Vec<MissingDependency>> {
         let mut deps = Vec::new();
 
-        // cannot load such file -- sinatra
-        let re1 = Regex::new(r"cannot load such file -- ([^\s\(]+)").ok()?;
+        // cannot load such file -- sinatra (LoadError)
+        let re1 = Regex::new(r"cannot load such file -- ([^\s\(]+) \(LoadError\)").ok()?;
         for cap in re1.captures_iter(output) {
             if let Some(gem) = cap.get(1) {
                 let gem_name = gem.as_str();
@@ -287,6 +298,11 @@ impl DependencyDetector {
         core_modules.contains(&module)
     }
 
+    // HELPER: Local files (`./util`, `../lib/x`, `/abs/path`) are not npm packages
+    fn is_relative_module(module: &str) -> bool {
+        module.starts_with('.') || module.starts_with('/')
+    }
+
     // HELPER: Java package to Maven coordinates
     fn java_package_to_maven(package: &str) -> String {
         let mappings = [
@@ -1,2 +1,2 @@
 pub mod registry;
-pub use registry::LanguageRegistry;
\ No newline at end of file
+pub use registry::{LanguageRegistry, LanguageDefinition};
\ No newline at end of file
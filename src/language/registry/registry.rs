@@ -14,6 +14,17 @@ pub struct LanguageDefinition {
     pub executable: String,
     pub package_manager: Option<PackageManager>,
     pub downloads: HashMap<String, DownloadInfo>,
+    /// Arguments after the executable when running a file; `{file}` and `{args}` are substituted.
+    #[serde(default)]
+    pub run_template: Vec<String>,
+    /// Arguments after the executable for `@lang` code; `{code}` is substituted.
+    #[serde(default)]
+    pub inline_template: Vec<String>,
+    /// For a compiled language, arguments after the executable that build `{file}`
+    /// into the program `{out}`, which then runs with the file's arguments in place
+    /// of `run_template`.
+    #[serde(default)]
+    pub compile_template: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,6 +45,19 @@ impl LanguageRegistry {
         Ok(Self::default())
     }
 
+    /// Maps command aliases (`python3`, `nodejs`, `rb`, ...) to registry keys.
+    /// `python2` isn't one: the managed Python is 3.x, so it is left to `PATH`.
+    pub fn canonical_name(name: &str) -> &str {
+        match name {
+            "python3" => "python",
+            "nodejs" => "node",
+            "rb" => "ruby",
+            "rustc" => "rust",
+            "javac" => "java",
+            _ => name,
+        }
+    }
+
     pub fn get_language(&self, name: &str) -> Result<LanguageDefinition> {
         self.languages
             .get(name)
@@ -56,6 +80,9 @@ impl Default for LanguageRegistry {
                 executable: "pip".to_string(),
                 install_cmd: vec!["install".to_string()],
             }),
+            run_template: vec!["{file}".to_string(), "{args}".to_string()],
+            inline_template: vec!["-c".to_string(), "{code}".to_string()],
+            compile_template: Vec::new(),
             downloads: {
                 let mut map = HashMap::new();
                 map.insert("linux-x86_64".to_string(), DownloadInfo {
@@ -83,8 +110,11 @@ impl Default for LanguageRegistry {
             package_manager: Some(PackageManager {
                 name: "npm".to_string(),
                 executable: "npm".to_string(),
-                install_cmd: vec!["install".to_string(), "-g".to_string()],
+                install_cmd: vec!["install".to_string()],
             }),
+            run_template: vec!["{file}".to_string(), "{args}".to_string()],
+            inline_template: vec!["-e".to_string(), "{code}".to_string()],
+            compile_template: Vec::new(),
             downloads: {
                 let mut map = HashMap::new();
                 map.insert("linux-x86_64".to_string(), DownloadInfo {
@@ -113,6 +143,9 @@ impl Default for LanguageRegistry {
                 executable: "go".to_string(),
                 install_cmd: vec!["install".to_string()],
             }),
+            run_template: vec!["run".to_string(), "{file}".to_string(), "{args}".to_string()],
+            inline_template: Vec::new(),
+            compile_template: Vec::new(),
             downloads: {
                 let mut map = HashMap::new();
                 map.insert("linux-x86_64".to_string(), DownloadInfo {
@@ -141,6 +174,9 @@ impl Default for LanguageRegistry {
                 executable: "cargo".to_string(),
                 install_cmd: vec!["install".to_string()],
             }),
+            run_template: Vec::new(),
+            inline_template: Vec::new(),
+            compile_template: vec!["{file}".to_string(), "-o".to_string(), "{out}".to_string()],
             downloads: {
                 let mut map = HashMap::new();
                 map.insert("linux-x86_64".to_string(), DownloadInfo {
@@ -169,6 +205,9 @@ impl Default for LanguageRegistry {
                 executable: "gem".to_string(),
                 install_cmd: vec!["install".to_string()],
             }),
+            run_template: vec!["{file}".to_string(), "{args}".to_string()],
+            inline_template: vec!["-e".to_string(), "{code}".to_string()],
+            compile_template: Vec::new(),
             downloads: {
                 let mut map = HashMap::new();
                 map.insert("linux-x86_64".to_string(), DownloadInfo {
@@ -192,10 +231,81 @@ impl Default for LanguageRegistry {
 }
 
 impl LanguageDefinition {
+    /// Builds the launcher arguments for running `file`, defaulting to `<file> <args...>`.
+    pub fn run_command(&self, file: &str, args: &[String]) -> Vec<String> {
+        if self.run_template.is_empty() {
+            let mut command = vec![file.to_string()];
+            command.extend(args.iter().cloned());
+            return command;
+        }
+
+        let mut command = Vec::new();
+        for part in &self.run_template {
+            if part == "{args}" {
+                command.extend(args.iter().cloned());
+            } else {
+                command.push(part.replace("{file}", file));
+            }
+        }
+        command
+    }
+
+    /// Builds the launcher arguments for inline `code`, with `args` as the program's
+    /// argv (`python -c code a b` gives `sys.argv[1:] == ['a', 'b']`).
+    pub fn inline_command(&self, code: &str, args: &[String]) -> Result<Vec<String>> {
+        if self.inline_template.is_empty() {
+            anyhow::bail!("Inline execution is not supported for {}; save the code to a file and run that", self.name);
+        }
+
+        let mut command: Vec<String> = self.inline_template
+            .iter()
+            .map(|part| part.replace("{code}", code))
+            .collect();
+        command.extend(args.iter().cloned());
+        Ok(command)
+    }
+
+    /// The compiler arguments that build `file` into `out`, for a compiled language.
+    pub fn compile_command(&self, file: &str, out: &str) -> Vec<String> {
+        self.compile_template
+            .iter()
+            .map(|part| part.replace("{file}", file).replace("{out}", out))
+            .collect()
+    }
+
     pub fn get_download_url(&self, platform: &str) -> Result<DownloadInfo> {
         self.downloads
             .get(platform)
             .cloned()
             .ok_or_else(|| anyhow::anyhow!("No download available for platform: {}", platform))
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct RegistryFile {
+        languages: HashMap<String, LanguageDefinition>,
+    }
+
+    #[test]
+    fn data_file_matches_the_built_in_registry() {
+        let file: RegistryFile = serde_json::from_str(include_str!("../../../data/registry/languages.json")).unwrap();
+        let registry = LanguageRegistry::default();
+
+        let mut names: Vec<&String> = file.languages.keys().collect();
+        let mut built_in: Vec<&String> = registry.languages.keys().collect();
+        names.sort();
+        built_in.sort();
+        assert_eq!(names, built_in);
+
+        for (name, language) in &file.languages {
+            let expected = &registry.languages[name];
+            assert_eq!(language.version, expected.version, "{}", name);
+            assert_eq!(language.run_template, expected.run_template, "{}", name);
+            assert_eq!(language.inline_template, expected.inline_template, "{}", name);
+            assert_eq!(language.compile_template, expected.compile_template, "{}", name);
+        }
+    }
+}
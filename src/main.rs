@@ -1,7 +1,10 @@
 use anyhow::Result;
 use colored::*;
 use rustyline::error::ReadlineError;
-use rustyline::{DefaultEditor, Config, CompletionType, EditMode};
+use rustyline::history::FileHistory;
+use rustyline::{Editor, Config, CompletionType, EditMode};
+use std::collections::VecDeque;
+use std::io::IsTerminal;
 
 mod shell;
 mod runtime;
@@ -12,14 +15,27 @@ mod language;
 mod utils;
 
 use shell::Shell;
+use shell::completion::ShellHelper;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // Initialize logging
     tracing_subscriber::fmt::init();
 
-    // Print welcome message
-    print_banner();
+    // Print welcome message (not when scripted or piped into)
+    let no_banner = std::env::args().skip(1).any(|arg| arg == "--no-banner");
+    if !no_banner && std::io::stdin().is_terminal() {
+        print_banner();
+    }
+
+    // `--quiet` / `--verbose` outrank the config file, as PIEBASH_VERBOSITY does
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--quiet" => std::env::set_var("PIEBASH_VERBOSITY", "quiet"),
+            "--verbose" => std::env::set_var("PIEBASH_VERBOSITY", "verbose"),
+            _ => {}
+        }
+    }
 
     // Initialize shell
     let mut shell = Shell::new().await?;
@@ -29,42 +45,106 @@ async fn main() -> Result<()> {
         .history_ignore_space(true)
         .completion_type(CompletionType::List)
         .edit_mode(EditMode::Emacs)
+        .bracketed_paste(true)
+        .max_history_size(shell.config().histsize)?
         .build();
 
-    let mut rl = DefaultEditor::with_config(config)?;
+    let mut rl: Editor<ShellHelper, FileHistory> = Editor::with_config(config)?;
+    rl.set_helper(Some(ShellHelper::new()));
 
     // Load history
     let history_file = shell.get_history_file();
     let _ = rl.load_history(&history_file);
+    for entry in rl.history().iter() {
+        shell.add_history(entry);
+    }
 
         // Read line
     // Main REPL loop
-    loop {
+    'repl: loop {
+        // Like bash, say which background jobs finished and run $PROMPT_COMMAND,
+        // but only at a terminal
+        if std::io::stdin().is_terminal() {
+            shell.report_jobs();
+            shell.run_prompt_command().await;
+            terminal::output::settle();
+        }
+        shell.update_title(None);
+
         // Get prompt (plain text, no ANSI codes)
         let prompt = shell.get_prompt();
 
+        // `complete -W`, aliases and the like may have changed since the last line
+        if let Some(helper) = rl.helper_mut() {
+            helper.set_words(shell.completions());
+            helper.set_commands(shell.command_names());
+        }
+
         let readline = rl.readline(&prompt);
 
         match readline {
-            Ok(line) => {
-                let line = line.trim();
-
-                if line.is_empty() {
-                    continue;
-                }
-
-                // Add to history
-                let _ = rl.add_history_entry(line);
-
-                // Check for exit
-                if line == "exit" || line == "quit" {
-                    println!("{}", "exiting....".green());
-                    break;
-                }
-
-                // Execute command
-                if let Err(e) = shell.execute(line).await {
-                    eprintln!("{} {}", "Error:".red().bold(), e);
+            Ok(text) => {
+                // A bracketed paste lands in the buffer as one edit, newlines and
+                // all, and nothing runs until Enter; then its lines run in order,
+                // as if typed one at a time
+                let mut lines: VecDeque<String> = text.lines().map(str::to_string).collect();
+                while let Some(line) = lines.pop_front() {
+                    let line = line.trim();
+
+                    if line.is_empty() {
+                        continue;
+                    }
+
+                    // Add to history
+                    let _ = rl.add_history_entry(line);
+                    shell.add_history(line);
+
+                    // Check for exit
+                    if line == "exit" || line == "quit" {
+                        println!("{}", "exiting....".green());
+                        break 'repl;
+                    }
+
+                    // Heredoc: keep reading body lines until the delimiter, from the
+                    // rest of a paste first
+                    let mut input = line.to_string();
+                    if let Some(heredoc) = shell::parser::split_heredoc(line) {
+                        let mut interrupted = false;
+                        loop {
+                            let next = match lines.pop_front() {
+                                Some(body_line) => Ok(body_line),
+                                None => rl.readline("> "),
+                            };
+                            match next {
+                                Ok(body_line) => {
+                                    let done = heredoc.ends_at(&body_line);
+                                    input.push('\n');
+                                    input.push_str(&body_line);
+                                    if done {
+                                        break;
+                                    }
+                                }
+                                Err(ReadlineError::Interrupted) => {
+                                    interrupted = true;
+                                    break;
+                                }
+                                Err(_) => break,
+                            }
+                        }
+                        if interrupted {
+                            println!("^C");
+                            break;
+                        }
+                    }
+
+                    // Execute command; a non-zero exit status is not an error
+                    shell.update_title(Some(&input));
+                    if let Err(e) = shell.execute(&input).await {
+                        eprintln!("{} {}", "Error:".red().bold(), e);
+                    }
+
+                    // Async output may have left a progress line or a half line behind
+                    terminal::output::settle();
                 }
             }
             Err(ReadlineError::Interrupted) => {
@@ -84,6 +164,8 @@ async fn main() -> Result<()> {
         }
     }
 
+    shell.run_exit_trap().await;
+
     // Save history
     let _ = rl.save_history(&history_file);
 
@@ -1,5 +1,86 @@
-// Platform adapter - placeholder for future use
-pub trait PlatformAdapter {
-    fn get_home_dir(&self) -> std::path::PathBuf;
-    fn get_temp_dir(&self) -> std::path::PathBuf;
-}
\ No newline at end of file
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Where platform differences in locating files are handled.
+pub trait PlatformAdapter: Sync {
+    fn get_home_dir(&self) -> PathBuf;
+    fn get_temp_dir(&self) -> PathBuf;
+
+    /// Where piebash keeps its config, runtimes, downloads and history.
+    ///
+    /// `PIEBASH_HOME` puts everything in one directory. Otherwise each kind of file
+    /// goes under its XDG base directory when that variable is set (Linux only), and
+    /// under `~/.piebash` (history in `~/.piebash_history`) when it isn't.
+    fn piebash_paths(&self) -> PiebashPaths {
+        let home = self.get_home_dir();
+
+        if let Some(root) = env_dir("PIEBASH_HOME") {
+            return PiebashPaths {
+                config_dir: root.clone(),
+                data_dir: root.clone(),
+                cache_dir: root.join("cache"),
+                history_file: root.join("history"),
+            };
+        }
+
+        let legacy = home.join(".piebash");
+        let xdg = |var: &str| if cfg!(target_os = "linux") { env_dir(var) } else { None };
+
+        let (data_dir, history_file) = match xdg("XDG_DATA_HOME") {
+            Some(dir) => (dir.join("piebash"), dir.join("piebash").join("history")),
+            None => (legacy.clone(), home.join(".piebash_history")),
+        };
+
+        PiebashPaths {
+            config_dir: xdg("XDG_CONFIG_HOME").map(|dir| dir.join("piebash")).unwrap_or_else(|| legacy.clone()),
+            data_dir,
+            cache_dir: xdg("XDG_CACHE_HOME").map(|dir| dir.join("piebash")).unwrap_or_else(|| legacy.join("cache")),
+            history_file,
+        }
+    }
+}
+
+/// piebash's on-disk layout.
+#[derive(Debug, Clone)]
+pub struct PiebashPaths {
+    /// Holds `config.toml`
+    pub config_dir: PathBuf,
+    /// Holds installed runtimes
+    pub data_dir: PathBuf,
+    /// Holds downloaded archives
+    pub cache_dir: PathBuf,
+    pub history_file: PathBuf,
+}
+
+impl PiebashPaths {
+    pub fn config_file(&self) -> PathBuf {
+        self.config_dir.join("config.toml")
+    }
+
+    pub fn runtimes_dir(&self) -> PathBuf {
+        self.data_dir.join("runtimes")
+    }
+
+    /// Where `rm --trash` puts files
+    pub fn trash_dir(&self) -> PathBuf {
+        self.data_dir.join("trash")
+    }
+
+    /// One list per language of the packages piebash has installed, for Tab
+    pub fn packages_dir(&self) -> PathBuf {
+        self.data_dir.join("packages")
+    }
+}
+
+/// The layout for this process, worked out on first use.
+pub fn paths() -> &'static PiebashPaths {
+    static PATHS: OnceLock<PiebashPaths> = OnceLock::new();
+    PATHS.get_or_init(|| super::native().piebash_paths())
+}
+
+/// A directory named by an environment variable; empty and relative values are ignored, per the XDG spec.
+fn env_dir(var: &str) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+}
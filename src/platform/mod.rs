@@ -7,9 +7,73 @@ pub mod unix;
 #[cfg(windows)]
 pub mod windows;
 
+// Platform-specific helpers, one implementation per OS
+#[cfg(unix)]
+pub use unix::{
+    ChildMemory, clone_file, file_mode, file_owner, format_permissions, get_umask, is_executable, redirect_stdio, set_mode,
+    set_process_group, set_umask, signal_group, signal_process, stop_requested, symlink, terminate,
+};
+#[cfg(windows)]
+pub use windows::{
+    ChildMemory, clone_file, file_mode, file_owner, format_permissions, get_umask, is_executable, redirect_stdio, set_mode,
+    set_process_group, set_umask, signal_group, signal_process, stop_requested, symlink, terminate,
+};
+
+/// Who owns a file, as `stat` reports it.
+pub struct FileOwner {
+    pub uid: u32,
+    pub gid: u32,
+    pub user: String,
+    pub group: String,
+}
+
+/// What job control sends to a job's process group.
+#[derive(Debug, Clone, Copy)]
+pub enum JobSignal {
+    /// Ctrl-C, forwarded while the job is in the foreground
+    Interrupt,
+    /// Ctrl-Z
+    Stop,
+    /// `fg` / `bg` resuming a stopped job
+    Continue,
+}
+
+/// How much memory a child process used at its peak.
+pub struct PeakMemory {
+    pub bytes: u64,
+    /// False when only an upper bound is known
+    pub exact: bool,
+}
+
+/// The adapter for the platform piebash was built for.
+pub fn native() -> &'static dyn adapter::PlatformAdapter {
+    #[cfg(unix)]
+    {
+        &unix::UnixPlatform
+    }
+
+    #[cfg(windows)]
+    {
+        &windows::WindowsPlatform
+    }
+}
+
 pub fn detect_platform() -> String {
     let os = std::env::consts::OS;
     let arch = std::env::consts::ARCH;
 
     format!("{}-{}", os, arch)
-}
\ No newline at end of file
+}
+
+/// Whether the shell runs with root privileges (effective uid 0).
+pub fn is_root() -> bool {
+    #[cfg(unix)]
+    {
+        nix::unistd::geteuid().is_root()
+    }
+
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
@@ -0,0 +1,263 @@
+use anyhow::Result;
+use std::fs;
+use std::io::Write;
+use std::marker::PhantomData;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use super::adapter::PlatformAdapter;
+use super::{FileOwner, JobSignal, PeakMemory};
+
+pub struct UnixPlatform;
+
+impl PlatformAdapter for UnixPlatform {
+    /// `$HOME`, falling back to the passwd entry, then `/`.
+    fn get_home_dir(&self) -> PathBuf {
+        dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"))
+    }
+
+    /// `$TMPDIR`, or `/tmp`.
+    fn get_temp_dir(&self) -> PathBuf {
+        std::env::temp_dir()
+    }
+}
+
+/// `rwxr-xr-x` from the mode bits.
+pub fn format_permissions(metadata: &fs::Metadata) -> String {
+    let mode = metadata.permissions().mode();
+    let mut out = String::with_capacity(9);
+    for shift in [6, 3, 0] {
+        let bits = (mode >> shift) & 0o7;
+        out.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        out.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        out.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+    }
+    out
+}
+
+/// Permission bits, as `stat %a` shows them.
+pub fn file_mode(metadata: &fs::Metadata) -> u32 {
+    metadata.permissions().mode() & 0o7777
+}
+
+/// Owner and group ids and names; a name falls back to its id when it has no entry.
+pub fn file_owner(metadata: &fs::Metadata) -> FileOwner {
+    use nix::unistd::{Gid, Group, Uid, User};
+    use std::os::unix::fs::MetadataExt;
+
+    let (uid, gid) = (metadata.uid(), metadata.gid());
+    let user = User::from_uid(Uid::from_raw(uid)).ok().flatten().map(|u| u.name);
+    let group = Group::from_gid(Gid::from_raw(gid)).ok().flatten().map(|g| g.name);
+    FileOwner {
+        uid,
+        gid,
+        user: user.unwrap_or_else(|| uid.to_string()),
+        group: group.unwrap_or_else(|| gid.to_string()),
+    }
+}
+
+/// Any execute bit set.
+pub fn is_executable(path: &Path) -> bool {
+    fs::metadata(path)
+        .map(|metadata| metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+pub fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
+
+pub fn symlink(source: &Path, dest: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(source, dest)?;
+    Ok(())
+}
+
+/// Makes `dest` a copy-on-write clone of `source`, sharing its blocks until either
+/// is written: `FICLONE` on Linux (Btrfs, XFS), `clonefile` on macOS (APFS). Fails
+/// where the filesystem can't, for the caller to fall back to a byte copy.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn clone_file(source: &Path, dest: &Path) -> Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let src = fs::File::open(source)?;
+    let existed = dest.exists();
+    let dst = fs::File::create(dest)?;
+    if unsafe { nix::libc::ioctl(dst.as_raw_fd(), nix::libc::FICLONE, src.as_raw_fd()) } == -1 {
+        let err = std::io::Error::last_os_error();
+        drop(dst);
+        if !existed {
+            fs::remove_file(dest).ok();
+        }
+        return Err(err.into());
+    }
+    fs::set_permissions(dest, src.metadata()?.permissions())?;
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+pub fn clone_file(source: &Path, dest: &Path) -> Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    // clonefile won't replace an existing file, so clone beside it and rename over it
+    let name = dest.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let temp = dest.with_file_name(format!(".{}.piebash-clone", name));
+    let src = CString::new(source.as_os_str().as_bytes())?;
+    let tmp = CString::new(temp.as_os_str().as_bytes())?;
+    if unsafe { nix::libc::clonefile(src.as_ptr(), tmp.as_ptr(), 0) } == -1 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    if let Err(e) = fs::rename(&temp, dest) {
+        fs::remove_file(&temp).ok();
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+pub fn clone_file(_source: &Path, _dest: &Path) -> Result<()> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported).into())
+}
+
+/// The process umask, which the OS applies to every file and directory created.
+pub fn get_umask() -> u32 {
+    use nix::sys::stat::{umask, Mode};
+
+    // Reading it means setting it, so put the old value straight back
+    let old = umask(Mode::empty());
+    umask(old);
+    old.bits() as u32
+}
+
+pub fn set_umask(mask: u32) {
+    use nix::sys::stat::{umask, Mode};
+
+    umask(Mode::from_bits_truncate(mask as nix::libc::mode_t));
+}
+
+/// Peak RSS of a child process, from `getrusage(RUSAGE_CHILDREN)`.
+///
+/// That figure is the largest of every child reaped so far, so it's only this
+/// child's own peak when it went above the value seen at `attach`; otherwise
+/// all that's known is that the child stayed at or under it.
+pub struct ChildMemory {
+    before: Option<u64>,
+}
+
+impl ChildMemory {
+    pub fn attach(_child: &tokio::process::Child) -> Self {
+        Self { before: children_max_rss() }
+    }
+
+    /// Call once the child has been waited for.
+    pub fn peak(&self) -> Option<PeakMemory> {
+        let after = children_max_rss()?;
+        Some(PeakMemory { bytes: after, exact: self.before.is_none_or(|before| after > before) })
+    }
+}
+
+fn children_max_rss() -> Option<u64> {
+    use nix::sys::resource::{getrusage, UsageWho};
+
+    let max_rss = getrusage(UsageWho::RUSAGE_CHILDREN).ok()?.max_rss() as u64;
+    // Bytes on macOS, KiB everywhere else
+    if cfg!(target_os = "macos") {
+        Some(max_rss)
+    } else {
+        Some(max_rss * 1024)
+    }
+}
+
+/// Sends SIGTERM.
+pub fn terminate(pid: u32) -> Result<()> {
+    use nix::sys::signal::{kill, Signal};
+    use nix::unistd::Pid;
+
+    kill(Pid::from_raw(pid as i32), Signal::SIGTERM)?;
+    Ok(())
+}
+
+/// Puts the child in process group `pgid`, or a new one of its own when it's 0.
+pub fn set_process_group(cmd: &mut tokio::process::Command, pgid: u32) {
+    cmd.process_group(pgid as i32);
+}
+
+/// Sends `signal` to every process in the group.
+pub fn signal_group(pgid: u32, signal: JobSignal) -> Result<()> {
+    use nix::sys::signal::killpg;
+    use nix::unistd::Pid;
+
+    killpg(Pid::from_raw(pgid as i32), unix_signal(signal))?;
+    Ok(())
+}
+
+/// Sends `signal` to one process.
+pub fn signal_process(pid: u32, signal: JobSignal) -> Result<()> {
+    use nix::sys::signal::kill;
+    use nix::unistd::Pid;
+
+    kill(Pid::from_raw(pid as i32), unix_signal(signal))?;
+    Ok(())
+}
+
+fn unix_signal(signal: JobSignal) -> nix::sys::signal::Signal {
+    use nix::sys::signal::Signal;
+
+    match signal {
+        JobSignal::Interrupt => Signal::SIGINT,
+        JobSignal::Stop => Signal::SIGSTOP,
+        JobSignal::Continue => Signal::SIGCONT,
+    }
+}
+
+/// The shell's own stdout and stderr pointed at files for as long as this lives, so
+/// whatever a builtin prints follows its `>` / `&>` redirects. Dropping it points
+/// them back.
+pub struct StdioRedirect<'a> {
+    saved: Vec<(RawFd, OwnedFd)>,
+    _files: PhantomData<&'a fs::File>,
+}
+
+pub fn redirect_stdio<'a>(stdout: Option<&'a fs::File>, stderr: Option<&'a fs::File>) -> Result<StdioRedirect<'a>> {
+    use nix::unistd::{dup, dup2};
+
+    flush_stdio();
+    let mut redirect = StdioRedirect { saved: Vec::new(), _files: PhantomData };
+    for (target, file) in [(1, stdout), (2, stderr)] {
+        let Some(file) = file else { continue };
+        // SAFETY: `dup` just returned this descriptor, so nothing else owns it.
+        let saved = unsafe { OwnedFd::from_raw_fd(dup(target)?) };
+        dup2(file.as_raw_fd(), target)?;
+        redirect.saved.push((target, saved));
+    }
+    Ok(redirect)
+}
+
+impl Drop for StdioRedirect<'_> {
+    fn drop(&mut self) {
+        flush_stdio();
+        for (target, saved) in self.saved.drain(..).rev() {
+            let _ = nix::unistd::dup2(saved.as_raw_fd(), target);
+        }
+    }
+}
+
+/// Buffered output goes out before the descriptors behind it change.
+fn flush_stdio() {
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+}
+
+/// Resolves at the next Ctrl-Z (SIGTSTP). Listening keeps the shell itself from stopping.
+pub async fn stop_requested() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::from_raw(nix::libc::SIGTSTP)) {
+        Ok(mut stop) => {
+            stop.recv().await;
+        }
+        Err(_) => std::future::pending().await,
+    }
+}
@@ -0,0 +1,263 @@
+use anyhow::Result;
+use std::fs;
+use std::io::Write;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use super::adapter::PlatformAdapter;
+use super::{FileOwner, JobSignal, PeakMemory};
+
+pub struct WindowsPlatform;
+
+impl PlatformAdapter for WindowsPlatform {
+    /// The user profile folder (`%USERPROFILE%`).
+    fn get_home_dir(&self) -> PathBuf {
+        dirs::home_dir()
+            .or_else(|| std::env::var_os("USERPROFILE").map(PathBuf::from))
+            .unwrap_or_else(|| PathBuf::from("C:\\"))
+    }
+
+    /// `%TMP%` / `%TEMP%`, or the Windows directory's temp folder.
+    fn get_temp_dir(&self) -> PathBuf {
+        std::env::temp_dir()
+    }
+}
+
+/// There are no mode bits; the read-only attribute takes away every `w`.
+pub fn format_permissions(metadata: &fs::Metadata) -> String {
+    let x = if metadata.is_dir() { 'x' } else { '-' };
+    let w = if metadata.permissions().readonly() { '-' } else { 'w' };
+    format!("r{w}{x}r{w}{x}r{w}{x}")
+}
+
+/// The mode `format_permissions` describes: read-only drops the write bits.
+pub fn file_mode(metadata: &fs::Metadata) -> u32 {
+    let write = if metadata.permissions().readonly() { 0 } else { 0o222 };
+    let execute = if metadata.is_dir() { 0o111 } else { 0 };
+    0o444 | write | execute
+}
+
+/// Files have no uid or gid here; the current user stands in as owner.
+pub fn file_owner(_metadata: &fs::Metadata) -> FileOwner {
+    FileOwner {
+        uid: 0,
+        gid: 0,
+        user: std::env::var("USERNAME").unwrap_or_else(|_| "unknown".to_string()),
+        group: "None".to_string(),
+    }
+}
+
+/// Judged by extension, as `cmd.exe` does.
+pub fn is_executable(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .is_some_and(|ext| matches!(ext.as_str(), "exe" | "bat" | "cmd" | "com"))
+}
+
+/// Only the owner write bit maps to anything: without it the file is made
+/// read-only (`chmod 444`), with it read-only is cleared (`chmod 644`).
+pub fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_readonly(mode & 0o200 == 0);
+    fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+// Windows has no umask; the value is only kept so `umask` can show it back
+static UMASK: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0o022);
+
+pub fn get_umask() -> u32 {
+    UMASK.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+pub fn set_umask(mask: u32) {
+    UMASK.store(mask, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// `ERROR_PRIVILEGE_NOT_HELD`: symlinks need administrator rights or Developer Mode.
+const ERROR_PRIVILEGE_NOT_HELD: i32 = 1314;
+
+/// Windows has separate file and directory symlinks. Without the privilege to make
+/// them, a directory gets a junction instead, which any user may create.
+pub fn symlink(source: &Path, dest: &Path) -> Result<()> {
+    let result = if source.is_dir() {
+        std::os::windows::fs::symlink_dir(source, dest)
+    } else {
+        std::os::windows::fs::symlink_file(source, dest)
+    };
+
+    match result {
+        Err(e) if e.raw_os_error() == Some(ERROR_PRIVILEGE_NOT_HELD) => {
+            if !source.is_dir() {
+                anyhow::bail!(
+                    "symlinks need administrator rights or Developer Mode \
+                     (Settings > For developers); use 'ln' without -s for a hard link"
+                );
+            }
+            junction(source, dest)?;
+            eprintln!("ln: no symlink privilege, created a directory junction instead");
+            Ok(())
+        }
+        other => Ok(other?),
+    }
+}
+
+/// Copy-on-write clones aren't supported here; callers fall back to a byte copy.
+pub fn clone_file(_source: &Path, _dest: &Path) -> Result<()> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported).into())
+}
+
+/// `mklink /J`; junction targets have to be absolute.
+fn junction(source: &Path, dest: &Path) -> Result<()> {
+    let target = std::env::current_dir()?.join(source);
+    let output = std::process::Command::new("cmd")
+        .arg("/C")
+        .arg("mklink")
+        .arg("/J")
+        .arg(dest)
+        .arg(&target)
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "could not create a junction either: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Peak memory of a child process, from a job object it's assigned to at spawn.
+pub struct ChildMemory {
+    job: Option<winapi::um::winnt::HANDLE>,
+}
+
+impl ChildMemory {
+    pub fn attach(child: &tokio::process::Child) -> Self {
+        use winapi::um::handleapi::CloseHandle;
+        use winapi::um::jobapi2::{AssignProcessToJobObject, CreateJobObjectW};
+
+        let Some(process) = child.raw_handle() else {
+            return Self { job: None };
+        };
+
+        // SAFETY: the process handle is owned by `child` and still open; the job
+        // handle is closed again on failure or in Drop.
+        unsafe {
+            let job = CreateJobObjectW(std::ptr::null_mut(), std::ptr::null());
+            if job.is_null() {
+                return Self { job: None };
+            }
+            if AssignProcessToJobObject(job, process as _) == 0 {
+                CloseHandle(job);
+                return Self { job: None };
+            }
+            Self { job: Some(job) }
+        }
+    }
+
+    /// Call once the child has been waited for.
+    pub fn peak(&self) -> Option<PeakMemory> {
+        use winapi::um::jobapi2::QueryInformationJobObject;
+        use winapi::um::winnt::{JobObjectExtendedLimitInformation, JOBOBJECT_EXTENDED_LIMIT_INFORMATION};
+
+        let job = self.job?;
+        // SAFETY: `info` is a plain C struct of the size passed alongside it.
+        unsafe {
+            let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+            let ok = QueryInformationJobObject(
+                job,
+                JobObjectExtendedLimitInformation,
+                &mut info as *mut _ as *mut _,
+                std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+                std::ptr::null_mut(),
+            );
+            (ok != 0).then(|| PeakMemory { bytes: info.PeakProcessMemoryUsed as u64, exact: true })
+        }
+    }
+}
+
+impl Drop for ChildMemory {
+    fn drop(&mut self) {
+        if let Some(job) = self.job {
+            // SAFETY: the handle came from CreateJobObjectW and is closed only here.
+            unsafe {
+                winapi::um::handleapi::CloseHandle(job);
+            }
+        }
+    }
+}
+
+/// Process groups only matter for signals, which Windows doesn't have.
+pub fn set_process_group(_cmd: &mut tokio::process::Command, _pgid: u32) {}
+
+pub fn signal_group(_pgid: u32, _signal: JobSignal) -> Result<()> {
+    anyhow::bail!("job control signals are not supported on Windows")
+}
+
+pub fn signal_process(_pid: u32, _signal: JobSignal) -> Result<()> {
+    anyhow::bail!("job control signals are not supported on Windows")
+}
+
+/// The shell's own stdout and stderr pointed at files for as long as this lives, so
+/// whatever a builtin prints follows its `>` / `&>` redirects. Dropping it points
+/// them back; Rust looks the standard handles up on every write.
+pub struct StdioRedirect<'a> {
+    saved: Vec<(u32, winapi::um::winnt::HANDLE)>,
+    _files: PhantomData<&'a fs::File>,
+}
+
+pub fn redirect_stdio<'a>(stdout: Option<&'a fs::File>, stderr: Option<&'a fs::File>) -> Result<StdioRedirect<'a>> {
+    use std::os::windows::io::AsRawHandle;
+    use winapi::um::processenv::{GetStdHandle, SetStdHandle};
+    use winapi::um::winbase::{STD_ERROR_HANDLE, STD_OUTPUT_HANDLE};
+
+    flush_stdio();
+    let mut redirect = StdioRedirect { saved: Vec::new(), _files: PhantomData };
+    for (target, file) in [(STD_OUTPUT_HANDLE, stdout), (STD_ERROR_HANDLE, stderr)] {
+        let Some(file) = file else { continue };
+        // SAFETY: the file handle stays open for the guard's lifetime `'a`, and the
+        // saved handle is only ever put back, never closed.
+        unsafe {
+            let saved = GetStdHandle(target);
+            if SetStdHandle(target, file.as_raw_handle() as _) == 0 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+            redirect.saved.push((target, saved));
+        }
+    }
+    Ok(redirect)
+}
+
+impl Drop for StdioRedirect<'_> {
+    fn drop(&mut self) {
+        flush_stdio();
+        for (target, saved) in self.saved.drain(..).rev() {
+            // SAFETY: `saved` is the handle that was standard before the redirect.
+            unsafe {
+                winapi::um::processenv::SetStdHandle(target, saved);
+            }
+        }
+    }
+}
+
+/// Buffered output goes out before the handles behind it change.
+fn flush_stdio() {
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+}
+
+/// There's no Ctrl-Z to forward on Windows.
+pub async fn stop_requested() {
+    std::future::pending().await
+}
+
+/// No signals on Windows; `taskkill /F` ends the process.
+pub fn terminate(pid: u32) -> Result<()> {
+    let output = std::process::Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/F"])
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!("taskkill failed");
+    }
+    Ok(())
+}
@@ -1,45 +1,94 @@
 use anyhow::Result;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 use sha2::{Sha256, Digest};
 use tokio::io::AsyncWriteExt;
 
+use crate::terminal::output;
+
 #[derive(Clone)]  // FIXED: Added Clone
 pub struct RuntimeDownloader {
     cache_dir: PathBuf,
+    // Offline mode: only archives already in the cache can be used
+    offline: bool,
+    // Archives being downloaded or installed from; eviction leaves these alone
+    in_use: Arc<Mutex<HashSet<PathBuf>>>,
 }
 
 impl RuntimeDownloader {
-    pub fn new(base_dir: PathBuf) -> Self {
-        let cache_dir = base_dir.join("cache");
+    pub fn new(cache_dir: PathBuf, offline: bool) -> Self {
         std::fs::create_dir_all(&cache_dir).ok();
 
-        Self { cache_dir }
+        Self { cache_dir, offline, in_use: Arc::new(Mutex::new(HashSet::new())) }
     }
 
+    /// Downloads `url` into the cache (or reuses a verified cached copy). The archive stays
+    /// protected from eviction until the caller hands it back with [`release`](Self::release).
     pub async fn download(&self, url: &str, expected_sha: &str) -> Result<PathBuf> {
         let filename = url.split('/').last()
             .ok_or_else(|| anyhow::anyhow!("Invalid URL"))?;
 
         let dest = self.cache_dir.join(filename);
 
+        self.in_use.lock().unwrap().insert(dest.clone());
+        let result = self.fetch(url, expected_sha, filename, &dest).await;
+        if result.is_err() {
+            self.release(&dest);
+        }
+        result.map(|()| dest)
+    }
+
+    /// Lets the size cap evict an archive again once its install is done.
+    pub fn release(&self, archive: &Path) {
+        self.in_use.lock().unwrap().remove(archive);
+    }
+
+    async fn fetch(&self, url: &str, expected_sha: &str, filename: &str, dest: &Path) -> Result<()> {
         // Check if already downloaded
         if dest.exists() {
-            println!("📦 Using cached file");
-            if self.verify_checksum(&dest, expected_sha)? {
-                return Ok(dest);
+            output::line("📦 Using cached file");
+            if self.check_archive(dest).is_ok() && self.verify_checksum(dest, expected_sha)? {
+                // Mark as recently used so the size cap evicts other archives first
+                std::fs::File::options()
+                    .append(true)
+                    .open(dest)
+                    .and_then(|f| f.set_modified(SystemTime::now()))
+                    .ok();
+                return Ok(());
             } else {
-                println!("⚠️  Cached file corrupted, re-downloading");
-                std::fs::remove_file(&dest)?;
+                output::line("⚠️  Cached file corrupted, re-downloading");
+                std::fs::remove_file(dest)?;
             }
         }
 
+        if self.offline {
+            anyhow::bail!("Offline mode: {} is not in the download cache", filename);
+        }
+
         // Download
-        println!("📥 Downloading from {}...", url);
+        output::line(format!("📥 Downloading from {}...", url));
         
         let response = reqwest::get(url).await?;
+
+        // Error pages come back with a body too; don't save them as the archive
+        let status = response.status();
+        if !status.is_success() {
+            anyhow::bail!("Download failed: {} returned HTTP {}", url, status);
+        }
+        let content_type = response.headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("");
+        if content_type.starts_with("text/") {
+            anyhow::bail!("Download failed: {} returned a {} page, not an archive", url, content_type);
+        }
+
         let total_size = response.content_length().unwrap_or(0);
 
-        let mut file = tokio::fs::File::create(&dest).await?;
+        let mut file = tokio::fs::File::create(dest).await?;
         let mut downloaded = 0u64;
         let mut stream = response.bytes_stream();
 
@@ -52,28 +101,129 @@ impl RuntimeDownloader {
 
             if total_size > 0 {
                 let progress = (downloaded as f64 / total_size as f64) * 100.0;
-                print!("\r📥 Progress: {:.1}% ({} / {} MB)", 
+                output::progress(format!(
+                    "📥 Progress: {:.1}% ({} / {} MB)",
                     progress,
                     downloaded / 1024 / 1024,
                     total_size / 1024 / 1024
-                );
-                use std::io::Write;
-                std::io::stdout().flush().ok();
+                ));
             }
         }
 
-        println!();
+        output::finish_progress();
+        file.flush().await?;
+        drop(file);
+
+        if total_size > 0 && downloaded != total_size {
+            std::fs::remove_file(dest)?;
+            anyhow::bail!(
+                "Download truncated: got {} of {} bytes from {}",
+                downloaded, total_size, url
+            );
+        }
+
+        if let Err(e) = self.check_archive(dest) {
+            std::fs::remove_file(dest)?;
+            return Err(e);
+        }
 
         // Verify checksum
-        if !self.verify_checksum(&dest, expected_sha)? {
-            std::fs::remove_file(&dest)?;
+        if !self.verify_checksum(dest, expected_sha)? {
+            std::fs::remove_file(dest)?;
             anyhow::bail!("Checksum verification failed");
         }
 
-        Ok(dest)
+        self.enforce_cache_limit()?;
+
+        Ok(())
+    }
+
+    /// Cached archives as `(path, size, last used)`, oldest first.
+    pub fn cache_entries(&self) -> Result<Vec<(PathBuf, u64, SystemTime)>> {
+        let mut entries = Vec::new();
+        if !self.cache_dir.exists() {
+            return Ok(entries);
+        }
+
+        for entry in std::fs::read_dir(&self.cache_dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_file() {
+                let used = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                entries.push((entry.path(), metadata.len(), used));
+            }
+        }
+        entries.sort_by_key(|(_, _, used)| *used);
+
+        Ok(entries)
     }
 
-    fn verify_checksum(&self, file: &PathBuf, expected: &str) -> Result<bool> {
+    /// Deletes every cached archive, returning how many files and bytes were freed.
+    pub fn clean_cache(&self) -> Result<(usize, u64)> {
+        let entries = self.cache_entries()?;
+        let freed = entries.iter().map(|(_, size, _)| size).sum();
+        for (path, _, _) in &entries {
+            std::fs::remove_file(path)?;
+        }
+        Ok((entries.len(), freed))
+    }
+
+    /// `PIEBASH_CACHE_MAX_MB` caps the cache; least recently used archives go first.
+    fn enforce_cache_limit(&self) -> Result<()> {
+        let Some(max_mb) = std::env::var("PIEBASH_CACHE_MAX_MB").ok().and_then(|v| v.parse::<u64>().ok()) else {
+            return Ok(());
+        };
+        self.evict_to(max_mb * 1024 * 1024)
+    }
+
+    fn evict_to(&self, limit: u64) -> Result<()> {
+        let entries = self.cache_entries()?;
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        for (path, size, _) in entries {
+            if total <= limit {
+                break;
+            }
+            // Never evict an archive a parallel install is still downloading or unpacking
+            if self.in_use.lock().unwrap().contains(&path) {
+                continue;
+            }
+            std::fs::remove_file(&path)?;
+            output::line(format!("🧹 Evicted {} from cache", path.display()));
+            total -= size;
+        }
+
+        Ok(())
+    }
+
+    /// Fails unless the file starts with the magic bytes its extension promises.
+    fn check_archive(&self, file: &Path) -> Result<()> {
+        let mut header = [0u8; 6];
+        let read = std::fs::File::open(file)?.read(&mut header)?;
+        let header = &header[..read];
+
+        let name = file.to_string_lossy();
+        let (kind, magic): (&str, &[u8]) = if name.ends_with(".zip") {
+            ("zip", b"PK\x03\x04")
+        } else if name.ends_with(".gz") || name.ends_with(".tgz") {
+            ("gzip", &[0x1f, 0x8b])
+        } else if name.ends_with(".xz") {
+            ("xz", &[0xfd, b'7', b'z', b'X', b'Z', 0x00])
+        } else {
+            return Ok(());
+        };
+
+        if !header.starts_with(magic) {
+            anyhow::bail!(
+                "Downloaded file {} is not a valid {} archive (server may have sent an error page)",
+                file.display(),
+                kind
+            );
+        }
+
+        Ok(())
+    }
+
+    fn verify_checksum(&self, file: &Path, expected: &str) -> Result<bool> {
         if expected.is_empty() {
             return Ok(true); // Skip verification if no checksum provided
         }
@@ -86,4 +236,27 @@ impl RuntimeDownloader {
 
         Ok(hash == expected)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eviction_skips_archives_still_in_use() {
+        let dir = tempfile::tempdir().unwrap();
+        let downloader = RuntimeDownloader::new(dir.path().to_path_buf(), true);
+        let installing = dir.path().join("node.tar.gz");
+        let idle = dir.path().join("go.tar.gz");
+        std::fs::write(&installing, b"node").unwrap();
+        std::fs::write(&idle, b"go").unwrap();
+
+        downloader.in_use.lock().unwrap().insert(installing.clone());
+        downloader.evict_to(0).unwrap();
+        assert!(installing.exists());
+        assert!(!idle.exists());
+
+        downloader.release(&installing);
+        downloader.evict_to(0).unwrap();
+        assert!(!installing.exists());
+    }
+}
@@ -1,6 +1,8 @@
 use anyhow::Result;
 use std::path::PathBuf;
 
+use crate::terminal::output;
+
 #[derive(Clone)]  // FIXED: Added Clone
 pub struct RuntimeInstaller {
     base_dir: PathBuf,
@@ -12,7 +14,7 @@ impl RuntimeInstaller {
     }
 
     pub async fn install(&self, archive: &PathBuf, dest: &PathBuf) -> Result<()> {
-        println!("📦 Installing to {}...", dest.display());
+        output::line(format!("📦 Installing to {}...", dest.display()));
 
         std::fs::create_dir_all(dest)?;
 
@@ -27,7 +29,7 @@ impl RuntimeInstaller {
             _ => anyhow::bail!("Unsupported archive type: {}", extension),
         }
 
-        println!("✅ Installation complete");
+        output::line("✅ Installation complete");
 
         Ok(())
     }
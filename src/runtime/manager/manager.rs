@@ -2,21 +2,28 @@ use anyhow::{Context, Result};
 use std::path::PathBuf;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use futures::future::{BoxFuture, FutureExt, Shared, TryFutureExt};
+use tokio::sync::{Mutex, RwLock};
 
 use crate::runtime::downloader::RuntimeDownloader;
 use crate::runtime::installer::RuntimeInstaller;
-use crate::language::registry::LanguageRegistry;
+use crate::language::registry::{LanguageRegistry, LanguageDefinition};
+use crate::utils::error::PieBashError;
+use crate::shell::config::Config;
+use crate::terminal::output;
 
 #[derive(Clone)]
 pub struct RuntimeManager {
-    base_dir: PathBuf,
     registry: Arc<LanguageRegistry>,
     downloader: RuntimeDownloader,
     installer: RuntimeInstaller,
     installed: Arc<RwLock<HashMap<String, RuntimeInfo>>>,
+    // Installs in progress; a second caller for the same language awaits the first one's result
+    in_flight: Arc<Mutex<HashMap<String, InstallFuture>>>,
 }
 
+type InstallFuture = Shared<BoxFuture<'static, Result<RuntimeInfo, Arc<anyhow::Error>>>>;
+
 #[derive(Debug, Clone)]
 pub struct RuntimeInfo {
     pub language: String,
@@ -26,23 +33,20 @@ pub struct RuntimeInfo {
 }
 
 impl RuntimeManager {
-    pub async fn new() -> Result<Self> {
-        let home = dirs::home_dir()
-            .ok_or_else(|| anyhow::anyhow!("Could not determine home directory"))?;
-
-        let base_dir = home.join(".piebash");
-        std::fs::create_dir_all(&base_dir)?;
+    pub async fn new(config: &Config) -> Result<Self> {
+        let paths = crate::platform::adapter::paths();
+        std::fs::create_dir_all(&paths.data_dir)?;
 
         let registry = Arc::new(LanguageRegistry::load()?);
-        let downloader = RuntimeDownloader::new(base_dir.clone());
-        let installer = RuntimeInstaller::new(base_dir.clone());
+        let downloader = RuntimeDownloader::new(paths.cache_dir.clone(), config.offline);
+        let installer = RuntimeInstaller::new(paths.data_dir.clone());
 
         let mut manager = Self {
-            base_dir,
             registry,
             downloader,
             installer,
             installed: Arc::new(RwLock::new(HashMap::new())),
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
         };
 
         // Scan for already installed runtimes
@@ -51,7 +55,39 @@ impl RuntimeManager {
         Ok(manager)
     }
 
+    /// True when `PIEBASH_DRY_RUN` is set: report downloads and installs instead of performing them.
+    pub fn is_dry_run() -> bool {
+        std::env::var("PIEBASH_DRY_RUN")
+            .map(|v| !v.is_empty() && v != "0")
+            .unwrap_or(false)
+    }
+
+    pub fn downloader(&self) -> &RuntimeDownloader {
+        &self.downloader
+    }
+
+    pub fn get_language(&self, language: &str) -> Result<LanguageDefinition> {
+        self.registry.get_language(LanguageRegistry::canonical_name(language))
+    }
+
     pub async fn ensure_runtime(&self, language: &str) -> Result<RuntimeInfo> {
+        let language = LanguageRegistry::canonical_name(language);
+        // An unknown name (`python2`, a typo) fails before anything says "downloading"
+        self.registry.get_language(language)?;
+
+        let manager = self.clone();
+        self.ensure_with(language, move |lang| {
+            async move { manager.install_runtime(&lang).await }.boxed()
+        })
+        .await
+    }
+
+    /// The runtime for `language`, running `install` only when it isn't installed and
+    /// no other caller is already installing it; concurrent callers share one install.
+    async fn ensure_with<F>(&self, language: &str, install: F) -> Result<RuntimeInfo>
+    where
+        F: FnOnce(String) -> BoxFuture<'static, Result<RuntimeInfo>>,
+    {
         // Check if already installed
         {
             let installed = self.installed.read().await;
@@ -60,41 +96,132 @@ impl RuntimeManager {
             }
         }
 
-        // Not installed - download and install
-        println!("📦 {} runtime not found", language);
-        self.install_runtime(language).await
+        let install = {
+            let mut in_flight = self.in_flight.lock().await;
+
+            // Checked again under the lock: an install may have just finished
+            if let Some(info) = self.installed.read().await.get(language) {
+                return Ok(info.clone());
+            }
+
+            if let Some(pending) = in_flight.get(language) {
+                output::line(format!("⏳ {} install already in progress, waiting...", language));
+                pending.clone()
+            } else {
+                // Not installed - download and install
+                output::line(format!("📦 {} runtime not found", language));
+                let pending = install(language.to_string())
+                    .map_err(Arc::new)
+                    .boxed()
+                    .shared();
+                in_flight.insert(language.to_string(), pending.clone());
+                pending
+            }
+        };
+
+        let result = install.clone().await;
+
+        // Done either way; a failed install is forgotten so the next call retries
+        let mut in_flight = self.in_flight.lock().await;
+        if in_flight.get(language).is_some_and(|f| f.ptr_eq(&install)) {
+            in_flight.remove(language);
+        }
+        drop(in_flight);
+
+        // Shared results hand out the error by reference; rebuild the kinds callers match on
+        result.map_err(|e| match e.downcast_ref::<PieBashError>() {
+            Some(PieBashError::RuntimeUnavailable(lang, reason)) => {
+                PieBashError::RuntimeUnavailable(lang.clone(), reason.clone()).into()
+            }
+            _ => anyhow::anyhow!("{:#}", e),
+        })
+    }
+
+    /// Every installed runtime, by language.
+    pub async fn installed_runtimes(&self) -> Vec<RuntimeInfo> {
+        let mut runtimes: Vec<RuntimeInfo> = self.installed.read().await.values().cloned().collect();
+        runtimes.sort_by(|a, b| a.language.cmp(&b.language));
+        runtimes
+    }
+
+    /// Whether `installed` is older than the version the registry pins for it.
+    pub fn is_outdated(&self, installed: &RuntimeInfo) -> Result<bool> {
+        let latest = self.registry.get_language(&installed.language)?.version;
+        Ok(compare_versions(&installed.version, &latest).is_lt())
+    }
+
+    /// Installs the registry's version of `language` and removes the old one. Returns the
+    /// old version and the new runtime, or None when it was already current.
+    pub async fn upgrade_runtime(&self, language: &str) -> Result<Option<(String, RuntimeInfo)>> {
+        let language = LanguageRegistry::canonical_name(language);
+
+        let Some(old) = self.installed.read().await.get(language).cloned() else {
+            anyhow::bail!("{} is not installed (use 'runtime install {}')", language, language);
+        };
+        if !self.is_outdated(&old)? {
+            return Ok(None);
+        }
+
+        // Forget the old runtime so ensure_runtime fetches the new one
+        self.installed.write().await.remove(language);
+        let info = match self.ensure_runtime(language).await {
+            Ok(info) => info,
+            Err(e) => {
+                self.installed.write().await.insert(language.to_string(), old);
+                return Err(e);
+            }
+        };
+
+        if old.path != info.path {
+            std::fs::remove_dir_all(&old.path)
+                .with_context(|| format!("Failed to remove {}", old.path.display()))?;
+        }
+
+        Ok(Some((old.version, info)))
     }
 
     async fn install_runtime(&self, language: &str) -> Result<RuntimeInfo> {
-        println!("📥 Downloading {}...", language);
+        output::line(format!("📥 Downloading {}...", language));
 
         // Get language info from registry
         let lang_def = self.registry.get_language(language)?;
 
         // Detect platform
         let platform = crate::platform::detect_platform();
-        println!("📍 Platform: {}", platform);
+        output::line(format!("📍 Platform: {}", platform));
 
         // Get download URL
-        let download_info = lang_def.get_download_url(&platform)?;
+        let download_info = lang_def.get_download_url(&platform)
+            .map_err(|e| PieBashError::RuntimeUnavailable(language.to_string(), e.to_string()))?;
+
+        let runtime_dir = crate::platform::adapter::paths()
+            .runtimes_dir()
+            .join(format!("{}-{}", language, lang_def.version));
+
+        if Self::is_dry_run() {
+            // One write, so a parallel install can't split the block
+            output::line(format!(
+                "[DRY-RUN] Would install {} {}\n  URL:         {}\n  Destination: {}",
+                language, lang_def.version, download_info.url, runtime_dir.display()
+            ));
+            return Err(PieBashError::RuntimeUnavailable(language.to_string(), "dry run".to_string()).into());
+        }
 
         // Download
         let archive_path = self.downloader
             .download(&download_info.url, &download_info.sha256)
             .await?;
 
-        println!("✅ Download complete");
+        output::line("✅ Download complete");
 
         // Install
-        let runtime_dir = self.base_dir
-            .join("runtimes")
-            .join(format!("{}-{}", language, lang_def.version));
-
-        self.installer
+        let installed = self.installer
             .install(&archive_path, &runtime_dir)
-            .await?;
+            .await;
+        self.downloader.release(&archive_path);
+        installed?;
 
-        println!("✅ {} {} installed to {}", language, lang_def.version, runtime_dir.display());
+        output::line(format!("✅ {} {} installed to {}", language, lang_def.version, runtime_dir.display()));
 
         // Find executable
         let executable = self.find_executable(&runtime_dir, &lang_def.executable)?;
@@ -116,13 +243,13 @@ impl RuntimeManager {
             installed.insert(language.to_string(), info.clone());
         }
 
-        println!("✅ {} ready to use!", language);
+        output::line(format!("✅ {} ready to use!", language));
 
         Ok(info)
     }
 
     async fn scan_installed(&mut self) -> Result<()> {
-        let runtimes_dir = self.base_dir.join("runtimes");
+        let runtimes_dir = crate::platform::adapter::paths().runtimes_dir();
         if !runtimes_dir.exists() {
             return Ok(());
         }
@@ -206,8 +333,111 @@ impl RuntimeManager {
         }
 
         let version = String::from_utf8_lossy(&output.stdout);
-        println!("✓ Verified: {}", version.trim());
+        output::line(format!("✓ Verified: {}", version.trim()));
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Orders dotted versions numerically (`3.9.1` < `3.11.6`); a non-numeric part
+/// compares as text.
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let mut a_parts = a.split(['.', '-']);
+    let mut b_parts = b.split(['.', '-']);
+    loop {
+        match (a_parts.next(), b_parts.next()) {
+            (None, None) => return std::cmp::Ordering::Equal,
+            (None, Some(_)) => return std::cmp::Ordering::Less,
+            (Some(_), None) => return std::cmp::Ordering::Greater,
+            (Some(x), Some(y)) => {
+                let order = match (x.parse::<u64>(), y.parse::<u64>()) {
+                    (Ok(x), Ok(y)) => x.cmp(&y),
+                    _ => x.cmp(y),
+                };
+                if order.is_ne() {
+                    return order;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    fn manager(dir: &std::path::Path) -> RuntimeManager {
+        RuntimeManager {
+            registry: Arc::new(LanguageRegistry::default()),
+            downloader: RuntimeDownloader::new(dir.join("cache"), true),
+            installer: RuntimeInstaller::new(dir.join("data")),
+            installed: Arc::new(RwLock::new(HashMap::new())),
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_ensures_share_one_install() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager(dir.path());
+        let installs = Arc::new(AtomicUsize::new(0));
+
+        let install = |installs: Arc<AtomicUsize>, path: PathBuf| {
+            move |language: String| {
+                async move {
+                    installs.fetch_add(1, Ordering::SeqCst);
+                    // Long enough for the other caller to find this install in flight
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    Ok(RuntimeInfo {
+                        language,
+                        version: "3.12.0".to_string(),
+                        executable: path.join("bin/python3"),
+                        path,
+                    })
+                }
+                .boxed()
+            }
+        };
+
+        let path = dir.path().join("data/python");
+        let (first, second) = tokio::join!(
+            manager.ensure_with("python", install(installs.clone(), path.clone())),
+            manager.ensure_with("python", install(installs.clone(), path.clone())),
+        );
+
+        assert_eq!(installs.load(Ordering::SeqCst), 1);
+        assert_eq!(first.unwrap().path, path);
+        assert_eq!(second.unwrap().path, path);
+        assert!(manager.in_flight.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_install_is_retried_by_the_next_ensure() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager(dir.path());
+
+        let failed = manager
+            .ensure_with("python", |_| async { Err(anyhow::anyhow!("network down")) }.boxed())
+            .await;
+        assert!(failed.is_err());
+
+        let path = dir.path().join("data/python");
+        let retried = manager
+            .ensure_with("python", |language| {
+                let path = path.clone();
+                async move {
+                    Ok(RuntimeInfo {
+                        language,
+                        version: "3.12.0".to_string(),
+                        executable: path.join("bin/python3"),
+                        path,
+                    })
+                }
+                .boxed()
+            })
+            .await;
+        assert_eq!(retried.unwrap().path, path);
+    }
+}
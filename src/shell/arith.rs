@@ -0,0 +1,378 @@
+use anyhow::Result;
+use std::collections::HashMap;
+
+use super::environment::Environment;
+
+/// Where arithmetic reads and assigns variables.
+pub trait ArithVars {
+    fn get(&self, name: &str) -> Option<String>;
+    fn set(&mut self, name: &str, value: i64) -> Result<()>;
+}
+
+impl ArithVars for Environment {
+    fn get(&self, name: &str) -> Option<String> {
+        self.get_var(name)
+    }
+
+    fn set(&mut self, name: &str, value: i64) -> Result<()> {
+        self.set_var(name, &value.to_string())
+    }
+}
+
+/// Read-only view for `$(( ))`, which is expanded before the command runs.
+pub struct ReadOnlyVars<'a>(pub &'a HashMap<String, String>);
+
+impl ArithVars for ReadOnlyVars<'_> {
+    fn get(&self, name: &str) -> Option<String> {
+        self.0.get(name).cloned()
+    }
+
+    fn set(&mut self, name: &str, _value: i64) -> Result<()> {
+        anyhow::bail!("{}: assignment is not supported in $(( )), use (( )) or let", name)
+    }
+}
+
+/// Evaluates a shell arithmetic expression (bash's integer operators, with
+/// assignment, `++`/`--`, `?:` and `,`). Unset or empty variables count as 0.
+pub fn eval(expr: &str, vars: &mut dyn ArithVars) -> Result<i64> {
+    let tokens = tokenize(expr)?;
+    if tokens.is_empty() {
+        return Ok(0);
+    }
+
+    let mut parser = Parser { tokens: &tokens, pos: 0 };
+    let ast = parser.comma()?;
+    if let Some(token) = parser.tokens.get(parser.pos) {
+        anyhow::bail!("{}: syntax error in expression (error token is \"{}\")", expr.trim(), token);
+    }
+
+    ast.eval(vars)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(i64),
+    Ident(String),
+    Op(&'static str),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Num(n) => write!(f, "{}", n),
+            Token::Ident(name) => write!(f, "{}", name),
+            Token::Op(op) => write!(f, "{}", op),
+        }
+    }
+}
+
+// Longest first, so `<<=` isn't read as `<<` then `=`
+const OPERATORS: &[&str] = &[
+    "<<=", ">>=", "**", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||",
+    "+=", "-=", "*=", "/=", "%=", "&=", "^=", "|=",
+    "+", "-", "*", "/", "%", "<", ">", "=", "!", "~", "&", "^", "|", "?", ":", ",", "(", ")",
+];
+
+fn tokenize(expr: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = expr.trim_start();
+
+    while let Some(ch) = rest.chars().next() {
+        if ch.is_ascii_digit() {
+            let end = rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+            tokens.push(Token::Num(parse_number(&rest[..end])?));
+            rest = &rest[end..];
+        } else if ch.is_alphabetic() || ch == '_' || ch == '$' {
+            // `$x` and `x` are the same variable here
+            let name = rest.trim_start_matches('$');
+            let end = name.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(name.len());
+            if end == 0 {
+                anyhow::bail!("{}: syntax error: operand expected", expr.trim());
+            }
+            tokens.push(Token::Ident(name[..end].to_string()));
+            rest = &name[end..];
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        } else {
+            anyhow::bail!("{}: syntax error: invalid arithmetic operator (error token is \"{}\")", expr.trim(), rest);
+        }
+        rest = rest.trim_start();
+    }
+
+    Ok(tokens)
+}
+
+/// `42`, `0x2a` or `052` (octal), as bash reads them.
+fn parse_number(text: &str) -> Result<i64> {
+    let parsed = if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16)
+    } else if text.len() > 1 && text.starts_with('0') {
+        i64::from_str_radix(&text[1..], 8)
+    } else {
+        text.parse()
+    };
+    parsed.map_err(|_| anyhow::anyhow!("{}: value too great for base (error token is \"{}\")", text, text))
+}
+
+#[derive(Debug)]
+enum Expr {
+    Num(i64),
+    Var(String),
+    Unary(&'static str, Box<Expr>),
+    /// `++x` / `--x` (prefix) and `x++` / `x--` (postfix)
+    Step { name: String, delta: i64, prefix: bool },
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    /// `=` or a compound `op=`; `op` is the binary operator, if any
+    Assign { name: String, op: Option<&'static str>, value: Box<Expr> },
+    Ternary(Box<Expr>, Box<Expr>, Box<Expr>),
+    Comma(Box<Expr>, Box<Expr>),
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+// Binary operators from loosest to tightest binding, below `?:`
+const LEVELS: &[&[&str]] = &[
+    &["||"],
+    &["&&"],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["==", "!="],
+    &["<", "<=", ">", ">="],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+impl Parser<'_> {
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, op: &str) -> Result<()> {
+        if self.peek_op() == Some(op) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            anyhow::bail!("syntax error: `{}' expected", op)
+        }
+    }
+
+    fn comma(&mut self) -> Result<Expr> {
+        let mut left = self.assignment()?;
+        while self.peek_op() == Some(",") {
+            self.pos += 1;
+            left = Expr::Comma(Box::new(left), Box::new(self.assignment()?));
+        }
+        Ok(left)
+    }
+
+    fn assignment(&mut self) -> Result<Expr> {
+        if let (Some(Token::Ident(name)), Some(Token::Op(op))) = (self.tokens.get(self.pos), self.tokens.get(self.pos + 1)) {
+            if op.ends_with('=') && !matches!(*op, "==" | "!=" | "<=" | ">=") {
+                self.pos += 2;
+                let binary = OPERATORS.iter().find(|o| o.len() + 1 == op.len() && op.starts_with(**o)).copied();
+                return Ok(Expr::Assign {
+                    name: name.clone(),
+                    op: binary,
+                    value: Box::new(self.assignment()?),
+                });
+            }
+        }
+        self.ternary()
+    }
+
+    fn ternary(&mut self) -> Result<Expr> {
+        let cond = self.binary(0)?;
+        if self.peek_op() != Some("?") {
+            return Ok(cond);
+        }
+        self.pos += 1;
+        let then = self.assignment()?;
+        self.expect(":")?;
+        let otherwise = self.assignment()?;
+        Ok(Expr::Ternary(Box::new(cond), Box::new(then), Box::new(otherwise)))
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr> {
+        if level == LEVELS.len() {
+            return self.power();
+        }
+        let mut left = self.binary(level + 1)?;
+        while let Some(op) = self.peek_op().filter(|op| LEVELS[level].contains(op)) {
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.binary(level + 1)?));
+        }
+        Ok(left)
+    }
+
+    /// `**` binds tighter than `*` and groups to the right.
+    fn power(&mut self) -> Result<Expr> {
+        let base = self.unary()?;
+        if self.peek_op() == Some("**") {
+            self.pos += 1;
+            return Ok(Expr::Binary("**", Box::new(base), Box::new(self.power()?)));
+        }
+        Ok(base)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        match self.peek_op() {
+            Some(op @ ("++" | "--")) => {
+                self.pos += 1;
+                match self.tokens.get(self.pos) {
+                    Some(Token::Ident(name)) => {
+                        self.pos += 1;
+                        Ok(Expr::Step { name: name.clone(), delta: if op == "++" { 1 } else { -1 }, prefix: true })
+                    }
+                    _ => anyhow::bail!("syntax error: variable expected after `{}'", op),
+                }
+            }
+            Some(op @ ("!" | "~" | "-" | "+")) => {
+                self.pos += 1;
+                Ok(Expr::Unary(op, Box::new(self.unary()?)))
+            }
+            _ => self.postfix(),
+        }
+    }
+
+    fn postfix(&mut self) -> Result<Expr> {
+        let primary = self.primary()?;
+        if let Expr::Var(name) = &primary {
+            if let Some(op @ ("++" | "--")) = self.peek_op() {
+                self.pos += 1;
+                return Ok(Expr::Step { name: name.clone(), delta: if op == "++" { 1 } else { -1 }, prefix: false });
+            }
+        }
+        Ok(primary)
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        match token {
+            Some(Token::Num(n)) => Ok(Expr::Num(n)),
+            Some(Token::Ident(name)) => Ok(Expr::Var(name)),
+            Some(Token::Op("(")) => {
+                let inner = self.comma()?;
+                self.expect(")")?;
+                Ok(inner)
+            }
+            Some(other) => anyhow::bail!("syntax error: operand expected (error token is \"{}\")", other),
+            None => anyhow::bail!("syntax error: operand expected"),
+        }
+    }
+}
+
+impl Expr {
+    fn eval(&self, vars: &mut dyn ArithVars) -> Result<i64> {
+        match self {
+            Expr::Num(n) => Ok(*n),
+            Expr::Var(name) => read_var(name, vars),
+            Expr::Unary(op, inner) => {
+                let value = inner.eval(vars)?;
+                Ok(match *op {
+                    "!" => (value == 0) as i64,
+                    "~" => !value,
+                    "-" => value.wrapping_neg(),
+                    _ => value,
+                })
+            }
+            Expr::Step { name, delta, prefix } => {
+                let old = read_var(name, vars)?;
+                let new = old.wrapping_add(*delta);
+                vars.set(name, new)?;
+                Ok(if *prefix { new } else { old })
+            }
+            // Short-circuit: the right side's assignments only happen when it's evaluated
+            Expr::Binary("&&", left, right) => {
+                Ok((left.eval(vars)? != 0 && right.eval(vars)? != 0) as i64)
+            }
+            Expr::Binary("||", left, right) => {
+                Ok((left.eval(vars)? != 0 || right.eval(vars)? != 0) as i64)
+            }
+            Expr::Binary(op, left, right) => {
+                let left = left.eval(vars)?;
+                let right = right.eval(vars)?;
+                apply(op, left, right)
+            }
+            Expr::Assign { name, op, value } => {
+                let value = value.eval(vars)?;
+                let result = match op {
+                    Some(op) => apply(op, read_var(name, vars)?, value)?,
+                    None => value,
+                };
+                vars.set(name, result)?;
+                Ok(result)
+            }
+            Expr::Ternary(cond, then, otherwise) => {
+                if cond.eval(vars)? != 0 {
+                    then.eval(vars)
+                } else {
+                    otherwise.eval(vars)
+                }
+            }
+            Expr::Comma(left, right) => {
+                left.eval(vars)?;
+                right.eval(vars)
+            }
+        }
+    }
+}
+
+/// A variable's value; like bash, its text may itself be an expression.
+fn read_var(name: &str, vars: &mut dyn ArithVars) -> Result<i64> {
+    match vars.get(name) {
+        None => Ok(0),
+        Some(text) if text.trim().is_empty() => Ok(0),
+        Some(text) => match text.trim().parse::<i64>() {
+            Ok(n) => Ok(n),
+            Err(_) => eval(&text, &mut ReadOnlyVarsOf(vars)),
+        },
+    }
+}
+
+/// Lets a variable's expression read other variables without assigning any.
+struct ReadOnlyVarsOf<'a>(&'a mut dyn ArithVars);
+
+impl ArithVars for ReadOnlyVarsOf<'_> {
+    fn get(&self, name: &str) -> Option<String> {
+        self.0.get(name)
+    }
+
+    fn set(&mut self, name: &str, _value: i64) -> Result<()> {
+        anyhow::bail!("{}: assignment inside a variable's value is not supported", name)
+    }
+}
+
+fn apply(op: &str, left: i64, right: i64) -> Result<i64> {
+    Ok(match op {
+        "+" => left.wrapping_add(right),
+        "-" => left.wrapping_sub(right),
+        "*" => left.wrapping_mul(right),
+        "/" | "%" if right == 0 => anyhow::bail!("division by 0"),
+        "/" => left.wrapping_div(right),
+        "%" => left.wrapping_rem(right),
+        "**" if right < 0 => anyhow::bail!("exponent less than 0"),
+        "**" => left.wrapping_pow(right.min(u32::MAX as i64) as u32),
+        "<<" => left.wrapping_shl(right as u32),
+        ">>" => left.wrapping_shr(right as u32),
+        "&" => left & right,
+        "|" => left | right,
+        "^" => left ^ right,
+        "<" => (left < right) as i64,
+        "<=" => (left <= right) as i64,
+        ">" => (left > right) as i64,
+        ">=" => (left >= right) as i64,
+        "==" => (left == right) as i64,
+        "!=" => (left != right) as i64,
+        _ => anyhow::bail!("{}: unknown operator", op),
+    })
+}
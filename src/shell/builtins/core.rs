@@ -3,40 +3,178 @@ use colored::*;
 
 
 use crate::shell::parser::Command;
-use crate::shell::environment::Environment;
+use crate::shell::environment::{Environment, VarAttributes, SHELL_OPTIONS, SHOPT_OPTIONS};
+use crate::shell::arith;
+use crate::shell::resolve::{self, Resolution};
+use crate::utils::error::PieBashError;
+use crate::platform;
+use super::utils::interpret_escapes;
+use super::Builtins;
 
 pub fn cd(command: &Command, env: &mut Environment) -> Result<()> {
-    let target = if command.args.is_empty() {
-        env.get_home_dir()
-    } else {
-        let path = &command.args[0];
-        if path == "~" {
-            env.get_home_dir()
-        } else if path.starts_with("~/") {
-            env.get_home_dir().join(&path[2..])
-        } else if path.starts_with('/') || (path.len() > 1 && path.chars().nth(1) == Some(':')) {
+    let target = cd_target(command.args.first(), env)?;
+    env.set_cwd(target)?;
+    Ok(())
+}
+
+/// The directory `cd path` goes to; home when there's no path.
+fn cd_target(path: Option<&String>, env: &Environment) -> Result<std::path::PathBuf> {
+    let target = match path {
+        None => env.get_home_dir(),
+        Some(path) if path == "~" => env.get_home_dir(),
+        Some(path) if path.starts_with("~/") => env.get_home_dir().join(&path[2..]),
+        Some(path) if path.starts_with('/') || (path.len() > 1 && path.chars().nth(1) == Some(':')) => {
             std::path::PathBuf::from(path)
-        } else if path == ".." {
-            env.get_cwd()
-                .parent()
-                .ok_or_else(|| anyhow::anyhow!("cd: already at root"))?
-                .to_path_buf()
-        } else if path == "." {
-            env.get_cwd().clone()
-        } else {
-            env.get_cwd().join(path)
         }
+        Some(path) if path == ".." => env.get_cwd()
+            .parent()
+            .ok_or_else(|| anyhow::anyhow!("cd: already at root"))?
+            .to_path_buf(),
+        Some(path) if path == "." => env.get_cwd().clone(),
+        Some(path) => env.get_cwd().join(path),
     };
 
     if !target.exists() {
         anyhow::bail!("cd: no such file or directory: {}", target.display());
     }
 
-    if !target.is_dir() {
-        anyhow::bail!("cd: not a directory: {}", target.display());
+    let target = if target.is_dir() {
+        target
+    } else {
+        let parent = target.parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_else(|| env.get_cwd().clone());
+
+        // Opt-in convenience: `cd some/file.txt` lands in `some/`
+        if env.get_var("PIEBASH_CD_FILE_PARENT").is_some_and(|v| !v.is_empty() && v != "0") {
+            parent
+        } else {
+            anyhow::bail!(
+                "cd: not a directory: {} (did you mean 'cd {}'?)",
+                target.display(),
+                parent.display()
+            );
+        }
+    };
+
+    Ok(target)
+}
+
+/// A `+N` / `-N` operand of `pushd`, `popd` or `dirs` as an index into the stack:
+/// `+N` counts from the left of `dirs`' listing, starting at 0, `-N` from the right.
+fn stack_index(name: &str, arg: &str, len: usize) -> Result<Option<usize>> {
+    let (from_right, digits) = match (arg.strip_prefix('+'), arg.strip_prefix('-')) {
+        (Some(digits), _) => (false, digits),
+        (_, Some(digits)) => (true, digits),
+        _ => return Ok(None),
+    };
+    let Ok(n) = digits.parse::<usize>() else {
+        return Ok(None);
+    };
+    if n >= len {
+        anyhow::bail!("{}: {}: directory stack index out of range", name, arg);
     }
+    Ok(Some(if from_right { len - 1 - n } else { n }))
+}
 
-    env.set_cwd(target)?;
+/// `/home/me/src` as `~/src`, as bash lists the stack unless given `-l`.
+fn tilde_path(path: &std::path::Path, env: &Environment) -> String {
+    match path.strip_prefix(env.get_home_dir()) {
+        Ok(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Ok(rest) => format!("~/{}", rest.display()),
+        Err(_) => path.display().to_string(),
+    }
+}
+
+fn print_dir_stack(env: &Environment) {
+    let stack: Vec<String> = env.get_dir_stack().iter().map(|dir| tilde_path(dir, env)).collect();
+    println!("{}", stack.join(" "));
+}
+
+/// `pushd dir` changes to `dir`, keeping the old directory on the stack; `pushd`
+/// alone swaps the top two, and `pushd +N` / `-N` rotates entry N to the top.
+pub fn pushd(command: &Command, env: &mut Environment) -> Result<()> {
+    let mut stack = env.get_dir_stack();
+    match command.args.first() {
+        None => {
+            if stack.len() < 2 {
+                anyhow::bail!("pushd: no other directory");
+            }
+            stack.swap(0, 1);
+        }
+        Some(arg) => match stack_index("pushd", arg, stack.len())? {
+            Some(n) => stack.rotate_left(n),
+            None => stack.insert(0, cd_target(Some(arg), env)?),
+        },
+    }
+
+    env.set_dir_stack(stack)?;
+    print_dir_stack(env);
+    Ok(())
+}
+
+/// `popd` drops the top of the stack and changes to the next entry; `popd +N` /
+/// `-N` drops entry N instead.
+pub fn popd(command: &Command, env: &mut Environment) -> Result<()> {
+    let mut stack = env.get_dir_stack();
+    if stack.len() < 2 {
+        anyhow::bail!("popd: directory stack empty");
+    }
+    let index = match command.args.first() {
+        None => 0,
+        Some(arg) => stack_index("popd", arg, stack.len())?
+            .ok_or_else(|| anyhow::anyhow!("popd: {}: invalid argument", arg))?,
+    };
+
+    stack.remove(index);
+    env.set_dir_stack(stack)?;
+    print_dir_stack(env);
+    Ok(())
+}
+
+/// `dirs [-clpv] [+N | -N]`: shows the directory stack. `-v` numbers it, one per
+/// line, `-p` lists it one per line, `-l` keeps full paths, `-c` clears it.
+pub fn dirs(command: &Command, env: &mut Environment) -> Result<()> {
+    let (mut clear, mut long, mut per_line, mut numbered) = (false, false, false, false);
+    let mut index = None;
+    for arg in &command.args {
+        let stack_len = env.get_dir_stack().len();
+        if let Some(n) = stack_index("dirs", arg, stack_len)? {
+            index = Some(n);
+            continue;
+        }
+        for flag in arg.strip_prefix('-').unwrap_or(arg).chars() {
+            match flag {
+                'c' => clear = true,
+                'l' => long = true,
+                'p' => per_line = true,
+                'v' => numbered = true,
+                _ => anyhow::bail!("dirs: {}: invalid option (usage: dirs [-clpv] [+N | -N])", arg),
+            }
+        }
+    }
+
+    if clear {
+        let cwd = env.get_cwd().clone();
+        return env.set_dir_stack(vec![cwd]);
+    }
+
+    let stack = env.get_dir_stack();
+    let show = |dir: &std::path::PathBuf| if long { dir.display().to_string() } else { tilde_path(dir, env) };
+    if let Some(n) = index {
+        println!("{}", show(&stack[n]));
+    } else if numbered {
+        for (n, dir) in stack.iter().enumerate() {
+            println!("{:>2}  {}", n, show(dir));
+        }
+    } else if per_line {
+        for dir in &stack {
+            println!("{}", show(dir));
+        }
+    } else {
+        let listing: Vec<String> = stack.iter().map(show).collect();
+        println!("{}", listing.join(" "));
+    }
     Ok(())
 }
 
@@ -46,44 +184,223 @@ pub fn pwd(env: &Environment) -> Result<()> {
 }
 
 pub fn echo(command: &Command) -> Result<()> {
-    let no_newline = command.args.contains(&"-n".to_string());
-    let args: Vec<&String> = command.args.iter().filter(|a| *a != "-n" && *a != "-e").collect();
-    
-    let output = args.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(" ");
-    
-    if no_newline {
-        print!("{}", output);
-    } else {
-        println!("{}", output);
-    }
+    print!("{}", echo_output(command));
     Ok(())
 }
 
-pub fn export(command: &Command, env: &mut Environment) -> Result<()> {
-    if command.args.is_empty() {
-        let mut vars: Vec<_> = env.get_all_vars().iter().collect();
-        vars.sort_by_key(|(k, _)| *k);
-        for (key, value) in vars {
-            println!("export {}={}", key, value);
+/// What `echo` writes, wherever it's going: leading `-n`, `-e` and `-E` words (alone or
+/// combined, as in `-ne`) are options, and with `-e` backslash escapes are interpreted.
+pub fn echo_output(command: &Command) -> String {
+    let mut newline = true;
+    let mut escapes = false;
+    let mut words = command.args.as_slice();
+
+    while let Some(flags) = words.first().and_then(|w| w.strip_prefix('-')) {
+        if flags.is_empty() || !flags.chars().all(|c| "neE".contains(c)) {
+            break;
+        }
+        for flag in flags.chars() {
+            match flag {
+                'n' => newline = false,
+                'e' => escapes = true,
+                _ => escapes = false,
+            }
+        }
+        words = &words[1..];
+    }
+
+    let mut output = words.join(" ");
+    if escapes {
+        // `\c` ends the output, newline included
+        if let Some(pos) = output.find("\\c") {
+            return interpret_escapes(&output[..pos]);
         }
+        output = interpret_escapes(&output);
+    }
+    if newline {
+        output.push('\n');
+    }
+    output
+}
+
+/// `export [-p] [name[=value]...]`. With no names (or `-p`), lists the exported variables.
+pub fn export(command: &Command, env: &mut Environment) -> Result<()> {
+    if is_export_listing(command) {
+        print!("{}", export_listing(env));
     } else {
         for arg in &command.args {
             if let Some(pos) = arg.find('=') {
                 let key = &arg[..pos];
                 let value = &arg[pos + 1..];
-                env.set_var(key, value);
+                env.set_var(key, value)?;
                 std::env::set_var(key, value);
+                mark_exported(key, env);
             } else {
                 // export existing var
                 if let Some(val) = env.get_var(arg) {
                     std::env::set_var(arg, val);
                 }
+                mark_exported(arg, env);
             }
         }
     }
     Ok(())
 }
 
+pub fn is_export_listing(command: &Command) -> bool {
+    command.args.is_empty() || command.args == ["-p"]
+}
+
+/// The exported variables as `export` commands, quoted so sourcing them restores the values.
+pub fn export_listing(env: &Environment) -> String {
+    // Names the shell couldn't read back (inherited ones like `a.b`) are left out
+    let valid = |key: &str| !key.starts_with(|c: char| c.is_ascii_digit()) && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let mut vars: Vec<_> = env.get_all_vars().iter()
+        .filter(|(key, _)| env.get_attributes(key).exported && valid(key))
+        .collect();
+    vars.sort_by_key(|(k, _)| *k);
+    vars.iter().map(|(key, value)| format!("export {}={}\n", key, shell_quote(value))).collect()
+}
+
+fn mark_exported(name: &str, env: &mut Environment) {
+    let attributes = env.get_attributes(name);
+    env.set_attributes(name, VarAttributes { exported: true, ..attributes });
+}
+
+/// `declare [-irxp] [+ix] [NAME[=VALUE]...]` (also `typeset`, and `readonly` as `declare -r`):
+/// set variable attributes, or list variables with theirs.
+pub fn declare(command: &Command, env: &mut Environment) -> Result<()> {
+    let name = command.name.as_str();
+    let mut set = VarAttributes { readonly: name == "readonly", ..Default::default() };
+    let mut clear = VarAttributes::default();
+    let mut array = false;
+    let mut print = false;
+    let mut names = Vec::new();
+
+    for arg in &command.args {
+        let (on, flags) = match arg.chars().next() {
+            Some(sign @ ('-' | '+')) if names.is_empty() && arg.len() > 1 => (sign == '-', &arg[1..]),
+            _ => {
+                names.push(arg);
+                continue;
+            }
+        };
+        let target = if on { &mut set } else { &mut clear };
+        for flag in flags.chars() {
+            match flag {
+                'a' if on => array = true,
+                'a' => anyhow::bail!("{}: +a: cannot destroy an array this way, use unset", name),
+                'i' => target.integer = true,
+                'r' if on => target.readonly = true,
+                'r' => anyhow::bail!("{}: +r: a readonly variable cannot be made writable", name),
+                'x' => target.exported = true,
+                'p' => print = true,
+                _ => anyhow::bail!("{}: -{}: invalid option (supported: -a -i -r -x -p)", name, flag),
+            }
+        }
+    }
+
+    if names.is_empty() {
+        let listed = array || set != VarAttributes::default() || name == "readonly";
+        let mut vars: Vec<_> = env.get_all_vars().keys().chain(env.get_arrays().keys()).cloned().collect();
+        vars.sort();
+        for var in vars {
+            let attributes = env.get_attributes(&var);
+            let matches = (!array || env.get_array(&var).is_some())
+                && (!set.integer || attributes.integer)
+                && (!set.readonly || attributes.readonly)
+                && (!set.exported || attributes.exported);
+            if !listed || matches {
+                print_declaration(&var, env);
+            }
+        }
+        return Ok(());
+    }
+
+    for arg in names {
+        let (var, value) = match arg.split_once('=') {
+            Some((var, value)) => (var, Some(value)),
+            None => (arg.as_str(), None),
+        };
+
+        if print {
+            if env.get_var(var).is_none() && env.get_array(var).is_none() {
+                anyhow::bail!("{}: {}: not found", name, var);
+            }
+            print_declaration(var, env);
+            continue;
+        }
+
+        if var.is_empty() || var.starts_with(|c: char| c.is_ascii_digit()) || !var.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            anyhow::bail!("{}: `{}': not a valid identifier", name, arg);
+        }
+
+        let old = env.get_attributes(var);
+        if old.readonly && (value.is_some() || (set.integer && !old.integer)) {
+            anyhow::bail!("{}: {}: readonly variable", name, var);
+        }
+
+        // -i applies to the value being assigned; -r only after it's in place
+        let attributes = VarAttributes {
+            integer: (old.integer || set.integer) && !clear.integer,
+            readonly: old.readonly,
+            exported: (old.exported || set.exported) && !clear.exported,
+        };
+        env.set_attributes(var, attributes);
+        if array && env.get_array(var).is_none() {
+            let first = env.get_var(var);
+            env.set_array(var, first.into_iter().collect())?;
+        }
+        if let Some(value) = value {
+            env.set_var(var, value).map_err(|e| anyhow::anyhow!("{}: {}", name, e))?;
+        }
+        env.set_attributes(var, VarAttributes { readonly: attributes.readonly || set.readonly, ..attributes });
+
+        match env.get_var(var) {
+            Some(value) if attributes.exported => std::env::set_var(var, value),
+            _ if clear.exported => std::env::remove_var(var),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn print_declaration(var: &str, env: &Environment) {
+    let attributes = env.get_attributes(var);
+    let mut flags = String::new();
+    let array = env.get_array(var);
+    if array.is_some() {
+        flags.push('a');
+    }
+    if attributes.integer {
+        flags.push('i');
+    }
+    if attributes.readonly {
+        flags.push('r');
+    }
+    if attributes.exported {
+        flags.push('x');
+    }
+    if flags.is_empty() {
+        flags.push('-');
+    }
+
+    if let Some(array) = array {
+        let elements: Vec<String> = array
+            .iter()
+            .map(|(index, value)| format!("[{}]={}", index, shell_quote(value)))
+            .collect();
+        println!("declare -{} {}=({})", flags, var, elements.join(" "));
+        return;
+    }
+
+    match env.get_var(var) {
+        Some(value) => println!("declare -{} {}={}", flags, var, shell_quote(&value)),
+        None => println!("declare -{} {}", flags, var),
+    }
+}
+
 pub fn env_cmd(environment: &Environment) -> Result<()> {
     let mut vars: Vec<_> = environment.get_all_vars().iter().collect();
     vars.sort_by_key(|(k, _)| *k);
@@ -93,21 +410,262 @@ pub fn env_cmd(environment: &Environment) -> Result<()> {
     Ok(())
 }
 
+pub fn printenv(command: &Command, environment: &Environment) -> Result<()> {
+    if command.args.is_empty() {
+        return env_cmd(environment);
+    }
+
+    // Unset prints nothing but fails, so scripts can tell unset from empty
+    let mut all_set = true;
+    for name in &command.args {
+        match environment.get_var(name) {
+            Some(value) => println!("{}", value),
+            None => all_set = false,
+        }
+    }
+
+    if !all_set {
+        return Err(PieBashError::ExitStatus(1).into());
+    }
+    Ok(())
+}
+
 pub fn set_cmd(command: &Command, env: &mut Environment) -> Result<()> {
     if command.args.is_empty() {
-        // Show all variables
+        // Show all variables, quoted so the output can be pasted back in
         let mut vars: Vec<_> = env.get_all_vars().iter().collect();
         vars.sort_by_key(|(k, _)| *k);
         for (key, value) in vars {
-            println!("{}={}", key, value);
+            println!("{}={}", key, shell_quote(value));
+        }
+        return Ok(());
+    }
+
+    let mut args = command.args.iter();
+    while let Some(arg) = args.next() {
+        // `set -- a b` (or just `set a b`) replaces $1, $2, ...
+        if arg == "--" || !(arg.starts_with('-') || arg.starts_with('+')) {
+            let skip = usize::from(arg == "--");
+            let rest = std::iter::once(arg).chain(args).skip(skip).cloned().collect();
+            env.set_positional(rest);
+            break;
+        }
+
+        // `-x` turns an option on, `+x` turns it off
+        let on = arg.starts_with('-');
+
+        for flag in arg.chars().skip(1) {
+            match flag {
+                'e' => env.set_option("errexit", on),
+                'C' => env.set_option("noclobber", on),
+                'f' => env.set_option("noglob", on),
+                'x' => env.set_option("xtrace", on),
+                'o' => match args.next() {
+                    Some(name) if SHELL_OPTIONS.contains(&name.as_str()) => env.set_option(name, on),
+                    // `shopt` options are accepted here too, e.g. `set -o nocaseglob`
+                    Some(name) if SHOPT_OPTIONS.contains(&name.as_str()) => env.set_option(name, on),
+                    Some(name) => anyhow::bail!("set: {}: invalid option name", name),
+                    None => {
+                        for name in SHELL_OPTIONS {
+                            let state = if env.has_option(name) { "on" } else { "off" };
+                            println!("{:<15} {}", name, state);
+                        }
+                    }
+                },
+                _ => anyhow::bail!("set: {}{}: invalid option", if on { '-' } else { '+' }, flag),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `shopt [-s|-u] [-pq] [optname...]`: turn `shopt` options on or off, or show them.
+/// Showing a single option also reports it in the exit status, 0 meaning on.
+pub fn shopt_cmd(command: &Command, env: &mut Environment) -> Result<()> {
+    let mut set = None;
+    let mut print = false;
+    let mut quiet = false;
+    let mut names = Vec::new();
+
+    for arg in &command.args {
+        match arg.as_str() {
+            "-s" => set = Some(true),
+            "-u" => set = Some(false),
+            "-p" => print = true,
+            "-q" => quiet = true,
+            _ if arg.starts_with('-') => anyhow::bail!("shopt: {}: invalid option", arg),
+            _ if SHOPT_OPTIONS.contains(&arg.as_str()) => names.push(arg.as_str()),
+            _ => anyhow::bail!("shopt: {}: invalid shell option name", arg),
+        }
+    }
+
+    if let Some(on) = set {
+        if names.is_empty() {
+            anyhow::bail!("shopt: usage: shopt [-s|-u] optname...");
+        }
+        for name in names {
+            env.set_option(name, on);
+        }
+        return Ok(());
+    }
+
+    let shown = if names.is_empty() { SHOPT_OPTIONS.to_vec() } else { names.clone() };
+    for name in shown.iter().filter(|_| !quiet) {
+        let on = env.has_option(name);
+        if print {
+            println!("shopt {} {}", if on { "-s" } else { "-u" }, name);
+        } else {
+            println!("{:<15} {}", name, if on { "on" } else { "off" });
+        }
+    }
+
+    if !names.is_empty() && !names.iter().all(|name| env.has_option(name)) {
+        return Err(PieBashError::ExitStatus(1).into());
+    }
+    Ok(())
+}
+
+/// `getopts optstring name [arg...]`, as in POSIX: each call stores the next option
+/// from the args (default: the positional parameters) in `$name`, its argument in
+/// `$OPTARG`, and the index of the next arg to look at in `$OPTIND`.
+///
+/// A letter followed by `:` in optstring takes an argument. A leading `:` turns off
+/// error messages: `$name` becomes `?` (unknown option) or `:` (missing argument) and
+/// `$OPTARG` the offending letter. Exit status is 1 once the options run out.
+pub fn getopts(command: &Command, env: &mut Environment) -> Result<()> {
+    if command.args.len() < 2 {
+        anyhow::bail!("getopts: usage: getopts optstring name [arg ...]");
+    }
+
+    let spec = command.args[0].as_str();
+    let name = command.args[1].as_str();
+    let args: Vec<String> = if command.args.len() > 2 {
+        command.args[2..].to_vec()
+    } else {
+        env.get_positional().to_vec()
+    };
+    let (silent, spec) = match spec.strip_prefix(':') {
+        Some(rest) => (true, rest),
+        None => (false, spec),
+    };
+
+    let optind = env.get_var("OPTIND").and_then(|v| v.parse::<usize>().ok()).unwrap_or(1).max(1);
+    // A reset OPTIND (or a new word) starts at the word's first letter again
+    let offset = match env.getopts_pos() {
+        (at, offset) if at == optind => offset,
+        _ => 1,
+    };
+
+    let word = match args.get(optind - 1) {
+        Some(word) if word == "--" => {
+            env.set_var("OPTIND", &(optind + 1).to_string())?;
+            None
+        }
+        Some(word) if word.len() > 1 && word.starts_with('-') => Some(word.as_str()),
+        _ => None,
+    };
+    let Some(word) = word else {
+        env.set_var(name, "?")?;
+        env.unset_var("OPTARG")?;
+        return Err(PieBashError::ExitStatus(1).into());
+    };
+
+    let letters: Vec<char> = word.chars().collect();
+    let offset = offset.min(letters.len() - 1);
+    let letter = letters[offset];
+    let rest: String = letters[offset + 1..].iter().collect();
+
+    // Where the next call starts, unless an argument consumes more
+    let (mut next_ind, mut next_offset) = if rest.is_empty() { (optind + 1, 1) } else { (optind, offset + 1) };
+
+    match spec.find(letter).filter(|_| letter != ':') {
+        None => {
+            if silent {
+                env.set_var("OPTARG", &letter.to_string())?;
+            } else {
+                eprintln!("getopts: illegal option -- {}", letter);
+                env.unset_var("OPTARG")?;
+            }
+            env.set_var(name, "?")?;
+        }
+        Some(at) if spec[at + 1..].starts_with(':') => {
+            // `-fvalue` or `-f value`
+            let (value, consumed) = if !rest.is_empty() {
+                (Some(rest), 1)
+            } else {
+                match args.get(optind) {
+                    Some(next) => (Some(next.clone()), 2),
+                    None => (None, 1),
+                }
+            };
+            next_ind = optind + consumed;
+            next_offset = 1;
+
+            match value {
+                Some(value) => {
+                    env.set_var("OPTARG", &value)?;
+                    env.set_var(name, &letter.to_string())?;
+                }
+                None if silent => {
+                    env.set_var("OPTARG", &letter.to_string())?;
+                    env.set_var(name, ":")?;
+                }
+                None => {
+                    eprintln!("getopts: option requires an argument -- {}", letter);
+                    env.unset_var("OPTARG")?;
+                    env.set_var(name, "?")?;
+                }
+            }
+        }
+        Some(_) => {
+            env.unset_var("OPTARG")?;
+            env.set_var(name, &letter.to_string())?;
         }
     }
+
+    env.set_var("OPTIND", &next_ind.to_string())?;
+    env.set_getopts_pos(next_ind, next_offset);
     Ok(())
 }
 
+/// `let expr...` and `(( expr ))`: evaluate arithmetic, assigning as they go. Like bash,
+/// the exit status is 0 when the last value is non-zero and 1 when it is zero.
+pub fn let_cmd(command: &Command, env: &mut Environment) -> Result<()> {
+    if command.args.is_empty() {
+        anyhow::bail!("let: expression expected");
+    }
+
+    let mut last = 0;
+    for expr in &command.args {
+        last = arith::eval(expr, env).map_err(|e| anyhow::anyhow!("{}: {}", command.name, e))?;
+    }
+
+    if last == 0 {
+        return Err(PieBashError::ExitStatus(1).into());
+    }
+    Ok(())
+}
+
+/// Quotes `value` for re-input: bare when it's all safe characters, single-quoted otherwise.
+pub fn shell_quote(value: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_-./:=@%+,".contains(c);
+    if !value.is_empty() && value.chars().all(safe) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
 pub fn unset(command: &Command, env: &mut Environment) -> Result<()> {
     for var in &command.args {
-        env.unset_var(var);
+        // `unset arr[i]` removes one element
+        if let Some((name, subscript)) = var.strip_suffix(']').and_then(|v| v.split_once('[')) {
+            let index = arith::eval(subscript, env)?;
+            env.unset_array_element(name, index).map_err(|e| anyhow::anyhow!("unset: {}", e))?;
+            continue;
+        }
+
+        env.unset_var(var).map_err(|e| anyhow::anyhow!("unset: {}", e))?;
         std::env::remove_var(var);
     }
     Ok(())
@@ -145,46 +703,126 @@ pub fn unalias_cmd(command: &Command, env: &mut Environment) -> Result<()> {
     Ok(())
 }
 
-pub fn history_cmd(env: &Environment) -> Result<()> {
-    let history_file = env.get_home_dir().join(".piebash_history");
-    if history_file.exists() {
-        let contents = std::fs::read_to_string(&history_file)?;
-        for (i, line) in contents.lines().enumerate() {
-            println!("{:>5}  {}", i + 1, line);
+/// `complete [-pr] [-W wordlist] [name...]`: set the words Tab offers for a
+/// command's arguments, show them as `complete` commands, or remove them.
+pub fn complete_cmd(command: &Command, env: &mut Environment) -> Result<()> {
+    let mut words = None;
+    let mut remove = false;
+    let mut names = Vec::new();
+
+    let mut args = command.args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-W" => match args.next() {
+                Some(list) => words = Some(list.split_whitespace().map(String::from).collect::<Vec<_>>()),
+                None => anyhow::bail!("complete: -W: option requires an argument"),
+            },
+            "-r" => remove = true,
+            "-p" => {}
+            _ if arg.starts_with('-') => anyhow::bail!("complete: {}: invalid option", arg),
+            _ => names.push(arg.as_str()),
+        }
+    }
+
+    if remove {
+        if names.is_empty() {
+            env.remove_completion(None);
+        }
+        for name in names {
+            if !env.remove_completion(Some(name)) {
+                anyhow::bail!("complete: {}: no completion specification", name);
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(words) = words {
+        if names.is_empty() {
+            anyhow::bail!("complete: usage: complete -W wordlist name...");
+        }
+        for name in names {
+            env.set_completion(name, words.clone());
+        }
+        return Ok(());
+    }
+
+    let completions = env.get_completions();
+    for name in &names {
+        if !completions.contains_key(*name) {
+            anyhow::bail!("complete: {}: no completion specification", name);
+        }
+    }
+    for (name, words) in completions {
+        if names.is_empty() || names.contains(&name.as_str()) {
+            println!("complete -W {} {}", shell_quote(&words.join(" ")), name);
         }
     }
     Ok(())
 }
 
-pub fn type_cmd(command: &Command) -> Result<()> {
-    let builtins = vec![
-        "cd", "pwd", "echo", "export", "env", "set", "unset",
-        "alias", "unalias", "help", "clear", "exit", "history",
-        "ls", "cat", "touch", "mkdir", "rm", "cp", "mv", "ln",
-        "chmod", "chown", "stat", "file",
-        "grep", "find", "wc", "head", "tail", "sort", "uniq", "which",
-        "wget", "curl",
-        "true", "false", "yes", "kill", "sleep",
-    ];
-
-    for cmd in &command.args {
-        if builtins.contains(&cmd.as_str()) {
-            println!("{} is a shell builtin", cmd);
-        } else if let Ok(path) = which::which(cmd) {
-            println!("{} is {}", cmd, path.display());
-        } else {
+pub fn history_cmd(env: &Environment) -> Result<()> {
+    for (i, line) in env.get_history().iter().enumerate() {
+        println!("{:>5}  {}", i + 1, line);
+    }
+    Ok(())
+}
+
+pub fn type_cmd(command: &Command, env: &Environment, builtins: &Builtins) -> Result<()> {
+    let all = command.args.iter().any(|a| a == "-a");
+
+    for cmd in command.args.iter().filter(|a| !a.starts_with('-')) {
+        let found = match all {
+            true => resolve::resolve_all(cmd, env, builtins),
+            false => resolve::resolve(cmd, env, builtins).into_iter().collect(),
+        };
+        for resolution in &found {
+            println!("{}", resolution.describe(cmd));
+        }
+
+        if found.is_empty() {
             eprintln!("{}: not found", cmd);
         }
     }
     Ok(())
 }
 
+/// `command -v name`: prints how `name` resolves.
+/// Running `command name args` is handled by the shell's dispatcher.
+pub fn command_cmd(command: &Command, env: &Environment, builtins: &Builtins) -> Result<()> {
+    let verbose = command.args.iter().any(|a| a == "-V");
+    if !verbose && !command.args.iter().any(|a| a == "-v") {
+        anyhow::bail!("command: usage: command [-v|-V] name [args...]");
+    }
+
+    let mut missing = false;
+    for name in command.args.iter().filter(|a| !a.starts_with('-')) {
+        match resolve::resolve(name, env, builtins) {
+            Some(resolution) if verbose => println!("{}", resolution.describe(name)),
+            Some(Resolution::Alias(value)) => println!("alias {}='{}'", name, value),
+            Some(Resolution::File(path)) => println!("{}", path.display()),
+            Some(Resolution::Builtin | Resolution::Runtime) => println!("{}", name),
+            None => {
+                if verbose {
+                    eprintln!("command: {}: not found", name);
+                }
+                missing = true;
+            }
+        }
+    }
+
+    // Like POSIX `command -v`, a name that isn't found prints nothing and fails
+    if missing {
+        return Err(PieBashError::ExitStatus(1).into());
+    }
+    Ok(())
+}
+
 pub fn true_cmd() -> Result<()> {
     Ok(())
 }
 
 pub fn false_cmd() -> Result<()> {
-    anyhow::bail!("false")
+    Err(PieBashError::ExitStatus(1).into())
 }
 
 pub fn yes_cmd(command: &Command) -> Result<()> {
@@ -199,6 +837,38 @@ pub fn yes_cmd(command: &Command) -> Result<()> {
     }
 }
 
+/// `umask` shows the file creation mask, `umask 022` sets it, `-S` shows it as `u=rwx,g=rx,o=rx`.
+pub fn umask_cmd(command: &Command) -> Result<()> {
+    let symbolic = command.args.iter().any(|a| a == "-S");
+
+    match command.args.iter().find(|a| !a.starts_with('-')) {
+        Some(value) => {
+            let mask = u32::from_str_radix(value, 8)
+                .ok()
+                .filter(|mask| *mask <= 0o777)
+                .ok_or_else(|| anyhow::anyhow!("umask: {}: invalid octal number", value))?;
+            platform::set_umask(mask);
+        }
+        None if symbolic => {
+            let allowed = !platform::get_umask() & 0o777;
+            let part = |who: char, shift: u32| {
+                let bits = (allowed >> shift) & 0o7;
+                let mut out = format!("{}=", who);
+                for (bit, ch) in [(0o4, 'r'), (0o2, 'w'), (0o1, 'x')] {
+                    if bits & bit != 0 {
+                        out.push(ch);
+                    }
+                }
+                out
+            };
+            println!("{},{},{}", part('u', 6), part('g', 3), part('o', 0));
+        }
+        None => println!("{:04o}", platform::get_umask()),
+    }
+
+    Ok(())
+}
+
 pub fn sleep_cmd(command: &Command) -> Result<()> {
     if command.args.is_empty() {
         anyhow::bail!("sleep: missing operand");
@@ -216,27 +886,20 @@ pub fn kill_cmd(command: &Command) -> Result<()> {
         anyhow::bail!("kill: missing operand");
     }
 
+    let mut failed = false;
     for arg in &command.args {
         if arg.starts_with('-') { continue; }
         let pid: u32 = arg.parse()
             .map_err(|_| anyhow::anyhow!("kill: invalid pid: {}", arg))?;
 
-        #[cfg(unix)]
-        {
-            use nix::sys::signal::{kill, Signal};
-            use nix::unistd::Pid;
-            kill(Pid::from_raw(pid as i32), Signal::SIGTERM)?;
+        if let Err(e) = platform::terminate(pid) {
+            eprintln!("kill: ({}) - {}", pid, e);
+            failed = true;
         }
+    }
 
-        #[cfg(windows)]
-        {
-            let output = std::process::Command::new("taskkill")
-                .args(&["/PID", &pid.to_string(), "/F"])
-                .output()?;
-            if !output.status.success() {
-                eprintln!("kill: could not kill pid {}", pid);
-            }
-        }
+    if failed {
+        return Err(PieBashError::ExitStatus(1).into());
     }
     Ok(())
 }
@@ -252,25 +915,44 @@ pub fn help() -> Result<()> {
     println!();
 
     println!("{}", "File & Directory:".yellow().bold());
-    println!("  ls [-lahrs]          List directory contents");
+    println!("  ls [-laAh1d]         List directory contents");
     println!("  cd <dir>             Change directory");
+    println!("  pushd <dir> / +N     Change directory, keeping the old one on a stack");
+    println!("  popd [+N]            Return to / drop a directory on the stack");
+    println!("  dirs [-clpv]         Show or clear the directory stack");
     println!("  pwd                  Print working directory");
     println!("  cat [-n] <file>      Display file contents");
     println!("  touch <file>         Create/update file");
     println!("  mkdir [-p] <dir>     Create directory");
-    println!("  rm [-rf] <file>      Remove file/directory");
-    println!("  cp [-r] <src> <dst>  Copy file/directory");
-    println!("  mv <src> <dst>       Move/rename file");
+    println!("  mktemp [-d] [tmpl]   Create unique temp file/directory");
+    println!("  rm [-rfi] <file>     Remove file/directory");
+    println!("  rm --trash <file>    Move to the trash instead (PIEBASH_TRASH=1: always)");
+    println!("  trash [list]         List trashed files");
+    println!("  trash restore <f>    Put a trashed file back");
+    println!("  trash empty          Delete trashed files for good");
+    println!("  cp [-ri] <src> <dst> Copy file/directory");
+    println!("  cp --reflink=auto    Clone copy-on-write where supported (Btrfs, XFS, APFS)");
+    println!("  cp/mv --progress     Show bytes and files copied");
+    println!("  mv [-i] <src> <dst>  Move/rename file");
     println!("  ln [-s] <src> <dst>  Create link");
     println!("  chmod <mode> <file>  Change permissions");
     println!("  stat <file>          File information");
+    println!("  stat -c / --printf   Custom format: %n %s %a %A %U %G %Y %y %F");
     println!("  file <file>          Determine file type");
+    println!("  du [-sh] [path]      Disk usage");
     println!();
 
     println!("{}", "Text Processing:".yellow().bold());
-    println!("  echo [-n] <text>           Print text");
+    println!("  echo [-neE] <text>         Print text (-e: interpret \\n, \\t, ...)");
     println!("  grep <pattern> <file>      Search in files");
-    println!("  wc [-lwc] <file>           Count lines/words/chars");
+    println!("  grep -c / -A N -B N -C N   Count matches / show context lines");
+    println!("  grep --color               Highlight the matched text");
+    println!("  grep -l / -L               List files with / without a match");
+    println!("  grep -w / -x               Match whole words / whole lines");
+    println!("  grep -F                    Treat the pattern as literal text");
+    println!("  grep -q / -m N             Only set the status / stop after N matches");
+    println!("  wc [-lwcL] <file>          Count lines/words/chars, longest line");
+    println!("  wc / du --json | --csv     Structured counts with a total");
     println!("  head [-n N] <file>         Show first N lines");
     println!("  tail [-n N] <file>         Show last N lines");
     println!("  sort [-r] <file>           Sort lines");
@@ -279,8 +961,13 @@ pub fn help() -> Result<()> {
 
     println!("{}", "Search:".yellow().bold());
     println!("  find <path> -name <pat>    Find files");
+    println!("  find -L / --follow         Descend into symlinked directories");
+    println!("  find ... -exec cmd {{}} \\;   Run cmd per match (end with + to batch)");
+    println!("  find --git / ls --git      Skip .gitignore'd files (or set PIEBASH_GITIGNORE=1)");
     println!("  which <cmd>                Locate command");
-    println!("  type <cmd>                 Show command type");
+    println!("  type [-a] <cmd>            Show command type");
+    println!("  command [-v] <cmd>         Run/resolve ignoring aliases");
+    println!("  builtin <cmd> [args]       Force a builtin");
     println!();
 
     println!("{}", "Network:".yellow().bold());
@@ -290,17 +977,43 @@ pub fn help() -> Result<()> {
 
     println!("{}", "System:".yellow().bold());
     println!("  export VAR=value           Set variable");
+    println!("  export -p                  List exported variables, quoted for source");
     println!("  unset VAR                  Unset variable");
+    println!("  declare -i VAR=expr        Integer variable (assignments are arithmetic)");
+    println!("  declare -r VAR=value       Readonly variable (also: readonly VAR=value)");
+    println!("  declare -p [VAR]           Show variables with their attributes");
+    println!("  arr=(a b c)                Indexed array: ${{arr[1]}}, ${{arr[@]}}, ${{#arr[@]}}");
+    println!("  arr[i]=value               Set one element (unset arr[i] removes it)");
+    println!("  set [-eCfx] [-o pipefail]  Set shell options (+ turns them off)");
+    println!("  set -- args                Set positional parameters $1, $2, ... ($#, $@)");
+    println!("  shopt -s globstar          Let ** match directories recursively (ls src/**/*.rs)");
+    println!("  shopt -s nocaseglob        Match globs regardless of case (also set -o nocaseglob)");
+    println!("  set -f                     Don't expand globs at all (noglob); \\* or '*' keeps one literal");
+    println!("  getopts spec name [args]   Parse the next option into $name / $OPTARG");
+    println!("  let \"x = x + 1\" / (( ... )) Integer arithmetic; fails when the result is 0");
     println!("  env                        Show variables");
+    println!("  printenv [VAR...]          Print variable values");
     println!("  alias name=value           Set alias");
     println!("  unalias name               Remove alias");
     println!("  history                    Show history");
+    println!("  PROMPT_COMMAND=cmd         Run cmd before each prompt");
+    println!("  source <file> / . <file>   Run file's commands in this shell");
+    println!("  ~/.piebashrc               Run at startup; not saved to, re-read it with source ~/.piebashrc");
+    println!("  complete -W \"words\" cmd    Offer words when Tab-completing cmd's arguments");
+    println!("  fc [-l] [first [last]]     Edit history entries in $EDITOR and re-run them (-l: list)");
+    println!("  fc -s [old=new] [cmd]      Re-run a command, with old replaced by new");
+    println!("  umask [-S] [mode]          Show or set the file creation mask");
     println!("  sleep <n>                  Sleep N seconds");
+    println!("  repeat [-e] N <cmd>        Run cmd N times (-e: stop on failure)");
     println!("  kill <pid>                 Kill process");
+    println!("  trap 'cmd' EXIT INT TERM   Run cmd on exit or signal (- resets)");
+    println!("  jobs [-l]                  List background jobs");
+    println!("  fg / bg [%job]             Bring a job to the foreground / resume it in the background");
     println!("  true                       Return success");
     println!("  false                      Return failure");
     println!("  clear                      Clear screen");
-    println!("  help                       This help");
+    println!("  help [name] / help --all   This help, one builtin, or all of them as JSON");
+    println!("  man <name>                 A builtin's help as a man page (system man otherwise)");
     println!("  exit                       Exit shell");
     println!();
 
@@ -308,9 +1021,14 @@ pub fn help() -> Result<()> {
     println!("  cmd1 | cmd2                Pipe output");
     println!("  cmd > file                 Redirect output");
     println!("  cmd >> file                Append output");
+    println!("  cmd >| file                Overwrite even with set -o noclobber (-C)");
+    println!("  cmd &> file / &>> file     Redirect stdout and stderr (append)");
+    println!("  cmd <<EOF ... EOF          Here-document as stdin");
+    println!("  cmd <<< word               Here-string: word and a newline as stdin");
     println!("  cmd1 && cmd2               Run if success");
     println!("  cmd1 || cmd2               Run if fail");
     println!("  cmd1 ; cmd2                Run both");
+    println!("  cmd &                      Run in the background as a job ($! is its pid)");
     println!();
 
     println!("{}", "Code Execution:".yellow().bold());
@@ -319,6 +1037,33 @@ pub fn help() -> Result<()> {
     println!("  java Main.java             Run Java");
     println!("  go run main.go             Run Go");
     println!("  @python print('hi')        Inline code");
+    println!("  @python 'code' -- a b      Inline code with a and b as its arguments");
+    println!("  @python -f script.py       Run a file, whatever its extension");
+    println!("  cat code.py | @python      Run piped code (piped text is stdin for a script)");
+    println!("  x=@python print(2+2)       Capture inline output");
+    println!("  @python --stats script.py  Print wall time and peak memory (or PIEBASH_EXEC_STATS=1)");
+    println!();
+
+    println!("{}", "Runtimes:".yellow().bold());
+    println!("  runtime install <lang>...  Download runtimes ahead of time");
+    println!("  runtime cache [clean]      Show or empty the download cache");
+    println!("  runtime check              Report runtimes older than the registry's version");
+    println!("  runtime upgrade [lang...]  Install the newer version and remove the old one");
+    println!("  PIEBASH_CACHE_MAX_MB=N     Evict least recently used archives past N MB");
+    println!();
+
+    println!("{}", "Configuration (~/.piebash/config.toml):".yellow().bold());
+    println!("  color = \"auto\"             auto, always or never (PIEBASH_COLOR)");
+    println!("  offline = false            Only use installed runtimes and cached archives (PIEBASH_OFFLINE)");
+    println!("  autoinstall = true         Install missing packages and retry (PIEBASH_AUTOINSTALL)");
+    println!("  histsize = 1000            History lines kept (PIEBASH_HISTSIZE)");
+    println!("  timeout = 0                Kill code runs after N seconds (PIEBASH_TIMEOUT)");
+    println!("  verbosity = \"normal\"       quiet, normal or verbose auto-install output (PIEBASH_VERBOSITY)");
+    println!("  title = false              Show the directory and command in the window title (PIEBASH_TITLE)");
+    println!("  [prompt] path / symbol     Prompt style (PIEBASH_PROMPT_PATH / _SYMBOL)");
+    println!("  Environment variables override the file");
+    println!("  PIEBASH_HOME=dir           Keep config, runtimes, cache and history in dir");
+    println!("  XDG_CONFIG/DATA/CACHE_HOME Used on Linux when set, instead of ~/.piebash");
     println!();
 
     Ok(())
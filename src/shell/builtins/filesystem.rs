@@ -1,74 +1,144 @@
 use anyhow::Result;
 use colored::*;
+use ignore::WalkBuilder;
 use std::fs;
-use std::path::Path;
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 
+use super::utils::{interpret_escapes, print_structured, OutputFormat};
 use crate::shell::parser::Command;
 use crate::shell::environment::Environment;
+use crate::terminal::{output, renderer};
+use crate::platform;
+use crate::utils::error::PieBashError;
 
 pub fn ls(command: &Command, env: &Environment) -> Result<()> {
+    let stdout = io::stdout();
+    let terminal = stdout.is_terminal();
+    write_listing(command, env, &mut stdout.lock(), terminal)
+}
+
+/// What `ls` lists, written to `out`; `terminal` lays short names out in columns
+/// rather than one per line. The shell uses it to capture `ls` for pipes and files.
+pub fn write_listing(command: &Command, env: &Environment, out: &mut dyn Write, terminal: bool) -> Result<()> {
     // Parse flags and path separately
     let mut show_all = false;
+    let mut almost_all = false;
     let mut long_format = false;
     let mut human_readable = false;
-    let mut target_path = None;
+    let mut one_per_line = false;
+    let mut dir_itself = false;
+    let mut targets = Vec::new();
+    let git_aware = super::utils::respects_gitignore(command, env);
 
     for arg in &command.args {
-        if arg.starts_with('-') {
+        if arg == "--git" {
+            continue;
+        } else if arg.starts_with('-') {
             // It's a flag - parse each character
             for ch in arg.chars().skip(1) {
                 match ch {
-                    'a' => show_all = true,
+                    // -a adds `.` and `..` to the dotfiles; -A doesn't. The last one wins
+                    'a' => (show_all, almost_all) = (true, false),
+                    'A' => (show_all, almost_all) = (false, true),
                     'l' => long_format = true,
                     'h' => human_readable = true,
+                    '1' => one_per_line = true,
+                    'd' => dir_itself = true,
                     's' => {} // size - ignore for now
                     _ => {}
                 }
             }
         } else {
             // It's a path
-            target_path = Some(arg.as_str());
+            targets.push(arg.as_str());
         }
     }
 
-    let path = if let Some(p) = target_path {
-        env.get_cwd().join(p)
-    } else {
-        env.get_cwd().clone()
-    };
+    if targets.is_empty() {
+        targets.push(".");
+    }
 
-    if !path.exists() {
-        anyhow::bail!("ls: cannot access '{}': No such file or directory", path.display());
+    // -d lists the operands themselves, as do file operands; they were named
+    // explicitly, so dotfiles among them are never hidden
+    let show_hidden = show_all || almost_all;
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+    let mut missing = false;
+    for target in &targets {
+        let path = env.get_cwd().join(target);
+        if !dir_itself && path.is_dir() {
+            dirs.push((*target, path));
+        } else if fs::symlink_metadata(&path).is_ok() {
+            files.push((target.to_string(), path));
+        } else {
+            eprintln!("ls: cannot access '{}': No such file or directory", target);
+            missing = true;
+        }
     }
 
-    if path.is_file() {
-        println!("{}", path.display());
-        return Ok(());
+    if !files.is_empty() {
+        print_entries(out, terminal, &files, long_format, human_readable, true, one_per_line)?;
     }
 
-    let mut entries = Vec::new();
-    for entry in fs::read_dir(&path)? {
-        entries.push(entry?);
+    // Several operands get a `dir:` heading each, as in GNU ls
+    let headings = targets.len() > 1;
+    for (i, (target, path)) in dirs.iter().enumerate() {
+        if headings {
+            if i > 0 || !files.is_empty() {
+                writeln!(out)?;
+            }
+            writeln!(out, "{}:", target)?;
+        }
+
+        let mut entries = Vec::new();
+        if git_aware {
+            let walker = WalkBuilder::new(path).hidden(false).max_depth(Some(1)).build();
+            for entry in walker {
+                let entry = entry?;
+                if entry.depth() > 0 {
+                    entries.push((entry.file_name().to_string_lossy().to_string(), entry.into_path()));
+                }
+            }
+        } else {
+            for entry in fs::read_dir(path)? {
+                let entry = entry?;
+                entries.push((entry.file_name().to_string_lossy().to_string(), entry.path()));
+            }
+        }
+        entries.sort();
+
+        if show_all {
+            entries.insert(0, ("..".to_string(), path.join("..")));
+            entries.insert(0, (".".to_string(), path.clone()));
+        }
+        if long_format {
+            writeln!(out, "total {}", entries.len())?;
+        }
+        print_entries(out, terminal, &entries, long_format, human_readable, show_hidden, one_per_line)?;
     }
-    entries.sort_by_key(|e| e.file_name());
 
-    if long_format {
-        // Print total
-        println!("total {}", entries.len());
-        for entry in &entries {
-            let file_name = entry.file_name();
-            let name = file_name.to_string_lossy();
+    if missing {
+        return Err(PieBashError::ExitStatus(2).into());
+    }
+    Ok(())
+}
 
-            if !show_all && name.starts_with('.') {
+/// Writes `(name, path)` pairs in `ls`'s short or long format.
+#[allow(clippy::too_many_arguments)]
+fn print_entries(out: &mut dyn Write, terminal: bool, entries: &[(String, PathBuf)], long_format: bool, human_readable: bool, show_hidden: bool, one_per_line: bool) -> Result<()> {
+    if long_format {
+        for (name, path) in entries {
+            if !show_hidden && name.starts_with('.') {
                 continue;
             }
 
-            let metadata = entry.metadata()?;
+            let metadata = fs::symlink_metadata(path)?;
             let size = metadata.len();
             let is_dir = metadata.is_dir();
 
-            let file_type = if is_dir { "d" } else { "-" };
-            let permissions = if is_dir { "rwxr-xr-x" } else { "rw-r--r--" };
+            let file_type = if is_dir { "d" } else if metadata.is_symlink() { "l" } else { "-" };
+            let permissions = platform::format_permissions(&metadata);
 
             let size_str = if human_readable {
                 format_size(size)
@@ -84,15 +154,19 @@ pub fn ls(command: &Command, env: &Environment) -> Result<()> {
                 })
                 .unwrap_or_else(|| "Jan 01 00:00".to_string());
 
-            let display_name = if is_dir {
+            // Plain names for pipes and files, whatever `colored` decided for the terminal
+            let display_name = if !terminal {
+                name.to_string()
+            } else if is_dir {
                 name.blue().bold().to_string()
-            } else if is_executable(&entry.path()) {
+            } else if platform::is_executable(path) {
                 name.green().bold().to_string()
             } else {
                 name.to_string()
             };
 
-            println!(
+            writeln!(
+                out,
                 "{}{} {:>3} {:>8} {} {}",
                 file_type,
                 permissions,
@@ -100,36 +174,46 @@ pub fn ls(command: &Command, env: &Environment) -> Result<()> {
                 size_str,
                 modified,
                 display_name
-            );
+            )?;
         }
     } else {
         // Short format
-        for entry in &entries {
-            let file_name = entry.file_name();
-            let name = file_name.to_string_lossy();
-
-            if !show_all && name.starts_with('.') {
+        let mut items = Vec::new();
+        for (name, path) in entries {
+            if !show_hidden && name.starts_with('.') {
                 continue;
             }
 
-            let metadata = entry.metadata()?;
+            let metadata = fs::symlink_metadata(path)?;
             let is_dir = metadata.is_dir();
 
-            if is_dir {
-                print!("{}  ", name.blue().bold());
-            } else if is_executable(&entry.path()) {
-                print!("{}  ", name.green().bold());
+            // Plain names for pipes and files, whatever `colored` decided for the terminal
+            let display = if !terminal {
+                name.to_string()
+            } else if is_dir {
+                name.blue().bold().to_string()
+            } else if platform::is_executable(path) {
+                name.green().bold().to_string()
             } else {
-                print!("{}  ", name);
+                name.to_string()
+            };
+            items.push((display, name.chars().count()));
+        }
+
+        // Columns for people, one name per line for pipes and files
+        if !one_per_line && terminal {
+            write!(out, "{}", renderer::columns(&items, renderer::terminal_width()))?;
+        } else {
+            for (display, _) in &items {
+                writeln!(out, "{}", display)?;
             }
         }
-        println!();
     }
 
     Ok(())
 }
 
-fn format_size(size: u64) -> String {
+pub fn format_size(size: u64) -> String {
     if size >= 1024 * 1024 * 1024 {
         format!("{:.1}G", size as f64 / (1024.0 * 1024.0 * 1024.0))
     } else if size >= 1024 * 1024 {
@@ -157,14 +241,14 @@ pub fn cat(command: &Command) -> Result<()> {
             continue;
         }
 
-        let contents = fs::read_to_string(path)?;
+        let mut reader = BufReader::new(fs::File::open(path)?);
 
         if show_line_numbers {
-            for (i, line) in contents.lines().enumerate() {
-                println!("{:>6}  {}", i + 1, line);
+            for (i, line) in reader.lines().enumerate() {
+                println!("{:>6}  {}", i + 1, line?);
             }
         } else {
-            print!("{}", contents);
+            io::copy(&mut reader, &mut io::stdout().lock())?;
         }
     }
 
@@ -212,6 +296,60 @@ pub fn mkdir(command: &Command) -> Result<()> {
     Ok(())
 }
 
+/// `mktemp [-d] [-p DIR] [TEMPLATE]`: creates a uniquely named file (or directory) and prints its path.
+///
+/// The trailing `X`s of TEMPLATE (default `tmp.XXXXXXXXXX`) become random characters. A given
+/// template is created in the current directory unless `-p DIR` or `-t` (the temp dir) says otherwise.
+pub fn mktemp(command: &Command) -> Result<()> {
+    let mut directory = false;
+    let mut parent: Option<String> = None;
+    let mut in_tmpdir = false;
+    let mut template: Option<&str> = None;
+
+    let mut iter = command.args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-d" | "--directory" => directory = true,
+            "-p" | "--tmpdir" => match iter.next() {
+                Some(dir) => parent = Some(dir.clone()),
+                None => anyhow::bail!("mktemp: option requires an argument -- 'p'"),
+            },
+            "-t" => in_tmpdir = true,
+            _ if arg.starts_with('-') => anyhow::bail!("mktemp: invalid option -- '{}'", arg.trim_start_matches('-')),
+            _ if template.is_some() => anyhow::bail!("mktemp: too many templates"),
+            _ => template = Some(arg),
+        }
+    }
+
+    let dir = match parent {
+        Some(dir) => std::path::PathBuf::from(dir),
+        None if template.is_some() && !in_tmpdir => std::env::current_dir()?,
+        None => crate::platform::native().get_temp_dir(),
+    };
+
+    let template = template.unwrap_or("tmp.XXXXXXXXXX");
+    let prefix = template.trim_end_matches('X');
+    let random = template.len() - prefix.len();
+    if random < 3 {
+        anyhow::bail!("mktemp: too few X's in template '{}'", template);
+    }
+
+    let mut builder = tempfile::Builder::new();
+    builder.prefix(prefix).rand_bytes(random);
+
+    let created = if directory {
+        builder.make_in(&dir, |path| fs::create_dir(path))?.into_temp_path()
+    } else {
+        builder.tempfile_in(&dir)?.into_temp_path()
+    };
+
+    // Kept: the caller removes it, as with mktemp(1)
+    let path = created.keep()?;
+    println!("{}", path.display());
+
+    Ok(())
+}
+
 pub fn rm(command: &Command) -> Result<()> {
     if command.args.is_empty() {
         anyhow::bail!("rm: missing operand");
@@ -219,17 +357,36 @@ pub fn rm(command: &Command) -> Result<()> {
 
     let mut recursive = false;
     let mut force = false;
+    let mut interactive = false;
+    let mut trash = super::trash::enabled();
+    let mut preserve_root = true;
+    let mut refused = false;
 
     for arg in &command.args {
-        if arg.starts_with('-') {
+        if arg.starts_with("--") {
+            match arg.as_str() {
+                "--trash" => trash = true,
+                "--preserve-root" => preserve_root = true,
+                "--no-preserve-root" => preserve_root = false,
+                _ => anyhow::bail!("rm: unrecognized option '{}'", arg),
+            }
+        } else if arg.starts_with('-') {
             if arg.contains('r') || arg.contains('R') { recursive = true; }
             if arg.contains('f') { force = true; }
+            if arg.contains('i') { interactive = true; }
         }
     }
 
     for item in &command.args {
         if item.starts_with('-') { continue; }
 
+        // Checked as written, before `..` resolves to a directory that looks harmless
+        if names_dot_entry(item) {
+            eprintln!("rm: refusing to remove '.' or '..' directory: skipping '{}'", item);
+            refused = true;
+            continue;
+        }
+
         let path = Path::new(item.as_str());
 
         if !path.exists() {
@@ -239,27 +396,83 @@ pub fn rm(command: &Command) -> Result<()> {
             continue;
         }
 
-        if path.is_dir() {
-            if recursive {
-                fs::remove_dir_all(path)?;
-            } else {
-                anyhow::bail!("rm: cannot remove '{}': Is a directory (use -r)", item);
-            }
+        if interactive && !super::confirm(&format!("rm: remove '{}'?", item), false) {
+            continue;
+        }
+
+        if path.is_dir() && !recursive {
+            anyhow::bail!("rm: cannot remove '{}': Is a directory (use -r)", item);
+        }
+        if recursive && preserve_root && is_protected_dir(path) {
+            anyhow::bail!(
+                "rm: it is dangerous to operate recursively on '{}'\nrm: use --no-preserve-root to override this failsafe",
+                item
+            );
+        }
+
+        if trash {
+            super::trash::move_to_trash(path).map_err(|e| anyhow::anyhow!("rm: cannot trash '{}': {}", item, e))?;
+        } else if path.is_dir() {
+            fs::remove_dir_all(path)?;
         } else {
             fs::remove_file(path)?;
         }
     }
 
+    if refused {
+        return Err(PieBashError::ExitStatus(1).into());
+    }
     Ok(())
 }
 
+/// Whether the operand's last component is `.` or `..` (`.`, `a/..`, `/tmp/t/../`).
+fn names_dot_entry(item: &str) -> bool {
+    let last = item.trim_end_matches(std::path::is_separator).rsplit(std::path::is_separator).next();
+    matches!(last, Some(".") | Some(".."))
+}
+
+/// What `rm -r` refuses without `--no-preserve-root`: a filesystem root (`/`, `C:\`)
+/// or the home directory, however the path spells it (`~`, `/..`, a symlink).
+fn is_protected_dir(path: &Path) -> bool {
+    let Ok(target) = fs::canonicalize(path) else {
+        return false;
+    };
+    let home = fs::canonicalize(platform::native().get_home_dir()).ok();
+    target.parent().is_none() || home.is_some_and(|home| home == target)
+}
+
+/// `cp --reflink[=WHEN]`: whether to clone files copy-on-write instead of copying bytes.
+#[derive(Clone, Copy, PartialEq)]
+enum Reflink {
+    Never,
+    /// Clone where the filesystem supports it, copy elsewhere
+    Auto,
+    /// Clone or fail
+    Always,
+}
+
 pub fn cp(command: &Command) -> Result<()> {
     let mut recursive = false;
+    let mut interactive = false;
+    let mut reflink = Reflink::Never;
+    let mut show_progress = false;
     let mut args: Vec<&String> = Vec::new();
 
     for arg in &command.args {
-        if arg.starts_with('-') {
+        if arg == "--progress" {
+            show_progress = true;
+        } else if let Some(when) = arg.strip_prefix("--reflink") {
+            reflink = match when.strip_prefix('=') {
+                None if when.is_empty() => Reflink::Always,
+                Some("always") => Reflink::Always,
+                Some("auto") => Reflink::Auto,
+                Some("never") => Reflink::Never,
+                Some(value) => anyhow::bail!("cp: invalid argument '{}' for '--reflink' (auto, always, never)", value),
+                None => anyhow::bail!("cp: unrecognized option '{}'", arg),
+            };
+        } else if arg.starts_with('-') {
             if arg.contains('r') || arg.contains('R') { recursive = true; }
+            if arg.contains('i') { interactive = true; }
         } else {
             args.push(arg);
         }
@@ -276,38 +489,142 @@ pub fn cp(command: &Command) -> Result<()> {
         anyhow::bail!("cp: cannot stat '{}': No such file or directory", source.display());
     }
 
+    let mut progress = show_progress.then(|| CopyProgress::measure(source));
     if source.is_dir() {
         if !recursive {
             anyhow::bail!("cp: -r not specified; omitting directory '{}'", source.display());
         }
-        copy_dir_all(source, dest)?;
+        copy_dir_all(source, dest, reflink, progress.as_mut())?;
     } else {
-        if dest.is_dir() {
-            let filename = source.file_name().unwrap();
-            fs::copy(source, dest.join(filename))?;
+        let target = if dest.is_dir() {
+            dest.join(source.file_name().unwrap())
         } else {
-            fs::copy(source, dest)?;
+            dest.to_path_buf()
+        };
+        if interactive && target.exists()
+            && !super::confirm(&format!("cp: overwrite '{}'?", target.display()), false)
+        {
+            return Ok(());
+        }
+        copy_file(source, &target, reflink, progress.as_mut())?;
+    }
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+
+    Ok(())
+}
+
+/// `cp --progress` / `mv --progress`: bytes and files copied so far against the
+/// total, counted up front, on the progress line the runtime downloader uses.
+struct CopyProgress {
+    total_bytes: u64,
+    total_files: u64,
+    bytes: u64,
+    files: u64,
+    drawn: Option<std::time::Instant>,
+}
+
+impl CopyProgress {
+    fn measure(source: &Path) -> Self {
+        let (mut total_bytes, mut total_files) = (0, 0);
+        for entry in walkdir::WalkDir::new(source).into_iter().filter_map(|e| e.ok()) {
+            if !entry.file_type().is_dir() {
+                total_bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+                total_files += 1;
+            }
         }
+        Self { total_bytes, total_files, bytes: 0, files: 0, drawn: None }
+    }
+
+    fn add_bytes(&mut self, n: u64) {
+        self.bytes += n;
+        self.draw(false);
     }
 
+    fn file_done(&mut self) {
+        self.files += 1;
+        self.draw(false);
+    }
+
+    /// Redraws at most ten times a second, so a tree of small files doesn't flood the terminal.
+    fn draw(&mut self, force: bool) {
+        if !force && self.drawn.is_some_and(|at| at.elapsed() < std::time::Duration::from_millis(100)) {
+            return;
+        }
+        self.drawn = Some(std::time::Instant::now());
+        let percent = if self.total_bytes > 0 { self.bytes as f64 / self.total_bytes as f64 * 100.0 } else { 100.0 };
+        output::progress(format!(
+            "{:.1}% ({} / {}, {} of {} files)",
+            percent,
+            format_size(self.bytes),
+            format_size(self.total_bytes),
+            self.files,
+            self.total_files
+        ));
+    }
+
+    fn finish(mut self) {
+        self.draw(true);
+        output::finish_progress();
+    }
+}
+
+fn copy_file(source: &Path, target: &Path, reflink: Reflink, progress: Option<&mut CopyProgress>) -> Result<()> {
+    if reflink != Reflink::Never {
+        match platform::clone_file(source, target) {
+            Ok(()) => {
+                if let Some(progress) = progress {
+                    progress.add_bytes(fs::metadata(source)?.len());
+                    progress.file_done();
+                }
+                return Ok(());
+            }
+            Err(e) if reflink == Reflink::Always => anyhow::bail!(
+                "cp: failed to clone '{}' from '{}': {}", target.display(), source.display(), e
+            ),
+            Err(_) => {}
+        }
+    }
+
+    let Some(progress) = progress else {
+        fs::copy(source, target)?;
+        return Ok(());
+    };
+    // fs::copy can't report as it goes, so copy in chunks, then match the permissions as it would
+    let mut reader = fs::File::open(source)?;
+    let mut writer = fs::File::create(target)?;
+    let mut buf = vec![0; 1024 * 1024];
+    loop {
+        let n = io::Read::read(&mut reader, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        io::Write::write_all(&mut writer, &buf[..n])?;
+        progress.add_bytes(n as u64);
+    }
+    fs::set_permissions(target, reader.metadata()?.permissions())?;
+    progress.file_done();
     Ok(())
 }
 
-fn copy_dir_all(src: &Path, dst: &Path) -> Result<()> {
+fn copy_dir_all(src: &Path, dst: &Path, reflink: Reflink, mut progress: Option<&mut CopyProgress>) -> Result<()> {
     fs::create_dir_all(dst)?;
     for entry in fs::read_dir(src)? {
         let entry = entry?;
         let ty = entry.file_type()?;
         if ty.is_dir() {
-            copy_dir_all(&entry.path(), &dst.join(entry.file_name()))?;
+            copy_dir_all(&entry.path(), &dst.join(entry.file_name()), reflink, progress.as_deref_mut())?;
         } else {
-            fs::copy(entry.path(), dst.join(entry.file_name()))?;
+            copy_file(&entry.path(), &dst.join(entry.file_name()), reflink, progress.as_deref_mut())?;
         }
     }
     Ok(())
 }
 
 pub fn mv(command: &Command) -> Result<()> {
+    let interactive = command.args.iter().any(|a| a.starts_with('-') && !a.starts_with("--") && a.contains('i'));
+    let show_progress = command.args.iter().any(|a| a == "--progress");
     let args: Vec<&String> = command.args.iter().filter(|a| !a.starts_with('-')).collect();
 
     if args.len() < 2 {
@@ -321,11 +638,33 @@ pub fn mv(command: &Command) -> Result<()> {
         anyhow::bail!("mv: cannot stat '{}': No such file or directory", source.display());
     }
 
-    if dest.is_dir() {
-        let filename = source.file_name().unwrap();
-        fs::rename(source, dest.join(filename))?;
+    let target = if dest.is_dir() {
+        dest.join(source.file_name().unwrap())
     } else {
-        fs::rename(source, dest)?;
+        dest.to_path_buf()
+    };
+    if interactive && target.exists()
+        && !super::confirm(&format!("mv: overwrite '{}'?", target.display()), false)
+    {
+        return Ok(());
+    }
+
+    match fs::rename(source, &target) {
+        // Another filesystem: copy, then delete the original
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            let mut progress = show_progress.then(|| CopyProgress::measure(source));
+            if source.is_dir() {
+                copy_dir_all(source, &target, Reflink::Never, progress.as_mut())?;
+                fs::remove_dir_all(source)?;
+            } else {
+                copy_file(source, &target, Reflink::Never, progress.as_mut())?;
+                fs::remove_file(source)?;
+            }
+            if let Some(progress) = progress {
+                progress.finish();
+            }
+        }
+        result => result?,
     }
 
     Ok(())
@@ -342,20 +681,8 @@ pub fn ln(command: &Command) -> Result<()> {
     let source = Path::new(args[0].as_str());
     let dest = Path::new(args[1].as_str());
 
-    #[cfg(unix)]
     if symbolic {
-        std::os::unix::fs::symlink(source, dest)?;
-    } else {
-        fs::hard_link(source, dest)?;
-    }
-
-    #[cfg(windows)]
-    if symbolic {
-        if source.is_dir() {
-            std::os::windows::fs::symlink_dir(source, dest)?;
-        } else {
-            std::os::windows::fs::symlink_file(source, dest)?;
-        }
+        platform::symlink(source, dest)?;
     } else {
         fs::hard_link(source, dest)?;
     }
@@ -364,32 +691,22 @@ pub fn ln(command: &Command) -> Result<()> {
 }
 
 pub fn chmod(command: &Command) -> Result<()> {
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-
-        let args: Vec<&String> = command.args.iter().filter(|a| !a.starts_with('-')).collect();
+    let args: Vec<&String> = command.args.iter().filter(|a| !a.starts_with('-')).collect();
 
-        if args.len() < 2 {
-            anyhow::bail!("chmod: missing operand");
-        }
+    if args.len() < 2 {
+        anyhow::bail!("chmod: missing operand");
+    }
 
-        let mode = u32::from_str_radix(args[0], 8)
-            .map_err(|_| anyhow::anyhow!("chmod: invalid mode: {}", args[0]))?;
+    let mode = u32::from_str_radix(args[0], 8)
+        .map_err(|_| anyhow::anyhow!("chmod: invalid mode: {}", args[0]))?;
 
-        for file in &args[1..] {
-            let path = Path::new(file.as_str());
-            if !path.exists() {
-                eprintln!("chmod: cannot access '{}': No such file or directory", file);
-                continue;
-            }
-            fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    for file in &args[1..] {
+        let path = Path::new(file.as_str());
+        if !path.exists() {
+            eprintln!("chmod: cannot access '{}': No such file or directory", file);
+            continue;
         }
-    }
-
-    #[cfg(windows)]
-    {
-        eprintln!("chmod: not fully supported on Windows");
+        platform::set_mode(path, mode)?;
     }
 
     Ok(())
@@ -409,24 +726,130 @@ pub fn chown(command: &Command) -> Result<()> {
     Ok(())
 }
 
+/// `du [-sh] [--json|--csv] [path...]`: disk usage of each directory, in KiB unless `-h`.
+/// Sizes are the files' apparent sizes; symlinks are not followed.
+pub fn du(command: &Command) -> Result<()> {
+    let format = OutputFormat::from_args(&command.args);
+    let mut summarize = false;
+    let mut human = false;
+    let mut targets = Vec::new();
+
+    for arg in &command.args {
+        if OutputFormat::is_flag(arg) {
+            continue;
+        } else if arg.starts_with('-') && arg.len() > 1 {
+            for ch in arg.chars().skip(1) {
+                match ch {
+                    's' => summarize = true,
+                    'h' => human = true,
+                    _ => anyhow::bail!("du: invalid option -- '{}'", ch),
+                }
+            }
+        } else {
+            targets.push(arg.as_str());
+        }
+    }
+    if targets.is_empty() {
+        targets.push(".");
+    }
+
+    // (path, bytes), each directory after its contents
+    let mut rows = Vec::new();
+    let mut total = 0;
+    for target in targets {
+        let path = Path::new(target);
+        if fs::symlink_metadata(path).is_err() {
+            anyhow::bail!("du: cannot access '{}': No such file or directory", target);
+        }
+        let size = disk_usage(path, !summarize, &mut rows)?;
+        total += size;
+        if summarize || !path.is_dir() {
+            rows.push((target.to_string(), size));
+        }
+    }
+
+    if format != OutputFormat::Text {
+        let json_rows: Vec<Vec<serde_json::Value>> = rows
+            .iter()
+            .map(|(path, size)| vec![serde_json::json!(path), serde_json::json!(size)])
+            .collect();
+        return print_structured(format, "entries", &["path", "bytes"], &json_rows, &[("bytes", serde_json::json!(total))]);
+    }
+
+    for (path, size) in &rows {
+        let shown = if human { format_size(*size) } else { size.div_ceil(1024).to_string() };
+        println!("{}\t{}", shown, path);
+    }
+
+    Ok(())
+}
+
+/// Total size under `path`. With `list`, also records every directory's total in `rows`.
+fn disk_usage(path: &Path, list: bool, rows: &mut Vec<(String, u64)>) -> Result<u64> {
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+
+    let mut total = 0;
+    for entry in fs::read_dir(path)? {
+        total += disk_usage(&entry?.path(), list, rows)?;
+    }
+    if list {
+        rows.push((path.display().to_string(), total));
+    }
+    Ok(total)
+}
+
+/// `stat [-c FORMAT | --format=FORMAT | --printf=FORMAT] file...`
+///
+/// The formats take GNU's `%s %n %a %A %U %G %u %g %Y %y %F %%`. `-c`/`--format` end
+/// each file's output with a newline; `--printf` doesn't, but interprets backslash
+/// escapes such as `\n` and `\t`.
 pub fn stat(command: &Command) -> Result<()> {
     if command.args.is_empty() {
         anyhow::bail!("stat: missing file operand");
     }
 
-    for file in &command.args {
-        if file.starts_with('-') { continue; }
+    // (format, printf-style)
+    let mut format = None;
+    let mut files = Vec::new();
+    let mut args = command.args.iter();
+    while let Some(arg) = args.next() {
+        if let Some(fmt) = arg.strip_prefix("--format=") {
+            format = Some((fmt.to_string(), false));
+        } else if let Some(fmt) = arg.strip_prefix("--printf=") {
+            format = Some((fmt.to_string(), true));
+        } else if arg == "-c" || arg == "--format" || arg == "--printf" {
+            let fmt = args.next().ok_or_else(|| anyhow::anyhow!("stat: option '{}' requires an argument", arg))?;
+            format = Some((fmt.clone(), arg == "--printf"));
+        } else if !arg.starts_with('-') {
+            files.push(arg);
+        }
+    }
 
+    let mut failed = false;
+    for file in files {
         let path = Path::new(file.as_str());
-        if !path.exists() {
+        let Ok(metadata) = fs::symlink_metadata(path) else {
             eprintln!("stat: cannot stat '{}': No such file or directory", file);
+            failed = true;
+            continue;
+        };
+
+        if let Some((fmt, printf)) = &format {
+            let out = format_stat(fmt, file, &metadata);
+            if *printf {
+                print!("{}", interpret_escapes(&out));
+            } else {
+                println!("{}", out);
+            }
             continue;
         }
 
-        let metadata = fs::metadata(path)?;
         println!("  File: {}", file);
         println!("  Size: {}", metadata.len());
-        println!("  Type: {}", if metadata.is_dir() { "directory" } else { "regular file" });
+        println!("  Type: {}", file_type_name(&metadata));
 
         if let Ok(modified) = metadata.modified() {
             let dt: chrono::DateTime<chrono::Local> = modified.into();
@@ -438,9 +861,67 @@ pub fn stat(command: &Command) -> Result<()> {
         }
     }
 
+    if failed {
+        return Err(PieBashError::ExitStatus(1).into());
+    }
     Ok(())
 }
 
+/// GNU stat's `%F`.
+fn file_type_name(metadata: &fs::Metadata) -> &'static str {
+    if metadata.is_symlink() {
+        "symbolic link"
+    } else if metadata.is_dir() {
+        "directory"
+    } else if metadata.len() == 0 {
+        "regular empty file"
+    } else {
+        "regular file"
+    }
+}
+
+/// Fills in a `stat -c` format; unknown `%` sequences are kept as written.
+fn format_stat(fmt: &str, file: &str, metadata: &fs::Metadata) -> String {
+    let modified: Option<chrono::DateTime<chrono::Local>> = metadata.modified().ok().map(Into::into);
+    let mut out = String::new();
+    let mut chars = fmt.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '%' {
+            out.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some('s') => out.push_str(&metadata.len().to_string()),
+            Some('n') => out.push_str(file),
+            Some('a') => out.push_str(&format!("{:o}", platform::file_mode(metadata))),
+            Some('A') => {
+                let kind = if metadata.is_symlink() { 'l' } else if metadata.is_dir() { 'd' } else { '-' };
+                out.push(kind);
+                out.push_str(&platform::format_permissions(metadata));
+            }
+            Some('U') => out.push_str(&platform::file_owner(metadata).user),
+            Some('G') => out.push_str(&platform::file_owner(metadata).group),
+            Some('u') => out.push_str(&platform::file_owner(metadata).uid.to_string()),
+            Some('g') => out.push_str(&platform::file_owner(metadata).gid.to_string()),
+            Some('Y') => out.push_str(&modified.map(|t| t.timestamp().to_string()).unwrap_or_default()),
+            Some('y') => out.push_str(
+                &modified.map(|t| t.format("%Y-%m-%d %H:%M:%S%.9f %z").to_string()).unwrap_or_default(),
+            ),
+            Some('F') => out.push_str(file_type_name(metadata)),
+            Some('%') => out.push('%'),
+            Some(other) => {
+                out.push('%');
+                out.push(other);
+            }
+            None => out.push('%'),
+        }
+    }
+
+    out
+}
+
 pub fn file_cmd(command: &Command) -> Result<()> {
     if command.args.is_empty() {
         anyhow::bail!("file: missing operand");
@@ -482,26 +963,3 @@ pub fn file_cmd(command: &Command) -> Result<()> {
 
     Ok(())
 }
-
-fn is_executable(path: &Path) -> bool {
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        if let Ok(metadata) = fs::metadata(path) {
-            return metadata.permissions().mode() & 0o111 != 0;
-        }
-        false
-    }
-
-    #[cfg(windows)]
-    {
-        if let Some(ext) = path.extension() {
-            let ext = ext.to_string_lossy().to_lowercase();
-            return ext == "exe" || ext == "bat" || ext == "cmd" || ext == "com";
-        }
-        false
-    }
-
-    #[cfg(not(any(unix, windows)))]
-    false
-}
\ No newline at end of file
@@ -0,0 +1,290 @@
+use anyhow::Result;
+use colored::*;
+use serde::Serialize;
+
+use crate::shell::parser::Command;
+use crate::utils::error::PieBashError;
+
+/// What `help NAME` and `help --all` know about a builtin.
+#[derive(Serialize)]
+pub struct BuiltinHelp {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub description: &'static str,
+    pub flags: &'static [Flag],
+}
+
+#[derive(Serialize)]
+pub struct Flag {
+    pub flag: &'static str,
+    pub description: &'static str,
+}
+
+macro_rules! flags {
+    ($($flag:literal => $description:literal),* $(,)?) => {
+        &[$(Flag { flag: $flag, description: $description }),*]
+    };
+}
+
+macro_rules! builtin {
+    ($name:literal, $usage:literal, $description:literal $(, $flag:literal => $flag_description:literal)* $(,)?) => {
+        BuiltinHelp { name: $name, usage: $usage, description: $description, flags: flags!($($flag => $flag_description),*) }
+    };
+}
+
+/// One entry per builtin, in the order `help` lists them.
+pub const BUILTINS: &[BuiltinHelp] = &[
+    builtin!("ls", "ls [-laAh1d] [--git] [path...]", "List directory contents",
+        "-l" => "Long format with permissions, size and date",
+        "-a" => "Show hidden entries, with . and ..",
+        "-A" => "Show hidden entries, without . and ..",
+        "-h" => "Human-readable sizes",
+        "-1" => "One entry per line",
+        "-d" => "List directories themselves, not their contents",
+        "--git" => "Skip .gitignore'd files"),
+    builtin!("cd", "cd [dir]", "Change directory (home when none is given)"),
+    builtin!("pwd", "pwd", "Print working directory"),
+    builtin!("pushd", "pushd [dir | +N | -N]", "Change directory, keeping the old one on the stack; +N rotates entry N to the top"),
+    builtin!("popd", "popd [+N | -N]", "Drop the top of the directory stack and return to the next, or drop entry N"),
+    builtin!("dirs", "dirs [-clpv] [+N | -N]", "Show the directory stack",
+        "-c" => "Clear it",
+        "-l" => "Show full paths instead of ~",
+        "-p" => "One entry per line",
+        "-v" => "One entry per line, numbered"),
+    builtin!("cat", "cat [-n] file...", "Display file contents",
+        "-n" => "Number the lines"),
+    builtin!("touch", "touch file...", "Create files or update their timestamps"),
+    builtin!("mkdir", "mkdir [-p] dir...", "Create directories",
+        "-p" => "Create missing parents; no error if it exists"),
+    builtin!("mktemp", "mktemp [-d] [-p dir] [-t] [template]", "Create a unique temporary file or directory",
+        "-d" => "Create a directory",
+        "-p" => "Create it in the given directory",
+        "-t" => "Create it in the temp directory"),
+    builtin!("rm", "rm [-rfi] [--trash] [--no-preserve-root] path...", "Remove files or directories",
+        "-r" => "Remove directories and their contents",
+        "-f" => "Ignore missing files",
+        "-i" => "Ask before removing each one",
+        "--trash" => "Move to the trash instead (always, with PIEBASH_TRASH=1)",
+        "--no-preserve-root" => "Allow -r on / or the home directory"),
+    builtin!("trash", "trash [list | restore name|path... | empty]", "List, restore or empty what rm --trash deleted"),
+    builtin!("cp", "cp [-ri] [--reflink[=WHEN]] [--progress] src dst", "Copy files or directories",
+        "-r" => "Copy directories recursively",
+        "-i" => "Ask before overwriting",
+        "--reflink" => "Clone copy-on-write; =auto falls back to copying, =always fails instead",
+        "--progress" => "Show bytes and files copied so far"),
+    builtin!("mv", "mv [-i] [--progress] src dst", "Move or rename a file",
+        "-i" => "Ask before overwriting",
+        "--progress" => "Show progress when it has to copy to another filesystem"),
+    builtin!("ln", "ln [-s] target link", "Create a link",
+        "-s" => "Create a symbolic link"),
+    builtin!("chmod", "chmod mode file...", "Change permissions"),
+    builtin!("chown", "chown owner file...", "Change owner"),
+    builtin!("stat", "stat [-c format | --printf=format] file...", "Show file information",
+        "-c" => "Print in a format (%n %s %a %A %U %G %u %g %Y %y %F %%), one line per file",
+        "--format" => "Same as -c",
+        "--printf" => "Like --format, without the newline and with backslash escapes"),
+    builtin!("file", "file file...", "Determine file type"),
+    builtin!("du", "du [-sh] [--json|--csv] [path...]", "Show disk usage",
+        "-s" => "Only a total for each path",
+        "-h" => "Human-readable sizes",
+        "--json" => "Sizes in bytes as JSON, with a total",
+        "--csv" => "Sizes in bytes as CSV, ending with a total row"),
+    builtin!("echo", "echo [-neE] text...", "Print text",
+        "-n" => "No trailing newline",
+        "-e" => "Interpret backslash escapes (\\n, \\t, \\c ...)",
+        "-E" => "Don't interpret backslash escapes (the default)"),
+    builtin!("grep", "grep [-clLwxFq] [-m N] [-A N] [-B N] [-C N] [--color] pattern [file...]", "Search for a pattern",
+        "-c" => "Count matching lines",
+        "-l" => "List files with a match",
+        "-L" => "List files without a match",
+        "-w" => "Match whole words",
+        "-x" => "Match whole lines",
+        "-F" => "Treat the pattern as literal text",
+        "-q" => "Print nothing; exit 0 at the first match",
+        "-m" => "Stop reading a file after N matches",
+        "-A" => "Show N lines after each match",
+        "-B" => "Show N lines before each match",
+        "-C" => "Show N lines around each match",
+        "--color" => "Highlight the matched text"),
+    builtin!("wc", "wc [-lwcL] [--json|--csv] [file...]", "Count lines, words and characters",
+        "-l" => "Lines",
+        "-w" => "Words",
+        "-c" => "Characters",
+        "-L" => "Length of the longest line",
+        "--json" => "Counts as JSON, with a total",
+        "--csv" => "Counts as CSV, ending with a total row"),
+    builtin!("head", "head [-n N] [file]", "Show the first lines",
+        "-n" => "Number of lines (default 10)"),
+    builtin!("tail", "tail [-n N] [file]", "Show the last lines",
+        "-n" => "Number of lines (default 10)"),
+    builtin!("sort", "sort [-r] [file]", "Sort lines",
+        "-r" => "Reverse order"),
+    builtin!("uniq", "uniq [-c] [file]", "Remove adjacent duplicate lines",
+        "-c" => "Prefix lines with their count"),
+    builtin!("find", "find [-L] [path] [-name pat] [-maxdepth N] [-exec cmd {} ;]", "Find files",
+        "-name" => "Match file names against a pattern",
+        "-maxdepth" => "Descend at most N levels",
+        "-exec" => "Run cmd per match (end with + to batch)",
+        "-L" => "Descend into symlinked directories",
+        "--git" => "Skip .gitignore'd files"),
+    builtin!("which", "which cmd...", "Show whether a command is an alias, builtin, runtime or program"),
+    builtin!("type", "type [-a] name...", "Show how a name would be run",
+        "-a" => "Show every match, not just the first"),
+    builtin!("command", "command [-vV] name [args...]", "Run or resolve a command, ignoring aliases",
+        "-v" => "Print what name resolves to",
+        "-V" => "Describe what name resolves to"),
+    builtin!("builtin", "builtin name [args...]", "Run a builtin even if something shadows it"),
+    builtin!("wget", "wget [-O file] url", "Download a file",
+        "-O" => "Save to the given file"),
+    builtin!("curl", "curl [-o file] [-O] url", "Transfer data from a URL",
+        "-o" => "Save to the given file",
+        "-O" => "Save under the URL's file name"),
+    builtin!("export", "export [-p] [name[=value]...]", "Set and export variables, or list them",
+        "-p" => "List exported variables as re-sourceable export commands"),
+    builtin!("unset", "unset name...", "Unset variables or array elements"),
+    builtin!("declare", "declare [-airxp] [+ix] [name[=value]...]", "Set variable attributes, or list variables",
+        "-a" => "Indexed array",
+        "-i" => "Integer; assignments are arithmetic",
+        "-r" => "Readonly",
+        "-x" => "Exported",
+        "-p" => "Show variables with their attributes"),
+    builtin!("typeset", "typeset [-airxp] [name[=value]...]", "Same as declare"),
+    builtin!("readonly", "readonly [name[=value]...]", "Same as declare -r"),
+    builtin!("set", "set [-eCfx] [-o option] [-- args...]", "Set shell options or positional parameters",
+        "-e" => "Stop a command line at the first failure",
+        "-C" => "Don't let > overwrite existing files (noclobber; >| still does)",
+        "-f" => "Don't expand glob patterns (noglob)",
+        "-x" => "Print commands before running them",
+        "-o" => "Set a named option (errexit, noclobber, noglob, pipefail, xtrace)",
+        "--" => "Set $1, $2, ... from the remaining args"),
+    builtin!("shopt", "shopt [-s|-u] [-pq] [option...]", "Set or show shopt options (globstar, nocaseglob)",
+        "-s" => "Turn options on",
+        "-u" => "Turn options off",
+        "-p" => "Show as shopt commands",
+        "-q" => "Only report through the exit status"),
+    builtin!("getopts", "getopts spec name [args...]", "Parse the next option into $name and $OPTARG"),
+    builtin!("let", "let expr...", "Integer arithmetic; fails when the result is 0"),
+    builtin!("((", "(( expr ))", "Integer arithmetic; fails when the result is 0"),
+    builtin!("env", "env", "Show variables"),
+    builtin!("printenv", "printenv [name...]", "Print variable values"),
+    builtin!("alias", "alias [name=value...]", "Set or list aliases"),
+    builtin!("unalias", "unalias name...", "Remove aliases"),
+    builtin!("history", "history", "Show history"),
+    builtin!("complete", "complete [-pr] [-W wordlist] [name...]", "Set, show or remove Tab completions for commands",
+        "-W" => "Complete the command's arguments from these words",
+        "-p" => "Show completions as complete commands (the default)",
+        "-r" => "Remove completions (all of them when no name is given)"),
+    builtin!("fc", "fc [-e editor] [-lnr] [first [last]] | fc -s [old=new] [cmd]", "Edit history entries and re-run them, or list them",
+        "-l" => "List the entries (the last 16 by default) instead of editing",
+        "-n" => "List without numbers",
+        "-r" => "Reverse the order",
+        "-e" => "Editor to use (default $FCEDIT, then $EDITOR, then vi)",
+        "-s" => "Re-run without an editor, replacing old with new"),
+    builtin!("umask", "umask [-S] [mode]", "Show or set the file creation mask",
+        "-S" => "Symbolic output"),
+    builtin!("sleep", "sleep seconds", "Sleep"),
+    builtin!("repeat", "repeat [-e] N cmd [args...]", "Run a command N times",
+        "-e" => "Stop at the first failure"),
+    builtin!("kill", "kill pid...", "Terminate processes"),
+    builtin!("source", "source file", "Run a file's commands in this shell; ~/.piebashrc is read at startup"),
+    builtin!(".", ". file", "Same as source"),
+    builtin!("trap", "trap [-p] ['cmd' | -] signal...", "Run a command on exit or signal (EXIT, INT, TERM)",
+        "-p" => "List the traps"),
+    builtin!("jobs", "jobs [-l]", "List background jobs",
+        "-l" => "Include process IDs"),
+    builtin!("fg", "fg [%job]", "Wait for a job in the foreground, passing it Ctrl-C and Ctrl-Z"),
+    builtin!("bg", "bg [%job]", "Resume a stopped job in the background"),
+    builtin!("true", "true", "Return success"),
+    builtin!("false", "false", "Return failure"),
+    builtin!("clear", "clear", "Clear the screen"),
+    builtin!("help", "help [--all] [name]", "Show help",
+        "--all" => "Every builtin as JSON"),
+    builtin!("man", "man name", "Show a builtin's help as a man page, or the system manual for anything else"),
+    builtin!("pip", "pip args...", "Python package manager (installs Python if needed)"),
+    builtin!("npm", "npm args...", "Node package manager (installs Node if needed)"),
+    builtin!("cargo", "cargo args...", "Rust package manager"),
+    builtin!("gem", "gem args...", "Ruby package manager"),
+    builtin!("runtime", "runtime install|upgrade lang... | runtime check | runtime cache [clean]", "Manage language runtimes"),
+];
+
+/// The `help --all` dump: a JSON array of `{name, usage, description, flags}`.
+pub fn all_json() -> Result<String> {
+    Ok(serde_json::to_string_pretty(BUILTINS)?)
+}
+
+/// The entry for `name`, if it's a builtin.
+pub fn find(name: &str) -> Option<&'static BuiltinHelp> {
+    BUILTINS.iter().find(|b| b.name == name)
+}
+
+/// A builtin's entry laid out like a man page.
+pub fn man_page(builtin: &BuiltinHelp) -> String {
+    let title = format!("{}(1)", builtin.name.to_uppercase());
+    let header = "piebash builtins";
+    let gap = 78usize.saturating_sub(2 * title.len() + header.len()) / 2;
+
+    let mut page = format!("{}{:gap$}{}{:gap$}{}\n\n", title, "", header, "", title, gap = gap.max(1));
+    page.push_str(&format!("NAME\n       {} - {}\n\n", builtin.name, builtin.description));
+    page.push_str(&format!("SYNOPSIS\n       {}\n", builtin.usage));
+    if !builtin.flags.is_empty() {
+        page.push_str("\nOPTIONS\n");
+        // Like man's tagged paragraphs: a short flag shares its description's line
+        for flag in builtin.flags {
+            if flag.flag.len() < 7 {
+                page.push_str(&format!("       {:<7}{}\n", flag.flag, flag.description));
+            } else {
+                page.push_str(&format!("       {}\n              {}\n", flag.flag, flag.description));
+            }
+        }
+    }
+    page
+}
+
+/// `man NAME`: piebash's own page for a builtin, the system `man` for anything else.
+pub fn man_cmd(command: &Command) -> Result<()> {
+    let Some(name) = command.args.first() else {
+        anyhow::bail!("man: what manual page do you want?");
+    };
+
+    if let Some(builtin) = find(name) {
+        print!("{}", man_page(builtin));
+        return Ok(());
+    }
+
+    let Ok(man) = which::which("man") else {
+        anyhow::bail!("man: no manual entry for {}", name);
+    };
+    let status = std::process::Command::new(man).args(&command.args).status()?;
+    if !status.success() {
+        return Err(PieBashError::from_status(&status).into());
+    }
+    Ok(())
+}
+
+/// `help` prints the reference, `help NAME` one builtin, and `help --all` every
+/// builtin as JSON for completion generators and docs tools.
+pub fn help_cmd(command: &Command) -> Result<()> {
+    match command.args.first().map(String::as_str) {
+        None => super::core::help(),
+        Some("--all") => {
+            println!("{}", all_json()?);
+            Ok(())
+        }
+        Some(name) => {
+            let Some(builtin) = find(name) else {
+                anyhow::bail!("help: no help topics match '{}'", name);
+            };
+
+            println!("{}", builtin.usage.bold());
+            println!("    {}", builtin.description);
+            if !builtin.flags.is_empty() {
+                println!();
+                let width = builtin.flags.iter().map(|f| f.flag.len()).max().unwrap_or(0);
+                for flag in builtin.flags {
+                    println!("    {:<width$}  {}", flag.flag, flag.description, width = width);
+                }
+            }
+            Ok(())
+        }
+    }
+}
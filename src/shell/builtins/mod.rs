@@ -1,9 +1,12 @@
 pub mod core;
+pub mod help;
 pub mod filesystem;
 pub mod text;
 pub mod network;
 pub mod utils;
 pub mod packages;
+pub mod runtime;
+pub mod trash;
 
 use anyhow::Result;
 use crate::shell::parser::Command;
@@ -18,18 +21,26 @@ impl Builtins {
     pub fn new() -> Self {
         Self {
             commands: vec![
-                "cd", "pwd", "echo", "export", "env", "set", "unset",
-                "alias", "unalias", "help", "clear", "history",
-                "ls", "cat", "touch", "mkdir", "rm", "cp", "mv", "ln",
-                "chmod", "chown", "stat", "file",
+                "cd", "pwd", "pushd", "popd", "dirs", "echo", "export", "env", "printenv", "set", "shopt", "unset",
+                "declare", "typeset", "readonly", "getopts", "let", "((",
+                "alias", "unalias", "help", "man", "clear", "history", "fc", "complete",
+                "jobs", "fg", "bg",
+                "ls", "cat", "touch", "mkdir", "mktemp", "rm", "trash", "cp", "mv", "ln",
+                "chmod", "chown", "stat", "file", "du",
                 "grep", "find", "wc", "head", "tail", "sort", "uniq", "which",
                 "wget", "curl",
-                "true", "false", "sleep", "kill", "type",
+                "true", "false", "sleep", "kill", "umask", "type", "command", "builtin", "repeat", "trap",
+                "source", ".",
                 "pip", "npm", "cargo", "gem",  // ADDED: Package managers
+                "runtime",
             ].into_iter().map(String::from).collect(),
         }
     }
 
+    pub fn commands(&self) -> &[String] {
+        &self.commands
+    }
+
     pub fn is_builtin(&self, name: &str) -> bool {
         self.commands.contains(&name.to_string())
     }
@@ -38,28 +49,48 @@ impl Builtins {
         match command.name.as_str() {
             "cd"       => core::cd(command, env),
             "pwd"      => core::pwd(env),
+            "pushd"    => core::pushd(command, env),
+            "popd"     => core::popd(command, env),
+            "dirs"     => core::dirs(command, env),
             "echo"     => core::echo(command),
             "export"   => core::export(command, env),
             "env"      => core::env_cmd(env),
+            "printenv" => core::printenv(command, env),
             "set"      => core::set_cmd(command, env),
+            "shopt"    => core::shopt_cmd(command, env),
             "unset"    => core::unset(command, env),
+            "declare" | "typeset" | "readonly" => core::declare(command, env),
+            "getopts"  => core::getopts(command, env),
+            "let" | "((" => core::let_cmd(command, env),
             "alias"    => core::alias_cmd(command, env),
             "unalias"  => core::unalias_cmd(command, env),
             "history"  => core::history_cmd(env),
-            "type"     => core::type_cmd(command),
-            "help"     => core::help(),
+            "complete" => core::complete_cmd(command, env),
+            "type"     => core::type_cmd(command, env, self),
+            "command"  => core::command_cmd(command, env, self),
+            "builtin"  => Ok(()),  // `builtin name ...` is dispatched by the shell
+            "repeat"   => Ok(()),  // so is `repeat N cmd ...`
+            "fc"       => Ok(()),  // and `fc`, which re-runs history entries
+            "trap"     => Ok(()),  // and `trap`, whose commands the shell keeps
+            "source" | "." => Ok(()),  // and `source`, which runs a file's lines in the shell
+            "jobs" | "fg" | "bg" => Ok(()),  // and the job commands, as the shell owns its jobs
+            "help"     => help::help_cmd(command),
+            "man"      => help::man_cmd(command),
             "clear"    => core::clear(),
             "true"     => core::true_cmd(),
             "false"    => core::false_cmd(),
             "yes"      => core::yes_cmd(command),
             "sleep"    => core::sleep_cmd(command),
             "kill"     => core::kill_cmd(command),
+            "umask"    => core::umask_cmd(command),
 
             "ls"       => filesystem::ls(command, env),
             "cat"      => filesystem::cat(command),
             "touch"    => filesystem::touch(command),
             "mkdir"    => filesystem::mkdir(command),
+            "mktemp"   => filesystem::mktemp(command),
             "rm"       => filesystem::rm(command),
+            "trash"    => trash::trash_cmd(command, env),
             "cp"       => filesystem::cp(command),
             "mv"       => filesystem::mv(command),
             "ln"       => filesystem::ln(command),
@@ -67,16 +98,17 @@ impl Builtins {
             "chown"    => filesystem::chown(command),
             "stat"     => filesystem::stat(command),
             "file"     => filesystem::file_cmd(command),
+            "du"       => filesystem::du(command),
 
             "grep"     => text::grep(command),
 
-            "find"     => utils::find(command),
+            "find"     => utils::find(command, env),
             "wc"       => utils::wc(command),
             "head"     => utils::head(command),
             "tail"     => utils::tail(command),
             "sort"     => utils::sort_cmd(command),
             "uniq"     => utils::uniq_cmd(command),
-            "which"    => utils::which_cmd(command),
+            "which"    => utils::which_cmd(command, env, self),
 
             _          => anyhow::bail!("Unknown built-in: {}", command.name),
         }
@@ -93,33 +125,69 @@ impl Builtins {
             "curl" => network::curl(command).await,
             "pip" => {
                 if let Some(rm) = runtime_manager {
-                    packages::pip_install(command, rm).await
+                    packages::pip_cmd(command, rm).await
                 } else {
                     anyhow::bail!("pip: runtime manager not available")
                 }
             }
             "npm" => {
                 if let Some(rm) = runtime_manager {
-                    packages::npm_install(command, rm).await
+                    packages::npm_cmd(command, rm).await
                 } else {
                     anyhow::bail!("npm: runtime manager not available")
                 }
             }
             "cargo" => {
                 if let Some(rm) = runtime_manager {
-                    packages::cargo_install(command, rm).await
+                    packages::cargo_cmd(command, rm).await
                 } else {
                     anyhow::bail!("cargo: runtime manager not available")
                 }
             }
             "gem" => {
                 if let Some(rm) = runtime_manager {
-                    packages::gem_install(command, rm).await
+                    packages::gem_cmd(command, rm).await
                 } else {
                     anyhow::bail!("gem: runtime manager not available")
                 }
             }
+            "runtime" => {
+                if let Some(rm) = runtime_manager {
+                    runtime::runtime_cmd(command, rm).await
+                } else {
+                    anyhow::bail!("runtime: runtime manager not available")
+                }
+            }
             _ => self.execute(command, env),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Asks a yes/no question and reads the answer from the terminal.
+///
+/// Builtins run between `readline` calls, so the REPL isn't holding stdin and a plain
+/// `read_line` is safe. When stdin is a script or pipe, its next line belongs to the
+/// script, so nothing is read and `default` is returned, as it is for an empty answer.
+pub fn confirm(prompt: &str, default: bool) -> bool {
+    use std::io::{IsTerminal, Write};
+
+    let hint = if default { "[Y/n]" } else { "[y/N]" };
+    print!("{} {} ", prompt, hint);
+    std::io::stdout().flush().ok();
+
+    if !std::io::stdin().is_terminal() {
+        println!();
+        return default;
+    }
+
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return default;
+    }
+
+    match answer.trim().to_lowercase().as_str() {
+        "" => default,
+        "y" | "yes" => true,
+        _ => false,
+    }
+}
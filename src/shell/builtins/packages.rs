@@ -1,14 +1,16 @@
 use anyhow::Result;
 use colored::*;
 use tokio::process::Command;
+use std::fs;
+use std::path::Path;
 use std::process::Stdio;
 
 use crate::shell::parser::Command as ShellCommand;
 use crate::runtime::RuntimeManager;
 
-pub async fn pip_install(command: &ShellCommand, runtime_manager: &RuntimeManager) -> Result<()> {
+pub async fn pip_cmd(command: &ShellCommand, runtime_manager: &RuntimeManager) -> Result<()> {
     if command.args.is_empty() {
-        anyhow::bail!("pip: missing package name");
+        anyhow::bail!("pip: missing command");
     }
 
     // Ensure Python runtime is installed
@@ -26,29 +28,16 @@ pub async fn pip_install(command: &ShellCommand, runtime_manager: &RuntimeManage
         anyhow::bail!("pip not found in Python installation");
     }
 
-    println!("{} Installing Python packages...", "[PIP]".cyan().bold());
-
-    // Run pip install
-    let mut cmd = Command::new(&pip_path);
-    cmd.arg("install");
-    cmd.args(&command.args);
-    cmd.stdin(Stdio::inherit());
-    cmd.stdout(Stdio::inherit());
-    cmd.stderr(Stdio::inherit());
-
-    let status = cmd.status().await?;
-
-    if !status.success() {
-        anyhow::bail!("pip install failed");
+    if command.args[0] == "install" {
+        println!("{} Installing Python packages...", "[PIP]".cyan().bold());
     }
 
-    println!("{} Installation complete!", "[OK]".green().bold());
-    Ok(())
+    run_package_manager("pip", &pip_path, &command.args).await
 }
 
-pub async fn npm_install(command: &ShellCommand, runtime_manager: &RuntimeManager) -> Result<()> {
+pub async fn npm_cmd(command: &ShellCommand, runtime_manager: &RuntimeManager) -> Result<()> {
     if command.args.is_empty() {
-        anyhow::bail!("npm: missing package name");
+        anyhow::bail!("npm: missing command");
     }
 
     let node_runtime = runtime_manager.ensure_runtime("node").await?;
@@ -63,29 +52,17 @@ pub async fn npm_install(command: &ShellCommand, runtime_manager: &RuntimeManage
         anyhow::bail!("npm not found in Node.js installation");
     }
 
-    println!("{} Installing Node.js packages...", "[NPM]".cyan().bold());
-
-    let mut cmd = Command::new(&npm_path);
-    cmd.arg("install");
-    cmd.arg("-g");
-    cmd.args(&command.args);
-    cmd.stdin(Stdio::inherit());
-    cmd.stdout(Stdio::inherit());
-    cmd.stderr(Stdio::inherit());
-
-    let status = cmd.status().await?;
-
-    if !status.success() {
-        anyhow::bail!("npm install failed");
+    if command.args[0] == "install" {
+        println!("{} Installing Node.js packages...", "[NPM]".cyan().bold());
     }
 
-    println!("{} Installation complete!", "[OK]".green().bold());
-    Ok(())
+    // Local install into the cwd's node_modules unless the user passes -g
+    run_package_manager("npm", &npm_path, &command.args).await
 }
 
-pub async fn cargo_install(command: &ShellCommand, runtime_manager: &RuntimeManager) -> Result<()> {
+pub async fn cargo_cmd(command: &ShellCommand, runtime_manager: &RuntimeManager) -> Result<()> {
     if command.args.is_empty() {
-        anyhow::bail!("cargo: missing package name");
+        anyhow::bail!("cargo: missing command");
     }
 
     let rust_runtime = runtime_manager.ensure_runtime("rust").await?;
@@ -100,28 +77,16 @@ pub async fn cargo_install(command: &ShellCommand, runtime_manager: &RuntimeMana
         anyhow::bail!("cargo not found in Rust installation");
     }
 
-    println!("{} Installing Rust packages...", "[CARGO]".cyan().bold());
-
-    let mut cmd = Command::new(&cargo_path);
-    cmd.arg("install");
-    cmd.args(&command.args);
-    cmd.stdin(Stdio::inherit());
-    cmd.stdout(Stdio::inherit());
-    cmd.stderr(Stdio::inherit());
-
-    let status = cmd.status().await?;
-
-    if !status.success() {
-        anyhow::bail!("cargo install failed");
+    if command.args[0] == "install" {
+        println!("{} Installing Rust packages...", "[CARGO]".cyan().bold());
     }
 
-    println!("{} Installation complete!", "[OK]".green().bold());
-    Ok(())
+    run_package_manager("cargo", &cargo_path, &command.args).await
 }
 
-pub async fn gem_install(command: &ShellCommand, runtime_manager: &RuntimeManager) -> Result<()> {
+pub async fn gem_cmd(command: &ShellCommand, runtime_manager: &RuntimeManager) -> Result<()> {
     if command.args.is_empty() {
-        anyhow::bail!("gem: missing package name");
+        anyhow::bail!("gem: missing command");
     }
 
     let ruby_runtime = runtime_manager.ensure_runtime("ruby").await?;
@@ -136,11 +101,66 @@ pub async fn gem_install(command: &ShellCommand, runtime_manager: &RuntimeManage
         anyhow::bail!("gem not found in Ruby installation");
     }
 
-    println!("{} Installing Ruby gems...", "[GEM]".cyan().bold());
+    if command.args[0] == "install" {
+        println!("{} Installing Ruby gems...", "[GEM]".cyan().bold());
+    }
+
+    run_package_manager("gem", &gem_path, &command.args).await
+}
+
+/// The language whose packages a package manager installs.
+pub fn language_of(manager: &str) -> Option<&'static str> {
+    match manager {
+        "pip" => Some("python"),
+        "npm" => Some("node"),
+        "cargo" => Some("rust"),
+        "gem" => Some("ruby"),
+        _ => None,
+    }
+}
+
+/// The packages piebash has installed for `language`, by `pip install` and the
+/// like or by auto-install, sorted.
+pub fn installed(language: &str) -> Vec<String> {
+    fs::read_to_string(crate::platform::adapter::paths().packages_dir().join(language))
+        .map(|text| text.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Options whose value follows as the next argument, like `pip install -r reqs.txt`.
+const VALUE_OPTIONS: &[&str] = &["-r", "-c", "-e", "-i", "-t", "--requirement", "--constraint", "--editable", "--index-url", "--target"];
+
+/// Adds `names` to `language`'s installed list. A version (`requests==2.31`,
+/// `lodash@4`) is dropped, and paths, URLs and options aren't names at all.
+pub fn record_installed<'a>(language: &str, names: impl IntoIterator<Item = &'a str>) {
+    let mut packages = installed(language);
+    let mut names = names.into_iter();
+    while let Some(name) = names.next() {
+        if VALUE_OPTIONS.contains(&name) {
+            names.next();
+            continue;
+        }
+        // A scoped npm package, `@types/node`, is the one name with a `/` in it
+        let path = name.contains(['\\', ':']) || (name.contains('/') && !name.starts_with('@'));
+        if name.is_empty() || name.starts_with(['-', '.']) || path {
+            continue;
+        }
+        let end = name.char_indices().skip(1).find(|(_, c)| "=<>~!@[ ".contains(*c)).map_or(name.len(), |(i, _)| i);
+        packages.push(name[..end].to_string());
+    }
+    packages.sort();
+    packages.dedup();
+
+    let dir = crate::platform::adapter::paths().packages_dir();
+    // Completion is a convenience; failing to remember a name isn't worth an error
+    let _ = fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(dir.join(language), packages.join("\n") + "\n"));
+}
 
-    let mut cmd = Command::new(&gem_path);
-    cmd.arg("install");
-    cmd.args(&command.args);
+/// Runs the package manager with `args` forwarded verbatim (`install`, `uninstall`, `list`, ...).
+async fn run_package_manager(name: &str, path: &Path, args: &[String]) -> Result<()> {
+    let mut cmd = Command::new(path);
+    cmd.args(args);
     cmd.stdin(Stdio::inherit());
     cmd.stdout(Stdio::inherit());
     cmd.stderr(Stdio::inherit());
@@ -148,9 +168,14 @@ pub async fn gem_install(command: &ShellCommand, runtime_manager: &RuntimeManage
     let status = cmd.status().await?;
 
     if !status.success() {
-        anyhow::bail!("gem install failed");
+        anyhow::bail!("{} {} failed", name, args[0]);
     }
 
-    println!("{} Installation complete!", "[OK]".green().bold());
+    if args[0] == "install" {
+        if let Some(language) = language_of(name) {
+            record_installed(language, args[1..].iter().map(String::as_str));
+        }
+        println!("{} Installation complete!", "[OK]".green().bold());
+    }
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,151 @@
+use anyhow::Result;
+use colored::*;
+
+use super::filesystem::format_size;
+use crate::shell::parser::Command;
+use crate::runtime::RuntimeManager;
+
+pub async fn runtime_cmd(command: &Command, runtime_manager: &RuntimeManager) -> Result<()> {
+    match command.args.first().map(String::as_str) {
+        Some("install") => install(&command.args[1..], runtime_manager).await,
+        Some("cache") => cache(&command.args[1..], runtime_manager),
+        Some("check") => check(runtime_manager).await,
+        Some("upgrade") => upgrade(&command.args[1..], runtime_manager).await,
+        Some(other) => anyhow::bail!("runtime: unknown subcommand '{}'", other),
+        None => anyhow::bail!("runtime: missing subcommand (try 'runtime install <lang>...')"),
+    }
+}
+
+/// Downloads and installs every requested runtime at once, then reports each outcome.
+async fn install(languages: &[String], runtime_manager: &RuntimeManager) -> Result<()> {
+    if languages.is_empty() {
+        anyhow::bail!("runtime install: missing language (e.g. 'runtime install python node')");
+    }
+
+    // A task per language so downloads and extraction actually overlap
+    let tasks = languages.iter().map(|lang| {
+        let runtime_manager = runtime_manager.clone();
+        let lang = lang.clone();
+        tokio::spawn(async move { runtime_manager.ensure_runtime(&lang).await })
+    });
+    let results = futures::future::join_all(tasks).await;
+
+    println!();
+    let mut failed = 0;
+    for (lang, result) in languages.iter().zip(results) {
+        match result.map_err(anyhow::Error::from).and_then(|r| r) {
+            Ok(info) => println!(
+                "{} {} {} ({})",
+                "[OK]".green().bold(),
+                info.language,
+                info.version,
+                info.path.display()
+            ),
+            Err(e) => {
+                failed += 1;
+                println!("{} {}: {}", "[FAIL]".red().bold(), lang, e);
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("runtime install: {} of {} runtimes failed", failed, languages.len());
+    }
+
+    Ok(())
+}
+
+/// Compares each installed runtime with the version the registry pins.
+async fn check(runtime_manager: &RuntimeManager) -> Result<()> {
+    let runtimes = runtime_manager.installed_runtimes().await;
+    if runtimes.is_empty() {
+        println!("No runtimes installed");
+        return Ok(());
+    }
+
+    let mut outdated = 0;
+    for info in &runtimes {
+        let latest = runtime_manager.get_language(&info.language)?.version;
+        if runtime_manager.is_outdated(info)? {
+            outdated += 1;
+            println!("{} {} {} -> {}", "[OUTDATED]".yellow().bold(), info.language, info.version, latest);
+        } else {
+            println!("{} {} {}", "[OK]".green().bold(), info.language, info.version);
+        }
+    }
+
+    if outdated > 0 {
+        println!("{} Run 'runtime upgrade <lang>' to update", "[HINT]".cyan().bold());
+    }
+    Ok(())
+}
+
+/// Replaces each named runtime (every outdated one when none are named) with the
+/// registry's version.
+async fn upgrade(languages: &[String], runtime_manager: &RuntimeManager) -> Result<()> {
+    let languages: Vec<String> = if languages.is_empty() {
+        let mut outdated = Vec::new();
+        for info in runtime_manager.installed_runtimes().await {
+            if runtime_manager.is_outdated(&info)? {
+                outdated.push(info.language);
+            }
+        }
+        outdated
+    } else {
+        languages.to_vec()
+    };
+
+    if languages.is_empty() {
+        println!("{} All runtimes are up to date", "[OK]".green().bold());
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for lang in &languages {
+        match runtime_manager.upgrade_runtime(lang).await {
+            Ok(Some((old, info))) => println!(
+                "{} {} {} -> {} ({})",
+                "[OK]".green().bold(),
+                info.language,
+                old,
+                info.version,
+                info.path.display()
+            ),
+            Ok(None) => println!("{} {} is up to date", "[OK]".green().bold(), lang),
+            Err(e) => {
+                failed += 1;
+                println!("{} {}: {}", "[FAIL]".red().bold(), lang, e);
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("runtime upgrade: {} of {} runtimes failed", failed, languages.len());
+    }
+
+    Ok(())
+}
+
+/// `runtime cache` shows what the download cache holds; `runtime cache clean` empties it.
+fn cache(args: &[String], runtime_manager: &RuntimeManager) -> Result<()> {
+    let downloader = runtime_manager.downloader();
+
+    match args.first().map(String::as_str) {
+        None => {
+            let entries = downloader.cache_entries()?;
+            for (path, size, _) in &entries {
+                let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+                println!("{:>8}  {}", format_size(*size), name);
+            }
+            let total = entries.iter().map(|(_, size, _)| size).sum();
+            println!("{} archive(s), {} total", entries.len(), format_size(total));
+            Ok(())
+        }
+        Some("clean") => {
+            let (count, freed) = downloader.clean_cache()?;
+            println!("{} Removed {} archive(s), freed {}", "[OK]".green().bold(), count, format_size(freed));
+            Ok(())
+        }
+        Some(other) => anyhow::bail!("runtime cache: unknown subcommand '{}'", other),
+    }
+}
@@ -1,39 +1,304 @@
 use anyhow::Result;
 use colored::*;
+use std::collections::VecDeque;
 use std::fs;
+use std::io::{BufRead, BufReader};
 use std::path::Path;
 use regex::Regex;
 
 use crate::shell::parser::Command;
+use crate::utils::error::PieBashError;
+
+#[derive(Default)]
+struct GrepOptions {
+    color: bool,
+    count: bool,
+    files_with_matches: bool,
+    files_without_match: bool,
+    word: bool,
+    line: bool,
+    fixed: bool,
+    quiet: bool,
+    max_count: Option<usize>,
+    before: usize,
+    after: usize,
+}
 
 pub fn grep(command: &Command) -> Result<()> {
-    if command.args.is_empty() {
+    let (opts, operands) = parse_grep_args(&command.args)?;
+
+    if operands.is_empty() {
         anyhow::bail!("grep: missing pattern");
     }
+    let regex = grep_regex(&opts, operands[0])?;
 
-    let pattern = &command.args[0];
-    let regex = Regex::new(pattern)?;
-
-    if command.args.len() == 1 {
+    if operands.len() == 1 {
         anyhow::bail!("grep: missing file operand");
     }
 
-    for file in &command.args[1..] {
-        let path = Path::new(file.as_str());  
-        
+    let mut any_match = false;
+    for file in &operands[1..] {
+        let path = Path::new(file.as_str());
+
         if !path.exists() {
             eprintln!("grep: {}: No such file or directory", file);
             continue;
         }
 
-        let contents = fs::read_to_string(path)?;
-        
-        for (line_num, line) in contents.lines().enumerate() {
-            if regex.is_match(line) {
-                println!("{}:{}:{}", file.cyan(), (line_num + 1).to_string().green(), line);
+        // Read line by line, so a multi-gigabyte log never sits in memory whole
+        let lines = BufReader::new(fs::File::open(path)?).lines();
+
+        // -q: the first match anywhere settles the exit status, so stop there
+        if opts.quiet {
+            for line in lines {
+                if regex.is_match(&line?) {
+                    return Ok(());
+                }
+            }
+            continue;
+        }
+
+        // Names only: stop reading the file at the first hit
+        if opts.files_with_matches || opts.files_without_match {
+            let mut found = false;
+            for line in lines {
+                if regex.is_match(&line?) {
+                    found = true;
+                    break;
+                }
+            }
+            any_match |= found;
+            if found == opts.files_with_matches {
+                println!("{}", file.cyan());
+            }
+            continue;
+        }
+
+        if opts.count {
+            let mut count = 0;
+            for line in lines {
+                if opts.max_count.is_some_and(|max| count >= max) {
+                    break;
+                }
+                if regex.is_match(&line?) {
+                    count += 1;
+                }
+            }
+            any_match |= count > 0;
+            println!("{}:{}", file.cyan(), count);
+            continue;
+        }
+
+        let mut last_shown: Option<usize> = None;
+        let mut print_line = |idx: usize, line: &str, matched: bool| {
+            // GNU-style `--` between groups that aren't adjacent
+            if opts.before + opts.after > 0 && last_shown.is_some_and(|last| idx > last + 1) {
+                println!("{}", "--".cyan());
+            }
+            last_shown = Some(idx);
+
+            let (sep, text) = if matched {
+                let text = if opts.color { highlight(&regex, line) } else { line.to_string() };
+                (":", text)
+            } else {
+                ("-", line.to_string())
+            };
+            println!("{}{}{}{}{}", file.cyan(), sep, (idx + 1).to_string().green(), sep, text);
+        };
+
+        // Only the last `-B` lines are kept, and `-A` counts down after each match
+        let mut before: VecDeque<(usize, String)> = VecDeque::with_capacity(opts.before);
+        let mut after_left = 0;
+        let mut matches = 0;
+        for (idx, line) in lines.enumerate() {
+            // -m: past the limit only the last match's trailing context is left to print
+            let limit_hit = opts.max_count.is_some_and(|max| matches >= max);
+            if limit_hit && after_left == 0 {
+                break;
+            }
+
+            let line = line?;
+            if !limit_hit && regex.is_match(&line) {
+                any_match = true;
+                matches += 1;
+                for (before_idx, before_line) in before.drain(..) {
+                    print_line(before_idx, &before_line, false);
+                }
+                print_line(idx, &line, true);
+                after_left = opts.after;
+            } else if after_left > 0 {
+                print_line(idx, &line, false);
+                after_left -= 1;
+            } else if opts.before > 0 {
+                if before.len() == opts.before {
+                    before.pop_front();
+                }
+                before.push_back((idx, line));
             }
         }
     }
 
+    // Like grep(1): nothing selected is exit status 1, not an error
+    if !any_match {
+        return Err(PieBashError::ExitStatus(1).into());
+    }
+
     Ok(())
-}
\ No newline at end of file
+}
+
+/// The regex for grep's pattern, with `-F`, `-x` and `-w` applied.
+fn grep_regex(opts: &GrepOptions, pattern: &str) -> Result<Regex> {
+    let literal = if opts.fixed { regex::escape(pattern) } else { pattern.to_string() };
+    let full = if opts.line {
+        format!("^(?:{})$", literal)
+    } else if opts.word {
+        format!(r"\b(?:{})\b", literal)
+    } else {
+        literal
+    };
+    Regex::new(&full).map_err(|e| {
+        let reason = match e {
+            regex::Error::Syntax(msg) => {
+                let last = msg.lines().last().unwrap_or_default().trim();
+                last.trim_start_matches("error: ").to_string()
+            }
+            other => other.to_string(),
+        };
+        anyhow::anyhow!(
+            "grep: invalid regex '{}': {}\n  hint: use 'grep -F' to search for the text literally",
+            pattern, reason
+        )
+    })
+}
+
+/// Whether `command` reads what's piped or fed to it instead of files, so the shell
+/// can run it on text: `grep` with only a pattern, `cat` and `wc` with no files.
+pub fn reads_stdin(command: &Command) -> bool {
+    let no_files = || command.args.iter().all(|arg| arg.starts_with('-'));
+    match command.name.as_str() {
+        "grep" => parse_grep_args(&command.args).is_ok_and(|(_, operands)| operands.len() == 1),
+        "cat" | "wc" => no_files(),
+        _ => false,
+    }
+}
+
+/// Runs a command [`reads_stdin`] accepts on `input`, returning what it prints and
+/// whether it succeeded (grep fails when nothing matched).
+pub fn filter(command: &Command, input: &str) -> Result<(String, bool)> {
+    match command.name.as_str() {
+        "grep" => grep_input(command, input),
+        "cat" => Ok((input.to_string(), true)),
+        "wc" => Ok((wc_input(command, input), true)),
+        other => anyhow::bail!("{}: can't read a pipe", other),
+    }
+}
+
+/// grep over piped text. Context options don't apply; there are no file names to show.
+fn grep_input(command: &Command, input: &str) -> Result<(String, bool)> {
+    let (opts, operands) = parse_grep_args(&command.args)?;
+    let pattern = operands.first().ok_or_else(|| anyhow::anyhow!("grep: missing pattern"))?;
+    let regex = grep_regex(&opts, pattern)?;
+
+    let limit = opts.max_count.unwrap_or(usize::MAX);
+    let matches: Vec<&str> = input.lines().filter(|line| regex.is_match(line)).take(limit).collect();
+    let output = if opts.quiet {
+        String::new()
+    } else if opts.count {
+        format!("{}\n", matches.len())
+    } else {
+        matches.iter().map(|line| {
+            let text = if opts.color { highlight(&regex, line) } else { line.to_string() };
+            text + "\n"
+        }).collect()
+    };
+    Ok((output, !matches.is_empty()))
+}
+
+/// `wc [-lwcL]` over piped text: the requested counts, lines, words and characters by default.
+fn wc_input(command: &Command, input: &str) -> String {
+    let has = |flag: &str| command.args.iter().any(|arg| arg == flag);
+    let counts = [
+        (has("-l"), input.lines().count()),
+        (has("-w"), input.split_whitespace().count()),
+        (has("-c"), input.len()),
+        (has("-L"), input.lines().map(|line| line.chars().count()).max().unwrap_or(0)),
+    ];
+
+    let chosen: Vec<usize> = counts.iter().filter(|(wanted, _)| *wanted).map(|(_, n)| *n).collect();
+    match chosen.as_slice() {
+        [] => format!("{:>7} {:>7} {:>7}\n", counts[0].1, counts[1].1, counts[2].1),
+        [only] => format!("{}\n", only),
+        many => many.iter().map(|n| format!("{:>7}", n)).collect::<Vec<_>>().join(" ") + "\n",
+    }
+}
+
+/// Splits grep's argv into options and operands (pattern first, then files).
+fn parse_grep_args(args: &[String]) -> Result<(GrepOptions, Vec<&String>)> {
+    let mut opts = GrepOptions::default();
+    let mut operands = Vec::new();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--color" | "--colour" | "--color=always" | "--color=auto" => opts.color = true,
+            "--color=never" => opts.color = false,
+            "--fixed-strings" => opts.fixed = true,
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                for (i, ch) in arg.char_indices().skip(1) {
+                    match ch {
+                        'c' => opts.count = true,
+                        'l' => opts.files_with_matches = true,
+                        'L' => opts.files_without_match = true,
+                        'w' => opts.word = true,
+                        'x' => opts.line = true,
+                        'F' => opts.fixed = true,
+                        'q' => opts.quiet = true,
+                        'A' | 'B' | 'C' | 'm' => {
+                            // `-A 3` or `-A3`
+                            let rest = &arg[i + 1..];
+                            let value = if rest.is_empty() {
+                                match iter.next() {
+                                    Some(v) => v.as_str(),
+                                    None => anyhow::bail!("grep: option requires an argument -- '{}'", ch),
+                                }
+                            } else {
+                                rest
+                            };
+                            let n = value.parse::<usize>().map_err(|_| match ch {
+                                'm' => anyhow::anyhow!("grep: invalid max count"),
+                                _ => anyhow::anyhow!("grep: {}: invalid context length argument", value),
+                            })?;
+                            match ch {
+                                'm' => opts.max_count = Some(n),
+                                'A' => opts.after = n,
+                                'B' => opts.before = n,
+                                _ => {
+                                    opts.after = n;
+                                    opts.before = n;
+                                }
+                            }
+                            break;
+                        }
+                        _ => anyhow::bail!("grep: invalid option -- '{}'", ch),
+                    }
+                }
+            }
+            _ => operands.push(arg),
+        }
+    }
+
+    Ok((opts, operands))
+}
+
+/// Paints each match span of `line` red, leaving the rest untouched.
+fn highlight(regex: &Regex, line: &str) -> String {
+    let mut out = String::new();
+    let mut last = 0;
+    for m in regex.find_iter(line).filter(|m| !m.is_empty()) {
+        out.push_str(&line[last..m.start()]);
+        out.push_str(&m.as_str().red().bold().to_string());
+        last = m.end();
+    }
+    out.push_str(&line[last..]);
+    out
+}
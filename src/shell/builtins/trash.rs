@@ -0,0 +1,177 @@
+use anyhow::{Context, Result};
+use colored::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::shell::parser::Command;
+use crate::shell::environment::Environment;
+
+/// `PIEBASH_TRASH=1` makes every `rm` move files to the trash, as `rm --trash` does.
+pub fn enabled() -> bool {
+    std::env::var("PIEBASH_TRASH").is_ok_and(|v| !v.is_empty() && v != "0")
+}
+
+/// The trash follows the freedesktop.org layout: the items in `files/`, and a
+/// `.trashinfo` in `info/` recording where each came from and when.
+fn trash_dirs() -> (PathBuf, PathBuf) {
+    let trash = crate::platform::adapter::paths().trash_dir();
+    (trash.join("files"), trash.join("info"))
+}
+
+/// Moves `path` to the trash under a timestamped name, which is returned.
+pub fn move_to_trash(path: &Path) -> Result<String> {
+    let (files, info) = trash_dirs();
+    fs::create_dir_all(&files)?;
+    fs::create_dir_all(&info)?;
+
+    let original = std::env::current_dir()?.join(path);
+    let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let now = chrono::Local::now();
+    let stamp = now.format("%Y%m%d-%H%M%S");
+    let mut name = format!("{}_{}", stamp, file_name);
+    let mut n = 1;
+    while files.join(&name).exists() || info.join(format!("{}.trashinfo", name)).exists() {
+        n += 1;
+        name = format!("{}-{}_{}", stamp, n, file_name);
+    }
+
+    fs::write(
+        info.join(format!("{}.trashinfo", name)),
+        format!("[Trash Info]\nPath={}\nDeletionDate={}\n", original.display(), now.format("%Y-%m-%dT%H:%M:%S")),
+    )?;
+    if let Err(e) = move_path(path, &files.join(&name)) {
+        fs::remove_file(info.join(format!("{}.trashinfo", name))).ok();
+        return Err(e);
+    }
+    Ok(name)
+}
+
+/// `rename`, or copy then delete when the two paths are on different filesystems.
+fn move_path(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    let copied = if fs::symlink_metadata(from)?.is_dir() {
+        copy_dir(from, to)
+    } else {
+        fs::copy(from, to).map(|_| ())
+    };
+    if let Err(e) = copied {
+        // Don't leave half a copy behind; the original is untouched
+        if to.is_dir() { fs::remove_dir_all(to).ok(); } else { fs::remove_file(to).ok(); }
+        return Err(e).with_context(|| format!("cannot move '{}' to '{}'", from.display(), to.display()));
+    }
+
+    if from.is_dir() {
+        fs::remove_dir_all(from)?;
+    } else {
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &to.join(entry.file_name()))?;
+        } else {
+            fs::copy(entry.path(), to.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+/// A trashed item: its name in the trash, where it came from, and when it was deleted.
+struct TrashEntry {
+    name: String,
+    original: PathBuf,
+    deleted: String,
+}
+
+/// Everything in the trash, oldest first.
+fn entries() -> Result<Vec<TrashEntry>> {
+    let (files, info) = trash_dirs();
+    let Ok(dir) = fs::read_dir(&info) else {
+        return Ok(Vec::new());
+    };
+
+    let mut entries = Vec::new();
+    for entry in dir {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()).and_then(|n| n.strip_suffix(".trashinfo")) else {
+            continue;
+        };
+        if fs::symlink_metadata(files.join(name)).is_err() {
+            continue;
+        }
+        let text = fs::read_to_string(&path)?;
+        let field = |key: &str| text.lines().find_map(|line| line.strip_prefix(key)).unwrap_or("").to_string();
+        entries.push(TrashEntry { name: name.to_string(), original: PathBuf::from(field("Path=")), deleted: field("DeletionDate=") });
+    }
+    entries.sort_by(|a, b| a.deleted.cmp(&b.deleted).then_with(|| a.name.cmp(&b.name)));
+    Ok(entries)
+}
+
+/// `trash list`, `trash restore name|path...` and `trash empty`.
+pub fn trash_cmd(command: &Command, env: &Environment) -> Result<()> {
+    match command.args.first().map(String::as_str) {
+        Some("list") | None => list(),
+        Some("restore") => restore(&command.args[1..], env),
+        Some("empty") => empty(),
+        Some(other) => anyhow::bail!("trash: unknown subcommand '{}' (list, restore, empty)", other),
+    }
+}
+
+fn list() -> Result<()> {
+    let entries = entries()?;
+    for entry in &entries {
+        println!("{}  {}  {}", entry.deleted.replace('T', " "), entry.name, entry.original.display());
+    }
+    if entries.is_empty() {
+        println!("trash is empty");
+    }
+    Ok(())
+}
+
+/// Puts items back where they were deleted from. An operand is a name from `trash
+/// list`, or the original path, which restores the most recently deleted copy.
+fn restore(operands: &[String], env: &Environment) -> Result<()> {
+    if operands.is_empty() {
+        anyhow::bail!("trash restore: missing operand (a name from 'trash list', or the original path)");
+    }
+
+    let (files, info) = trash_dirs();
+    let entries = entries()?;
+    for operand in operands {
+        let original = env.get_cwd().join(operand);
+        let entry = entries
+            .iter()
+            .find(|entry| entry.name == *operand)
+            .or_else(|| entries.iter().rev().find(|entry| entry.original == original))
+            .ok_or_else(|| anyhow::anyhow!("trash restore: {}: not in the trash", operand))?;
+
+        if fs::symlink_metadata(&entry.original).is_ok() {
+            anyhow::bail!("trash restore: {}: already exists", entry.original.display());
+        }
+        if let Some(parent) = entry.original.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        move_path(&files.join(&entry.name), &entry.original)?;
+        fs::remove_file(info.join(format!("{}.trashinfo", entry.name)))?;
+        println!("{} Restored {}", "[OK]".green().bold(), entry.original.display());
+    }
+    Ok(())
+}
+
+fn empty() -> Result<()> {
+    let entries = entries()?;
+    let trash = crate::platform::adapter::paths().trash_dir();
+    if trash.exists() {
+        fs::remove_dir_all(&trash)?;
+    }
+    println!("{} Permanently deleted {} item(s)", "[OK]".green().bold(), entries.len());
+    Ok(())
+}
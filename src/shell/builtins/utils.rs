@@ -1,26 +1,49 @@
 use anyhow::Result;
+use std::collections::VecDeque;
 use std::fs;
-use std::path::Path;
+use std::io::{BufRead, BufReader};
+use ignore::WalkBuilder;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
 use crate::shell::parser::Command;
+use crate::shell::environment::Environment;
+use crate::shell::resolve::{self, Resolution};
+use super::Builtins;
 
-pub fn find(command: &Command) -> Result<()> {
-    let path = if command.args.is_empty() {
+pub fn find(command: &Command, env: &Environment) -> Result<()> {
+    for path in find_paths(command, env)? {
+        println!("{}", path.display());
+    }
+
+    Ok(())
+}
+
+/// The paths `find` would print, for callers that act on them (`-exec`).
+pub fn find_paths(command: &Command, env: &Environment) -> Result<Vec<PathBuf>> {
+    let git_aware = respects_gitignore(command, env);
+    let is_follow = |a: &String| a == "-L" || a == "--follow" || a == "--follow-symlinks";
+    let follow_links = command.args.iter().any(is_follow);
+    let args: Vec<String> = command.args.iter()
+        .filter(|a| *a != "--git" && !is_follow(a))
+        .cloned()
+        .collect();
+
+    let path = if args.is_empty() {
         ".".to_string()
     } else {
-        command.args[0].clone()
+        args[0].clone()
     };
 
-    let pattern = if command.args.len() > 2 && command.args[1] == "-name" {
-        Some(&command.args[2])
+    let pattern = if args.len() > 2 && args[1] == "-name" {
+        Some(&args[2])
     } else {
         None
     };
 
-    let max_depth = if let Some(idx) = command.args.iter().position(|a| a == "-maxdepth") {
-        if idx + 1 < command.args.len() {
-            command.args[idx + 1].parse::<usize>().ok()
+    let max_depth = if let Some(idx) = args.iter().position(|a| a == "-maxdepth") {
+        if idx + 1 < args.len() {
+            args[idx + 1].parse::<usize>().ok()
         } else {
             None
         }
@@ -28,29 +51,200 @@ pub fn find(command: &Command) -> Result<()> {
         None
     };
 
-    let mut walker = WalkDir::new(&path);
-    if let Some(depth) = max_depth {
-        walker = walker.max_depth(depth);
-    }
+    let paths: Box<dyn Iterator<Item = PathBuf>> = if git_aware {
+        // Skip whatever .gitignore excludes, plus the repository's own .git directory
+        let walker = WalkBuilder::new(&path)
+            .hidden(false)
+            .follow_links(follow_links)
+            .max_depth(max_depth)
+            .filter_entry(|e| e.file_name() != ".git")
+            .build();
+        Box::new(walker.filter_map(|e| e.ok()).map(|e| e.into_path()))
+    } else {
+        // walkdir detects symlink loops itself and reports them as errors, which are skipped
+        let mut walker = WalkDir::new(&path).follow_links(follow_links);
+        if let Some(depth) = max_depth {
+            walker = walker.max_depth(depth);
+        }
+        Box::new(walker.into_iter().filter_map(|e| e.ok()).map(|e| e.into_path()))
+    };
 
-    for entry in walker.into_iter().filter_map(|e| e.ok()) {
-        let path = entry.path();
-        
+    let mut found = Vec::new();
+    for path in paths {
         if let Some(pat) = pattern {
             if let Some(name) = path.file_name() {
                 let name_str = name.to_string_lossy();
-                if name_str.contains(pat) {
-                    println!("{}", path.display());
+                let matches = if pat.contains(['*', '?']) {
+                    wildcard_match(pat, &name_str)
+                } else {
+                    name_str.contains(pat.as_str())
+                };
+                if matches {
+                    found.push(path);
                 }
             }
         } else {
-            println!("{}", path.display());
+            found.push(path);
+        }
+    }
+
+    Ok(found)
+}
+
+/// Shell-style `*` / `?` matching of a whole file name.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // Let the last `*` swallow one more character
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// `--git` on the command line, or `PIEBASH_GITIGNORE` set in the shell, hides `.gitignore`d files.
+pub fn respects_gitignore(command: &Command, env: &Environment) -> bool {
+    command.args.iter().any(|a| a == "--git")
+        || env.get_var("PIEBASH_GITIGNORE").is_some_and(|v| !v.is_empty() && v != "0")
+}
+
+/// How builtins with `--json` / `--csv` print their results.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+impl OutputFormat {
+    pub fn from_args(args: &[String]) -> Self {
+        if args.iter().any(|a| a == "--json") {
+            Self::Json
+        } else if args.iter().any(|a| a == "--csv") {
+            Self::Csv
+        } else {
+            Self::Text
+        }
+    }
+
+    pub fn is_flag(arg: &str) -> bool {
+        arg == "--json" || arg == "--csv"
+    }
+}
+
+/// Prints `rows` as JSON (`{"<key>": [{column: value}], "total": {...}}`) or CSV (a
+/// header, then the rows, then the total as one more row). Each row has one value per
+/// column; `total` may leave out columns that don't add up, such as a name.
+pub fn print_structured(
+    format: OutputFormat,
+    key: &str,
+    columns: &[&str],
+    rows: &[Vec<serde_json::Value>],
+    total: &[(&str, serde_json::Value)],
+) -> Result<()> {
+    use serde_json::{Map, Value};
+
+    let record = |row: &Vec<Value>| -> Map<String, Value> {
+        columns.iter().map(|c| c.to_string()).zip(row.iter().cloned()).collect()
+    };
+
+    match format {
+        OutputFormat::Json => {
+            let mut out = Map::new();
+            out.insert(key.to_string(), Value::Array(rows.iter().map(|r| Value::Object(record(r))).collect()));
+            let total: Map<String, Value> = total.iter().map(|(k, v)| (k.to_string(), v.clone())).collect();
+            out.insert("total".to_string(), Value::Object(total));
+            println!("{}", serde_json::to_string_pretty(&Value::Object(out))?);
+        }
+        OutputFormat::Csv | OutputFormat::Text => {
+            let cell = |value: &Value| {
+                let text = match value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                if text.contains([',', '"', '\n']) {
+                    format!("\"{}\"", text.replace('"', "\"\""))
+                } else {
+                    text
+                }
+            };
+
+            println!("{}", columns.join(","));
+            for row in rows {
+                println!("{}", row.iter().map(cell).collect::<Vec<_>>().join(","));
+            }
+            let total_row: Vec<String> = columns
+                .iter()
+                .map(|c| total.iter().find(|(k, _)| k == c).map(|(_, v)| cell(v)).unwrap_or_else(|| "total".to_string()))
+                .collect();
+            println!("{}", total_row.join(","));
         }
     }
 
     Ok(())
 }
 
+/// The backslash escapes `printf` understands: `\n \t \r \a \b \f \v \\ \"` and `\0NNN`.
+pub fn interpret_escapes(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('a') => out.push('\x07'),
+            Some('b') => out.push('\x08'),
+            Some('f') => out.push('\x0c'),
+            Some('v') => out.push('\x0b'),
+            Some('\\') => out.push('\\'),
+            Some('"') => out.push('"'),
+            Some('0') => {
+                let mut code = 0u32;
+                for _ in 0..3 {
+                    match chars.peek().and_then(|c| c.to_digit(8)) {
+                        Some(digit) => {
+                            code = code * 8 + digit;
+                            chars.next();
+                        }
+                        None => break,
+                    }
+                }
+                out.extend(char::from_u32(code));
+            }
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+
+    out
+}
+
 pub fn wc(command: &Command) -> Result<()> {
     if command.args.is_empty() {
         anyhow::bail!("wc: missing file operand");
@@ -59,9 +253,13 @@ pub fn wc(command: &Command) -> Result<()> {
     let count_lines = command.args.contains(&"-l".to_string());
     let count_words = command.args.contains(&"-w".to_string());
     let count_chars = command.args.contains(&"-c".to_string());
-    
-    let show_all = !count_lines && !count_words && !count_chars;
+    let longest_line = command.args.contains(&"-L".to_string());
+    let format = OutputFormat::from_args(&command.args);
 
+    let show_all = !count_lines && !count_words && !count_chars && !longest_line;
+
+    // (file, [lines, words, chars, longest line])
+    let mut counted = Vec::new();
     for file in &command.args {
         if file.starts_with('-') {
             continue;
@@ -73,21 +271,68 @@ pub fn wc(command: &Command) -> Result<()> {
             continue;
         }
 
-        let contents = fs::read_to_string(path)?;
-        let lines = contents.lines().count();
-        let words = contents.split_whitespace().count();
-        let chars = contents.len();
+        // One line at a time, reusing the buffer
+        let mut reader = BufReader::new(fs::File::open(path)?);
+        let mut buf = String::new();
+        let mut counts = [0; 4];
+        while reader.read_line(&mut buf)? > 0 {
+            let line = buf.strip_suffix('\n').unwrap_or(&buf);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            counts[0] += 1;
+            counts[1] += line.split_whitespace().count();
+            counts[2] += buf.len();
+            counts[3] = counts[3].max(line.chars().count());
+            buf.clear();
+        }
+        counted.push((file.clone(), counts));
+    }
 
-        if show_all {
-            println!("{:>8} {:>8} {:>8} {}", lines, words, chars, file);
-        } else {
-            let mut parts = Vec::new();
-            if count_lines { parts.push(format!("{:>8}", lines)); }
-            if count_words { parts.push(format!("{:>8}", words)); }
-            if count_chars { parts.push(format!("{:>8}", chars)); }
-            parts.push(file.clone());
-            println!("{}", parts.join(" "));
+    let mut total = [0; 4];
+    for (_, counts) in &counted {
+        for i in 0..3 {
+            total[i] += counts[i];
         }
+        total[3] = total[3].max(counts[3]);
+    }
+
+    let selected = [
+        count_lines || show_all,
+        count_words || show_all,
+        count_chars || show_all,
+        longest_line,
+    ];
+
+    if format != OutputFormat::Text {
+        let names = ["lines", "words", "chars", "max_line_length"];
+        let mut columns = vec!["file"];
+        columns.extend((0..4).filter(|&i| selected[i]).map(|i| names[i]));
+
+        let rows: Vec<Vec<serde_json::Value>> = counted
+            .iter()
+            .map(|(file, counts)| {
+                std::iter::once(serde_json::json!(file))
+                    .chain((0..4).filter(|&i| selected[i]).map(|i| serde_json::json!(counts[i])))
+                    .collect()
+            })
+            .collect();
+        let total: Vec<(&str, serde_json::Value)> = (0..4)
+            .filter(|&i| selected[i])
+            .map(|i| (names[i], serde_json::json!(total[i])))
+            .collect();
+        return print_structured(format, "files", &columns, &rows, &total);
+    }
+
+    let print_row = |counts: &[usize; 4], name: &str| {
+        let mut parts: Vec<String> = (0..4).filter(|&i| selected[i]).map(|i| format!("{:>8}", counts[i])).collect();
+        parts.push(name.to_string());
+        println!("{}", parts.join(" "));
+    };
+
+    for (file, counts) in &counted {
+        print_row(counts, file);
+    }
+    if counted.len() > 1 {
+        print_row(&total, "total");
     }
 
     Ok(())
@@ -110,12 +355,9 @@ pub fn head(command: &Command) -> Result<()> {
 
     let file = file_path.ok_or_else(|| anyhow::anyhow!("head: missing file"))?;
 
-    let contents = fs::read_to_string(file)?;
-    for (i, line) in contents.lines().enumerate() {
-        if i >= n {
-            break;
-        }
-        println!("{}", line);
+    // Stops reading after `n` lines
+    for line in BufReader::new(fs::File::open(file)?).lines().take(n) {
+        println!("{}", line?);
     }
 
     Ok(())
@@ -138,11 +380,19 @@ pub fn tail(command: &Command) -> Result<()> {
 
     let file = file_path.ok_or_else(|| anyhow::anyhow!("tail: missing file"))?;
 
-    let contents = fs::read_to_string(file)?;
-    let lines: Vec<&str> = contents.lines().collect();
-    let start = if lines.len() > n { lines.len() - n } else { 0 };
+    // Only the last `n` lines are kept while reading
+    let mut lines = VecDeque::with_capacity(n);
+    for line in BufReader::new(fs::File::open(file)?).lines() {
+        if n == 0 {
+            break;
+        }
+        if lines.len() == n {
+            lines.pop_front();
+        }
+        lines.push_back(line?);
+    }
 
-    for line in &lines[start..] {
+    for line in lines {
         println!("{}", line);
     }
 
@@ -202,13 +452,11 @@ pub fn uniq_cmd(command: &Command) -> Result<()> {
             continue;
         }
 
-        let contents = fs::read_to_string(path)?;
-        let lines: Vec<&str> = contents.lines().collect();
-        
-        let mut prev = "";
+        let mut prev = String::new();
         let mut line_count = 0;
 
-        for line in lines {
+        for line in BufReader::new(fs::File::open(path)?).lines() {
+            let line = line?;
             if line == prev {
                 line_count += 1;
             } else {
@@ -236,15 +484,19 @@ pub fn uniq_cmd(command: &Command) -> Result<()> {
     Ok(())
 }
 
-pub fn which_cmd(command: &Command) -> Result<()> {
+/// `which cmd...`: where each command comes from, in the order the shell looks.
+pub fn which_cmd(command: &Command, env: &Environment, builtins: &Builtins) -> Result<()> {
     if command.args.is_empty() {
         anyhow::bail!("which: missing command");
     }
 
     for cmd in &command.args {
-        match which::which(cmd) {
-            Ok(path) => println!("{}", path.display()),
-            Err(_) => eprintln!("{} not found", cmd),
+        match resolve::resolve(cmd, env, builtins) {
+            Some(Resolution::Alias(value)) => println!("{}: aliased to {}", cmd, value),
+            Some(Resolution::Builtin) => println!("{}: shell builtin", cmd),
+            Some(Resolution::Runtime) => println!("{}: piebash runtime", cmd),
+            Some(Resolution::File(path)) => println!("{}", path.display()),
+            None => eprintln!("{} not found", cmd),
         }
     }
 
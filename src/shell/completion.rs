@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+
+use super::builtins::packages;
+use super::resolve::RUNTIMES;
+
+/// The REPL's line editor helper: Tab completes a command name from the builtins,
+/// aliases and `@lang` runtimes, a command's arguments from its `complete -W` word
+/// list, `pip install` and the like from the packages installed before and the
+/// directories here, and file names everywhere else (`~` expanded, directories
+/// ending in `/`).
+pub struct ShellHelper {
+    words: BTreeMap<String, Vec<String>>,
+    commands: Vec<String>,
+    files: FilenameCompleter,
+}
+
+impl ShellHelper {
+    pub fn new() -> Self {
+        Self { words: BTreeMap::new(), commands: Vec::new(), files: FilenameCompleter::new() }
+    }
+
+    /// Takes the command names to offer at the start of a command.
+    pub fn set_commands(&mut self, commands: Vec<String>) {
+        self.commands = commands;
+    }
+
+    /// Takes the shell's current registrations; the REPL calls this before each prompt.
+    pub fn set_words(&mut self, words: &BTreeMap<String, Vec<String>>) {
+        self.words.clone_from(words);
+    }
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        // The command being typed starts after the last `|`, `;`, `&&` or `||`
+        let command_start = before.rfind(['|', ';', '&']).map_or(0, |i| i + 1);
+        let word_start = before.rfind(char::is_whitespace).map_or(0, |i| i + 1).max(command_start);
+
+        // The command name itself: `@` starts a runtime, a path is left to the
+        // file names, and anything else is a builtin or alias
+        let command = before[command_start..word_start].split_whitespace().next();
+        let prefix = &before[word_start..];
+        if command.is_none() && !prefix.contains('/') {
+            let candidates: Vec<Pair> = match prefix.strip_prefix('@') {
+                Some(language) => RUNTIMES
+                    .iter()
+                    .filter(|name| name.starts_with(language))
+                    .map(|name| format!("@{}", name))
+                    .map(|name| Pair { display: name.clone(), replacement: name + " " })
+                    .collect(),
+                None => self.commands
+                    .iter()
+                    .filter(|name| name.starts_with(prefix))
+                    .map(|name| Pair { display: name.clone(), replacement: format!("{} ", name) })
+                    .collect(),
+            };
+            if !candidates.is_empty() || prefix.starts_with('@') {
+                return Ok((word_start, candidates));
+            }
+        }
+
+        // Only an argument, i.e. a word after the command name, uses the word list
+        if let Some(words) = command.and_then(|name| self.words.get(name)) {
+            let candidates = words
+                .iter()
+                .filter(|word| word.starts_with(prefix))
+                .map(|word| Pair { display: word.clone(), replacement: word.clone() })
+                .collect();
+            return Ok((word_start, candidates));
+        }
+
+        // `pip install <Tab>`: a package installed before, or a directory to install from
+        let mut words = before[command_start..word_start].split_whitespace();
+        if let (Some(language), Some("install")) = (command.and_then(packages::language_of), words.nth(1)) {
+            let (start, files) = self.files.complete(line, pos, ctx)?;
+            let mut candidates: Vec<Pair> = files.into_iter().filter(|pair| pair.replacement.ends_with(['/', '\\'])).collect();
+            if start == word_start {
+                candidates.extend(
+                    packages::installed(language)
+                        .into_iter()
+                        .filter(|name| name.starts_with(prefix))
+                        .map(|name| Pair { display: name.clone(), replacement: format!("{} ", name) }),
+                );
+            }
+            return Ok((start, candidates));
+        }
+
+        self.files.complete(line, pos, ctx)
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
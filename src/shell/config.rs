@@ -0,0 +1,142 @@
+use colored::*;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Settings read from `config.toml` at startup (`~/.piebash`, or `$XDG_CONFIG_HOME/piebash`).
+///
+/// Every key is optional. The matching `PIEBASH_*` environment variable, when set,
+/// wins over the file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// `auto`, `always` or `never` (`PIEBASH_COLOR`)
+    pub color: ColorMode,
+    /// Never download runtimes; only installed ones and cached archives are used (`PIEBASH_OFFLINE`)
+    pub offline: bool,
+    /// Install packages a program fails to import, then retry it (`PIEBASH_AUTOINSTALL`)
+    pub autoinstall: bool,
+    /// Lines of history kept across sessions (`PIEBASH_HISTSIZE`)
+    pub histsize: usize,
+    /// Seconds a code run may take before it is killed, 0 for no limit (`PIEBASH_TIMEOUT`)
+    pub timeout: u64,
+    /// How much the install-and-retry loop reports (`PIEBASH_VERBOSITY`)
+    pub verbosity: Verbosity,
+    /// Show the directory and running command in the terminal's title (`PIEBASH_TITLE`)
+    pub title: bool,
+    pub prompt: PromptConfig,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PromptConfig {
+    /// `full`, `abbrev` or `basename` (`PIEBASH_PROMPT_PATH`); unset or unknown means `abbrev`
+    pub path: Option<String>,
+    /// Replaces the `$` / `#` prompt symbol (`PIEBASH_PROMPT_SYMBOL`)
+    pub symbol: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// `quiet` leaves only the program's output and real errors, `normal` adds the
+/// outcome of the install-and-retry loop, `verbose` every step of it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    Quiet,
+    #[default]
+    Normal,
+    Verbose,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            color: ColorMode::Auto,
+            offline: false,
+            autoinstall: true,
+            histsize: 1000,
+            timeout: 0,
+            verbosity: Verbosity::Normal,
+            title: false,
+            prompt: PromptConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config file, then applies environment overrides.
+    ///
+    /// A missing file is the defaults; an unreadable or malformed one is reported
+    /// and also falls back to the defaults, so a typo never keeps the shell from starting.
+    pub fn load(path: &Path) -> Self {
+        let mut config = match std::fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).unwrap_or_else(|e: toml::de::Error| {
+                eprintln!(
+                    "{} {}: {}; using defaults",
+                    "[WARN]".yellow().bold(),
+                    path.display(),
+                    e.message().trim().replace('\n', ", ")
+                );
+                Self::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                eprintln!("{} {}: {}; using defaults", "[WARN]".yellow().bold(), path.display(), e);
+                Self::default()
+            }
+        };
+
+        config.apply_env();
+        config
+    }
+
+    fn apply_env(&mut self) {
+        if let Ok(value) = std::env::var("PIEBASH_COLOR") {
+            match value.as_str() {
+                "auto" => self.color = ColorMode::Auto,
+                "always" => self.color = ColorMode::Always,
+                "never" => self.color = ColorMode::Never,
+                _ => {}
+            }
+        }
+        if let Ok(value) = std::env::var("PIEBASH_OFFLINE") {
+            self.offline = !value.is_empty() && value != "0";
+        }
+        if let Ok(value) = std::env::var("PIEBASH_AUTOINSTALL") {
+            self.autoinstall = !value.is_empty() && value != "0";
+        }
+        if let Some(n) = std::env::var("PIEBASH_HISTSIZE").ok().and_then(|v| v.parse().ok()) {
+            self.histsize = n;
+        }
+        if let Some(n) = std::env::var("PIEBASH_TIMEOUT").ok().and_then(|v| v.parse().ok()) {
+            self.timeout = n;
+        }
+        if let Ok(value) = std::env::var("PIEBASH_VERBOSITY") {
+            match value.as_str() {
+                "quiet" => self.verbosity = Verbosity::Quiet,
+                "normal" => self.verbosity = Verbosity::Normal,
+                "verbose" => self.verbosity = Verbosity::Verbose,
+                _ => {}
+            }
+        }
+        if let Ok(value) = std::env::var("PIEBASH_TITLE") {
+            self.title = !value.is_empty() && value != "0";
+        }
+    }
+
+    /// Forces colored output on or off; `auto` leaves the terminal checks in place.
+    pub fn apply_color(&self) {
+        match self.color {
+            ColorMode::Auto => {}
+            ColorMode::Always => colored::control::set_override(true),
+            ColorMode::Never => colored::control::set_override(false),
+        }
+    }
+}
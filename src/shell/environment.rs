@@ -1,39 +1,83 @@
 use anyhow::Result;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
 use std::path::PathBuf;
 
+use super::arith::{self, ReadOnlyVars};
+
 pub struct Environment {
     vars: HashMap<String, String>,
+    attributes: HashMap<String, VarAttributes>,
+    // Indexed arrays, which may have gaps (`a[5]=x` on an empty array)
+    arrays: HashMap<String, BTreeMap<usize, String>>,
     aliases: HashMap<String, String>,
+    // `complete -W` word lists, by command
+    completions: BTreeMap<String, Vec<String>>,
+    history: Vec<String>,
     cwd: PathBuf,
+    // `pushd`'s stack below the current directory, most recent first
+    dir_stack: Vec<PathBuf>,
     home_dir: PathBuf,
+    last_status: i32,
+    options: HashSet<String>,
+    positional: Vec<String>,
+    // getopts' place inside a group like `-abc`: (OPTIND it belongs to, char offset)
+    getopts_pos: (usize, usize),
+}
+
+/// What `declare` can say about a variable beyond its value.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VarAttributes {
+    /// `-i`: assignments are evaluated as arithmetic
+    pub integer: bool,
+    /// `-r`: assignments and `unset` fail
+    pub readonly: bool,
+    /// `-x`: also set in the process environment
+    pub exported: bool,
 }
 
+/// Options `set -o` knows about (`errexit` is `-e`, `noclobber` is `-C`, `noglob` is `-f`,
+/// `xtrace` is `-x`).
+pub const SHELL_OPTIONS: &[&str] = &["errexit", "noclobber", "noglob", "pipefail", "xtrace"];
+
+/// Options `shopt` knows about.
+pub const SHOPT_OPTIONS: &[&str] = &["globstar", "nocaseglob"];
+
 impl Environment {
     pub fn new() -> Result<Self> {
         let mut vars = HashMap::new();
+        let mut attributes = HashMap::new();
 
         for (key, value) in env::vars() {
+            attributes.insert(key.clone(), VarAttributes { exported: true, ..Default::default() });
             vars.insert(key, value);
         }
 
-        let home_dir = dirs::home_dir()
-            .ok_or_else(|| anyhow::anyhow!("Could not determine home directory"))?;
+        let home_dir = crate::platform::native().get_home_dir();
 
-        let piebash_home = home_dir.join(".piebash");
-        std::fs::create_dir_all(&piebash_home)?;
+        let piebash_home = &crate::platform::adapter::paths().data_dir;
+        std::fs::create_dir_all(piebash_home)?;
 
+        // PIEBASH_HOME is only passed on when the user set it: children inherit the
+        // environment, and a nested piebash would take it as a one-directory override
         vars.insert("SHELL".to_string(), "piebash".to_string());
-        vars.insert("PIEBASH_HOME".to_string(), piebash_home.to_string_lossy().to_string());
 
         let cwd = env::current_dir()?;
 
         Ok(Self {
             vars,
+            attributes,
+            arrays: HashMap::new(),
             aliases: HashMap::new(),
+            completions: BTreeMap::new(),
+            history: Vec::new(),
             cwd,
+            dir_stack: Vec::new(),
             home_dir,
+            last_status: 0,
+            options: HashSet::new(),
+            positional: Vec::new(),
+            getopts_pos: (1, 1),
         })
     }
 
@@ -41,12 +85,144 @@ impl Environment {
         self.vars.get(key).cloned()
     }
 
-    pub fn set_var(&mut self, key: &str, value: &str) {
-        self.vars.insert(key.to_string(), value.to_string());
+    /// Assigns `value`, evaluating it first for `declare -i` variables.
+    pub fn set_var(&mut self, key: &str, value: &str) -> Result<()> {
+        let attributes = self.get_attributes(key);
+        if attributes.readonly {
+            anyhow::bail!("{}: readonly variable", key);
+        }
+
+        let value = if attributes.integer {
+            arith::eval(value, &mut ReadOnlyVars(&self.vars))?.to_string()
+        } else {
+            value.to_string()
+        };
+
+        // Like bash, a plain assignment to an array sets its first element
+        if let Some(array) = self.arrays.get_mut(key) {
+            array.insert(0, value);
+        } else {
+            self.vars.insert(key.to_string(), value);
+        }
+        Ok(())
     }
 
-    pub fn unset_var(&mut self, key: &str) {
+    pub fn get_array(&self, key: &str) -> Option<&BTreeMap<usize, String>> {
+        self.arrays.get(key)
+    }
+
+    pub fn get_arrays(&self) -> &HashMap<String, BTreeMap<usize, String>> {
+        &self.arrays
+    }
+
+    /// `name=(a b c)`: replaces `name`, scalar or array, with the given elements.
+    pub fn set_array(&mut self, key: &str, values: Vec<String>) -> Result<()> {
+        if self.get_attributes(key).readonly {
+            anyhow::bail!("{}: readonly variable", key);
+        }
+
         self.vars.remove(key);
+        self.arrays.insert(key.to_string(), values.into_iter().enumerate().collect());
+        Ok(())
+    }
+
+    /// `name[index]=value`; a negative index counts back from the end. A scalar
+    /// becomes the array's first element.
+    pub fn set_array_element(&mut self, key: &str, index: i64, value: &str) -> Result<()> {
+        if self.get_attributes(key).readonly {
+            anyhow::bail!("{}: readonly variable", key);
+        }
+
+        let index = self.array_index(key, index)?;
+        let scalar = self.vars.remove(key);
+        let array = self.arrays.entry(key.to_string()).or_insert_with(|| {
+            scalar.into_iter().map(|value| (0, value)).collect()
+        });
+        array.insert(index, value.to_string());
+        Ok(())
+    }
+
+    pub fn unset_array_element(&mut self, key: &str, index: i64) -> Result<()> {
+        if self.get_attributes(key).readonly {
+            anyhow::bail!("{}: cannot unset: readonly variable", key);
+        }
+
+        let index = self.array_index(key, index)?;
+        if let Some(array) = self.arrays.get_mut(key) {
+            array.remove(&index);
+        }
+        Ok(())
+    }
+
+    fn array_index(&self, key: &str, index: i64) -> Result<usize> {
+        if index >= 0 {
+            return Ok(index as usize);
+        }
+
+        let len = self.arrays.get(key)
+            .and_then(|array| array.keys().next_back())
+            .map_or(0, |last| *last as i64 + 1);
+        usize::try_from(len + index).map_err(|_| anyhow::anyhow!("{}[{}]: bad array subscript", key, index))
+    }
+
+    pub fn get_attributes(&self, key: &str) -> VarAttributes {
+        self.attributes.get(key).copied().unwrap_or_default()
+    }
+
+    pub fn set_attributes(&mut self, key: &str, attributes: VarAttributes) {
+        if attributes == VarAttributes::default() {
+            self.attributes.remove(key);
+        } else {
+            self.attributes.insert(key.to_string(), attributes);
+        }
+    }
+
+    /// Exit status of the last command line, as seen by `$?`.
+    pub fn get_last_status(&self) -> i32 {
+        self.last_status
+    }
+
+    pub fn set_last_status(&mut self, status: i32) {
+        self.last_status = status;
+    }
+
+    pub fn set_option(&mut self, name: &str, on: bool) {
+        if on {
+            self.options.insert(name.to_string());
+        } else {
+            self.options.remove(name);
+        }
+    }
+
+    pub fn has_option(&self, name: &str) -> bool {
+        self.options.contains(name)
+    }
+
+    /// `$1`, `$2`, ... as set by `set -- args`.
+    pub fn get_positional(&self) -> &[String] {
+        &self.positional
+    }
+
+    pub fn set_positional(&mut self, args: Vec<String>) {
+        self.positional = args;
+    }
+
+    pub fn getopts_pos(&self) -> (usize, usize) {
+        self.getopts_pos
+    }
+
+    pub fn set_getopts_pos(&mut self, optind: usize, offset: usize) {
+        self.getopts_pos = (optind, offset);
+    }
+
+    pub fn unset_var(&mut self, key: &str) -> Result<()> {
+        if self.get_attributes(key).readonly {
+            anyhow::bail!("{}: cannot unset: readonly variable", key);
+        }
+        self.vars.remove(key);
+        self.arrays.remove(key);
+        self.attributes.remove(key);
+        Ok(())
     }
 
     pub fn get_all_vars(&self) -> &HashMap<String, String> {
@@ -67,6 +243,25 @@ impl Environment {
         self.home_dir.clone()
     }
 
+    /// The directory stack as `dirs` shows it: the current directory, then what
+    /// `pushd` left below it.
+    pub fn get_dir_stack(&self) -> Vec<PathBuf> {
+        std::iter::once(self.cwd.clone()).chain(self.dir_stack.iter().cloned()).collect()
+    }
+
+    /// Replaces the whole stack, changing to its first entry.
+    pub fn set_dir_stack(&mut self, mut stack: Vec<PathBuf>) -> Result<()> {
+        if stack.is_empty() {
+            anyhow::bail!("directory stack empty");
+        }
+        let top = stack.remove(0);
+        if top != self.cwd {
+            self.set_cwd(top)?;
+        }
+        self.dir_stack = stack;
+        Ok(())
+    }
+
     pub fn set_alias(&mut self, name: String, value: String) {
         self.aliases.insert(name, value);
     }
@@ -82,4 +277,41 @@ impl Environment {
     pub fn remove_alias(&mut self, name: &str) {
         self.aliases.remove(name);
     }
+
+    pub fn set_completion(&mut self, name: &str, words: Vec<String>) {
+        self.completions.insert(name.to_string(), words);
+    }
+
+    pub fn get_completions(&self) -> &BTreeMap<String, Vec<String>> {
+        &self.completions
+    }
+
+    /// Removes `name`'s word list, or every one when `name` is None.
+    pub fn remove_completion(&mut self, name: Option<&str>) -> bool {
+        match name {
+            Some(name) => self.completions.remove(name).is_some(),
+            None => {
+                self.completions.clear();
+                true
+            }
+        }
+    }
+
+    pub fn add_history(&mut self, line: &str, max: usize) {
+        if line.is_empty() || self.history.last().is_some_and(|last| last == line) {
+            return;
+        }
+        self.history.push(line.to_string());
+        if self.history.len() > max {
+            self.history.drain(..self.history.len() - max);
+        }
+    }
+
+    pub fn get_history(&self) -> &[String] {
+        &self.history
+    }
+
+    pub fn pop_history(&mut self) -> Option<String> {
+        self.history.pop()
+    }
 }
\ No newline at end of file
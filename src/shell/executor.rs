@@ -1,10 +1,14 @@
 use anyhow::Result;
-use tokio::process::Command;
+use tokio::process::{Child, Command};
+use std::io::Write;
+use std::path::PathBuf;
 use std::process::Stdio;
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-use crate::shell::parser::Command as ShellCommand;
+use crate::shell::parser::{Command as ShellCommand, RedirectFiles};
 use crate::shell::environment::Environment;
+use crate::shell::jobs::{Jobs, STOPPED_STATUS};
+use crate::utils::error::PieBashError;
 
 pub struct CommandExecutor;
 
@@ -13,121 +17,265 @@ impl CommandExecutor {
         Self
     }
 
-    pub async fn execute(&self, command: &ShellCommand, env: &Environment) -> Result<()> {
+    /// Runs `command` in the foreground. One stopped with Ctrl-Z becomes a job in `jobs`.
+    pub async fn execute(&self, command: &ShellCommand, env: &Environment, jobs: &mut Jobs) -> Result<()> {
         // Handle piped commands
         if command.pipe_to.is_some() {
-            return self.execute_pipeline(command, env).await;
+            return self.execute_pipeline(command, env, jobs).await;
         }
 
         // Handle redirects
-        if command.redirect_stdout.is_some() {
-            return self.execute_with_redirect(command, env).await;
+        if command.has_redirects() {
+            return self.execute_with_redirect(command, env, jobs).await;
         }
 
         // Normal execution
-        self.execute_simple(command, env).await
+        self.execute_simple(command, env, jobs).await
     }
 
-    async fn execute_simple(&self, command: &ShellCommand, env: &Environment) -> Result<()> {
+    async fn execute_simple(&self, command: &ShellCommand, env: &Environment, jobs: &mut Jobs) -> Result<()> {
         let cmd_path = which::which(&command.name)
-            .map_err(|_| anyhow::anyhow!("Command not found: {}", command.name))?;
+            .map_err(|_| PieBashError::CommandNotFound(command.name.clone()))?;
 
         let mut child = Command::new(cmd_path)
             .args(&command.args)
-            .stdin(Stdio::inherit())
+            .stdin(Self::stdin_for(command))
             .stdout(Stdio::inherit())
             .stderr(Stdio::inherit())
             .envs(env.get_all_vars())
             .spawn()?;
 
-        let status = child.wait().await?;
+        Self::feed_stdin(&mut child, command);
+        let status = Self::wait_foreground(command, vec![child], jobs).await?[0];
 
         if !status.success() {
-            anyhow::bail!("Command failed with exit code: {:?}", status.code());
+            return Err(PieBashError::from_status(&status).into());
         }
 
         Ok(())
     }
 
-    async fn execute_with_redirect(&self, command: &ShellCommand, env: &Environment) -> Result<()> {
-        let redirect = command.redirect_stdout.as_ref().unwrap();
-        
-        // Open output file
-        let file = if redirect.append {
-            std::fs::OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&redirect.target)?
-        } else {
-            std::fs::OpenOptions::new()
-                .create(true)
-                .write(true)
-                .truncate(true)
-                .open(&redirect.target)?
-        };
-
-        let cmd_path = which::which(&command.name)
-            .map_err(|_| anyhow::anyhow!("Command not found: {}", command.name))?;
+    async fn execute_with_redirect(&self, command: &ShellCommand, env: &Environment, jobs: &mut Jobs) -> Result<()> {
+        // Open output files
+        let mut files = command.open_redirects(env.has_option("noclobber"))?;
 
-        let stdout_stdio: Stdio = file.into();
+        let cmd_path = Self::find_program(command, &mut files)?;
 
         let mut child = Command::new(cmd_path)
             .args(&command.args)
-            .stdin(Stdio::inherit())
-            .stdout(stdout_stdio)
-            .stderr(Stdio::inherit())
+            .stdin(Self::stdin_for(command))
+            .stdout(files.stdout.map_or_else(Stdio::inherit, Stdio::from))
+            .stderr(files.stderr.map_or_else(Stdio::inherit, Stdio::from))
             .envs(env.get_all_vars())
             .spawn()?;
 
-        let status = child.wait().await?;
+        Self::feed_stdin(&mut child, command);
+        let status = Self::wait_foreground(command, vec![child], jobs).await?[0];
 
         if !status.success() {
-            anyhow::bail!("Command failed");
+            return Err(PieBashError::from_status(&status).into());
         }
 
         Ok(())
     }
 
-    async fn execute_pipeline(&self, command: &ShellCommand, env: &Environment) -> Result<()> {
-        // Simplified: use shell to execute pipe for now
-        // This is a temporary workaround - we'll improve it later
-        
-        if let Some(next_cmd) = &command.pipe_to {
-            // Build the full pipeline command
-            let full_command = format!("{} {} | {} {}", 
-                command.name, 
-                command.args.join(" "),
-                next_cmd.name,
-                next_cmd.args.join(" ")
-            );
-
-            // Execute via system shell
-            #[cfg(windows)]
-            let shell_cmd = "cmd";
-            #[cfg(windows)]
-            let shell_arg = "/C";
-            
-            #[cfg(not(windows))]
-            let shell_cmd = "sh";
-            #[cfg(not(windows))]
-            let shell_arg = "-c";
-
-            let mut child = Command::new(shell_cmd)
-                .arg(shell_arg)
-                .arg(&full_command)
-                .stdin(Stdio::inherit())
-                .stdout(Stdio::inherit())
-                .stderr(Stdio::inherit())
-                .envs(env.get_all_vars())
-                .spawn()?;
-
-            let status = child.wait().await?;
-
-            if !status.success() {
-                anyhow::bail!("Pipeline failed");
-            }
+    /// Runs `command`, a pipeline too, and returns what its last stage printed.
+    ///
+    /// The output is kept whatever the exit status, as it is when these stages
+    /// feed the rest of a pipeline.
+    pub async fn capture(&self, command: &ShellCommand, env: &Environment) -> Result<String> {
+        let (output, _) = self.capture_stages(command, env).await?;
+        Ok(output)
+    }
+
+    /// `capture`, along with how the command ended, by the same rule as `execute`.
+    pub async fn capture_with_status(&self, command: &ShellCommand, env: &Environment) -> Result<(String, Result<()>)> {
+        let (output, statuses) = self.capture_stages(command, env).await?;
+        Ok((output, Self::pipeline_status(&statuses, env)))
+    }
+
+    async fn capture_stages(&self, command: &ShellCommand, env: &Environment) -> Result<(String, Vec<std::process::ExitStatus>)> {
+        let mut children = self.spawn_stages(command, env, true, false)?;
+        let mut output = Vec::new();
+        if let Some(mut stdout) = children.last_mut().and_then(|child| child.stdout.take()) {
+            stdout.read_to_end(&mut output).await?;
+        }
+        let mut statuses = Vec::new();
+        for child in &mut children {
+            statuses.push(child.wait().await?);
+        }
+        Ok((String::from_utf8_lossy(&output).to_string(), statuses))
+    }
+
+    async fn execute_pipeline(&self, command: &ShellCommand, env: &Environment, jobs: &mut Jobs) -> Result<()> {
+        let children = self.spawn_stages(command, env, false, false)?;
+        let statuses = Self::wait_foreground(command, children, jobs).await?;
+        Self::pipeline_status(&statuses, env)
+    }
+
+    fn pipeline_status(statuses: &[std::process::ExitStatus], env: &Environment) -> Result<()> {
+        // The last stage decides, unless pipefail asks for the rightmost failure
+        let failed = if env.has_option("pipefail") {
+            statuses.iter().rev().find(|status| !status.success())
+        } else {
+            statuses.last().filter(|status| !status.success())
+        };
+
+        if let Some(status) = failed {
+            return Err(PieBashError::from_status(status).into());
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Waits for every process of a foreground command, in order. Ctrl-Z stops them
+    /// and hands them to `jobs`, and the command then fails with bash's 148.
+    async fn wait_foreground(command: &ShellCommand, mut children: Vec<Child>, jobs: &mut Jobs) -> Result<Vec<std::process::ExitStatus>> {
+        {
+            let finished = async {
+                let mut statuses = Vec::new();
+                for child in &mut children {
+                    statuses.push(child.wait().await?);
+                }
+                Ok::<_, std::io::Error>(statuses)
+            };
+
+            tokio::select! {
+                statuses = finished => return Ok(statuses?),
+                _ = crate::platform::stop_requested() => {}
+            }
+        }
+
+        jobs.add_stopped(command, children);
+        Err(PieBashError::ExitStatus(STOPPED_STATUS).into())
+    }
+
+    /// Starts `command &`: its stages share a process group of their own, so job
+    /// control can signal them together, and stdin is closed unless there's a heredoc.
+    pub fn spawn_background(&self, command: &ShellCommand, env: &Environment) -> Result<Vec<Child>> {
+        self.spawn_stages(command, env, false, true)
+    }
+
+    /// Spawns every stage of a pipeline, each reading the previous one's stdout.
+    /// The last stage's stdout is piped when `capture` is set.
+    fn spawn_stages(&self, command: &ShellCommand, env: &Environment, capture: bool, background: bool) -> Result<Vec<Child>> {
+        let mut stages = vec![command];
+        while let Some(next) = stages.last().and_then(|stage| stage.pipe_to.as_deref()) {
+            stages.push(next);
+        }
+
+        let mut children: Vec<Child> = Vec::new();
+        let mut upstream: Option<Stdio> = None;
+        for (i, stage) in stages.iter().enumerate() {
+            let mut files = stage.open_redirects(env.has_option("noclobber"))?;
+            let cmd_path = Self::find_program(stage, &mut files)?;
+
+            let is_last = i + 1 == stages.len();
+            let stdin = match upstream.take() {
+                Some(pipe) => pipe,
+                None if background && command.stdin_input.is_none() => Stdio::null(),
+                None => Self::stdin_for(command),
+            };
+            let stdout = match files.stdout {
+                Some(file) => file.into(),
+                None if is_last && !capture => Stdio::inherit(),
+                None => Stdio::piped(),
+            };
+
+            let mut cmd = Command::new(cmd_path);
+            cmd.args(&stage.args)
+                .stdin(stdin)
+                .stdout(stdout)
+                .stderr(files.stderr.map_or_else(Stdio::inherit, Stdio::from))
+                .envs(env.get_all_vars());
+            if background {
+                // The first stage leads the group, the rest join it
+                let leader = children.first().and_then(|child| child.id()).unwrap_or(0);
+                crate::platform::set_process_group(&mut cmd, leader);
+            }
+            let mut child = cmd.spawn()?;
+
+            if !is_last {
+                if let Some(stdout) = child.stdout.take() {
+                    upstream = Some(stdout.try_into()?);
+                }
+            }
+            children.push(child);
+        }
+
+        // Only now feed a heredoc, so a large body can't stall on a full pipe
+        Self::feed_stdin(&mut children[0], command);
+
+        Ok(children)
+    }
+
+    /// The program `command` names. When it can't be found and `2>` / `&>` is set,
+    /// the error goes to that file, as bash's does, and only the 127 comes back.
+    fn find_program(command: &ShellCommand, files: &mut RedirectFiles) -> Result<PathBuf> {
+        which::which(&command.name).map_err(|_| -> anyhow::Error {
+            let error = PieBashError::CommandNotFound(command.name.clone());
+            match &mut files.stderr {
+                Some(file) => {
+                    let _ = writeln!(file, "Error: {}", error);
+                    PieBashError::ExitStatus(127).into()
+                }
+                None => error.into(),
+            }
+        })
+    }
+
+    fn stdin_for(command: &ShellCommand) -> Stdio {
+        if command.stdin_input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::inherit()
+        }
+    }
+
+    /// Writes a heredoc body to the child's stdin and closes it. The write runs
+    /// as its own task, so a child that fills its stdout first can't deadlock us.
+    fn feed_stdin(child: &mut Child, command: &ShellCommand) {
+        if let (Some(input), Some(mut stdin)) = (command.stdin_input.clone(), child.stdin.take()) {
+            tokio::spawn(async move {
+                let _ = stdin.write_all(input.as_bytes()).await;
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::parser::CommandParser;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn foreground_pipeline_writes_last_stage_redirect() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out.txt");
+        let line = format!("seq 3 | tr 1 x > {}", out.display());
+        let command = CommandParser::new().parse(&line).unwrap();
+
+        let env = Environment::new().unwrap();
+        CommandExecutor::new().execute(&command, &env, &mut Jobs::new()).await.unwrap();
+
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "x\n2\n3\n");
+    }
+
+    #[tokio::test]
+    async fn missing_command_reports_into_its_stderr_redirect() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("err.log");
+        let line = format!("piebash-no-such-command &> {}", log.display());
+        let command = CommandParser::new().parse(&line).unwrap();
+
+        let env = Environment::new().unwrap();
+        let err = CommandExecutor::new().execute(&command, &env, &mut Jobs::new()).await.unwrap_err();
+
+        assert!(matches!(err.downcast_ref::<PieBashError>(), Some(PieBashError::ExitStatus(127))));
+        assert_eq!(
+            std::fs::read_to_string(&log).unwrap(),
+            "Error: Command not found: piebash-no-such-command\n"
+        );
+    }
+}
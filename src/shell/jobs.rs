@@ -0,0 +1,290 @@
+use anyhow::Result;
+use std::process::ExitStatus;
+use tokio::process::Child;
+
+use super::parser::Command;
+use crate::platform::{self, JobSignal};
+use crate::utils::error::PieBashError;
+
+/// Commands started with a trailing `&` or stopped with Ctrl-Z, numbered `%1`, `%2`,
+/// ... as in bash.
+///
+/// A `&` job runs in a process group of its own, so the terminal's Ctrl-C and Ctrl-Z
+/// reach only the shell, and while `fg` waits on it they're forwarded to the group. A
+/// command stopped in the foreground stays in the shell's group, where the terminal
+/// signals it directly.
+pub struct Jobs {
+    jobs: Vec<Job>,
+    last_pid: Option<u32>,
+}
+
+struct Job {
+    id: usize,
+    command: String,
+    children: Vec<Child>,
+    target: SignalTarget,
+    state: JobState,
+}
+
+/// Where job control sends a job's signals.
+#[derive(Debug, Clone)]
+enum SignalTarget {
+    /// The group a `&` job's stages share; the first stage leads it
+    Group(u32),
+    /// The processes of a job stopped in the foreground, in the shell's own group
+    Processes(Vec<u32>),
+}
+
+impl SignalTarget {
+    fn signal(&self, signal: JobSignal) -> Result<()> {
+        match self {
+            Self::Group(pgid) => platform::signal_group(*pgid, signal),
+            Self::Processes(pids) => pids.iter().try_for_each(|pid| platform::signal_process(*pid, signal)),
+        }
+    }
+
+    /// Whether the terminal's Ctrl-C misses the job, so `fg` has to pass it on.
+    fn needs_forwarding(&self) -> bool {
+        matches!(self, Self::Group(_))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum JobState {
+    Running,
+    Stopped,
+    Done(i32),
+}
+
+impl Job {
+    /// Marks the job done once every stage has exited. The last stage's status is the job's.
+    fn poll(&mut self) {
+        if matches!(self.state, JobState::Done(_)) {
+            return;
+        }
+        let mut last = None;
+        for child in &mut self.children {
+            match child.try_wait() {
+                Ok(Some(status)) => last = Some(status),
+                Ok(None) => return,
+                Err(_) => last = None,
+            }
+        }
+        self.state = JobState::Done(last.map_or(1, |status| exit_code(&status)));
+    }
+}
+
+/// What `fg` saw happen to the job it was waiting on.
+enum Waited {
+    Exited(Option<ExitStatus>),
+    Stopped,
+}
+
+/// The status bash reports for a job stopped by Ctrl-Z: 128 + SIGTSTP.
+pub const STOPPED_STATUS: i32 = 148;
+
+fn exit_code(status: &ExitStatus) -> i32 {
+    match PieBashError::from_status(status) {
+        PieBashError::ExitStatus(code) => code,
+        _ => 1,
+    }
+}
+
+impl Jobs {
+    pub fn new() -> Self {
+        Self { jobs: Vec::new(), last_pid: None }
+    }
+
+    /// Records a job started in the background and prints `[id] pid`, as bash does.
+    pub fn add(&mut self, command: &Command, children: Vec<Child>) -> Result<()> {
+        let id = self.next_id();
+        let pid = children.last().and_then(|child| child.id());
+        let pgid = children.first().and_then(|child| child.id())
+            .ok_or_else(|| anyhow::anyhow!("{}: exited before it could become a job", command.name))?;
+        if let Some(pid) = pid {
+            eprintln!("[{}] {}", id, pid);
+        }
+        self.last_pid = pid;
+        let command = super::Shell::xtrace_line(command);
+        self.jobs.push(Job { id, command, children, target: SignalTarget::Group(pgid), state: JobState::Running });
+        Ok(())
+    }
+
+    /// Records a foreground command the user stopped with Ctrl-Z. The terminal already
+    /// sent it SIGTSTP; a SIGSTOP makes sure, for programs that catch the former.
+    pub fn add_stopped(&mut self, command: &Command, children: Vec<Child>) {
+        let id = self.next_id();
+        let target = SignalTarget::Processes(children.iter().filter_map(|child| child.id()).collect());
+        target.signal(JobSignal::Stop).ok();
+
+        let command = super::Shell::xtrace_line(command);
+        self.jobs.push(Job { id, command, children, target, state: JobState::Stopped });
+        println!();
+        let index = self.jobs.len() - 1;
+        println!("{}", self.describe(index, &self.jobs[index], self.current_index(), false));
+    }
+
+    fn next_id(&self) -> usize {
+        self.jobs.last().map_or(1, |job| job.id + 1)
+    }
+
+    /// `$!`: the process ID of the last job started.
+    pub fn last_pid(&self) -> Option<u32> {
+        self.last_pid
+    }
+
+    /// Prints `[1]+  Done    cmd` for each job that has finished, then forgets it.
+    /// The REPL calls this before each prompt.
+    pub fn report_finished(&mut self) {
+        self.list(false, false);
+    }
+
+    /// `jobs [-l]`: list the jobs, with their process IDs for `-l`.
+    pub fn jobs_cmd(&mut self, command: &Command) -> Result<()> {
+        let mut long = false;
+        for arg in &command.args {
+            match arg.as_str() {
+                "-l" => long = true,
+                _ => anyhow::bail!("jobs: {}: invalid option", arg),
+            }
+        }
+
+        self.list(true, long);
+        Ok(())
+    }
+
+    /// Prints the finished jobs, or every job when `all`, and forgets the finished
+    /// ones: being listed is their notice.
+    fn list(&mut self, all: bool, long: bool) {
+        for job in &mut self.jobs {
+            job.poll();
+        }
+        let current = self.current_index();
+        for (index, job) in self.jobs.iter().enumerate() {
+            if all || matches!(job.state, JobState::Done(_)) {
+                println!("{}", self.describe(index, job, current, long));
+            }
+        }
+        self.jobs.retain(|job| !matches!(job.state, JobState::Done(_)));
+    }
+
+    /// `[1]+  Running                 sleep 10 &`
+    fn describe(&self, index: usize, job: &Job, current: Option<usize>, long: bool) -> String {
+        let marker = match current {
+            Some(c) if c == index => '+',
+            Some(c) if c > 0 && c - 1 == index => '-',
+            _ => ' ',
+        };
+        let state = match job.state {
+            JobState::Running => "Running".to_string(),
+            JobState::Stopped => "Stopped".to_string(),
+            JobState::Done(0) => "Done".to_string(),
+            JobState::Done(code) => format!("Exit {}", code),
+        };
+        let pid = match &job.target {
+            SignalTarget::Group(pgid) if long => format!("{} ", pgid),
+            SignalTarget::Processes(pids) if long && !pids.is_empty() => format!("{} ", pids[0]),
+            _ => String::new(),
+        };
+        let suffix = if job.state == JobState::Running { " &" } else { "" };
+        format!("[{}]{}  {}{:<24}{}{}", job.id, marker, pid, state, job.command, suffix)
+    }
+
+    /// The job `%+` means: the newest one.
+    fn current_index(&self) -> Option<usize> {
+        self.jobs.len().checked_sub(1)
+    }
+
+    /// Finds a job by `%N`, `N`, `%+` / `%%` (the current one), `%-` (the one before)
+    /// or `%prefix` of its command.
+    fn find(&self, name: &str, spec: Option<&String>) -> Result<usize> {
+        let current = self.current_index();
+        let index = match spec.map(|s| s.strip_prefix('%').unwrap_or(s)) {
+            None | Some("+" | "%" | "") => current,
+            Some("-") => current.and_then(|c| c.checked_sub(1)).or(current),
+            Some(n) if n.parse::<usize>().is_ok() => {
+                let id: usize = n.parse()?;
+                self.jobs.iter().position(|job| job.id == id)
+            }
+            Some(prefix) => self.jobs.iter().rposition(|job| job.command.starts_with(prefix)),
+        };
+        match (index, spec) {
+            (Some(index), _) => Ok(index),
+            (None, Some(spec)) => anyhow::bail!("{}: {}: no such job", name, spec),
+            (None, None) => anyhow::bail!("{}: current: no such job", name),
+        }
+    }
+
+    /// `fg [%job]`: resumes the job if it was stopped and waits for it, forwarding
+    /// Ctrl-C and Ctrl-Z. A job stopped again stays in the table.
+    pub async fn fg_cmd(&mut self, command: &Command) -> Result<()> {
+        let index = self.find("fg", command.args.first())?;
+        let job = &mut self.jobs[index];
+        println!("{}", job.command);
+
+        if job.state == JobState::Stopped {
+            job.target.signal(JobSignal::Continue)?;
+        }
+        job.state = JobState::Running;
+
+        let target = job.target.clone();
+        let waited = {
+            let children = &mut job.children;
+            let finished = async {
+                let mut last = None;
+                for child in children.iter_mut() {
+                    last = child.wait().await.ok();
+                }
+                last
+            };
+            tokio::pin!(finished);
+
+            loop {
+                tokio::select! {
+                    status = &mut finished => break Waited::Exited(status),
+                    _ = tokio::signal::ctrl_c() => {
+                        if target.needs_forwarding() {
+                            target.signal(JobSignal::Interrupt).ok();
+                        }
+                    }
+                    _ = platform::stop_requested() => {
+                        if target.signal(JobSignal::Stop).is_ok() {
+                            break Waited::Stopped;
+                        }
+                    }
+                }
+            }
+        };
+
+        match waited {
+            Waited::Stopped => {
+                job.state = JobState::Stopped;
+                println!();
+                let current = self.current_index();
+                println!("{}", self.describe(index, &self.jobs[index], current, false));
+                Err(PieBashError::ExitStatus(STOPPED_STATUS).into())
+            }
+            Waited::Exited(status) => {
+                self.jobs.remove(index);
+                match status {
+                    Some(status) if !status.success() => Err(PieBashError::from_status(&status).into()),
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+
+    /// `bg [%job]`: lets a stopped job carry on in the background.
+    pub fn bg_cmd(&mut self, command: &Command) -> Result<()> {
+        let index = self.find("bg", command.args.first())?;
+        let job = &mut self.jobs[index];
+        if job.state == JobState::Running {
+            anyhow::bail!("bg: job {} already in background", job.id);
+        }
+
+        job.target.signal(JobSignal::Continue)?;
+        job.state = JobState::Running;
+        println!("[{}]+ {} &", job.id, job.command);
+        Ok(())
+    }
+}
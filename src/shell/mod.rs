@@ -2,21 +2,33 @@ pub mod parser;
 pub mod builtins;
 pub mod environment;
 pub mod executor;
+pub mod config;
+pub mod traps;
+pub mod jobs;
+pub mod completion;
+pub mod arith;
+pub mod resolve;
 
 use anyhow::Result;
+use colored::*;
+use std::collections::HashMap;
 use std::path::PathBuf;
-use std::fs::OpenOptions;
-use std::io::Write;
+use std::io::{IsTerminal, Read, Seek, Write};
 
 
 use self::parser::CommandParser;
 use self::builtins::Builtins;
-use self::environment::Environment;
+use self::environment::{Environment, SHELL_OPTIONS, SHOPT_OPTIONS};
 use self::executor::CommandExecutor;
+use self::config::Config;
+use self::traps::Traps;
+use self::jobs::Jobs;
 
 use crate::runtime::RuntimeManager;
 use crate::executor::CodeExecutor;
 use crate::language::LanguageDetector;
+use crate::language::registry::LanguageRegistry;
+use crate::utils::error::PieBashError;
 
 pub struct Shell {
     parser: CommandParser,
@@ -26,16 +38,22 @@ pub struct Shell {
     code_executor: CodeExecutor,
     runtime_manager: RuntimeManager,
     language_detector: LanguageDetector,
+    config: Config,
+    traps: Traps,
+    jobs: Jobs,
 }
 
 impl Shell {
     pub async fn new() -> Result<Self> {
         let environment = Environment::new()?;
-        let runtime_manager = RuntimeManager::new().await?;
+        let config = Config::load(&crate::platform::adapter::paths().config_file());
+        config.apply_color();
+
+        let runtime_manager = RuntimeManager::new(&config).await?;
         let language_detector = LanguageDetector::new()?;
-        let code_executor = CodeExecutor::new(runtime_manager.clone());
+        let code_executor = CodeExecutor::new(runtime_manager.clone(), &config);
 
-        Ok(Self {
+        let mut shell = Self {
             parser: CommandParser::new(),
             builtins: Builtins::new(),
             environment,
@@ -43,72 +61,326 @@ impl Shell {
             code_executor,
             runtime_manager,
             language_detector,
-        })
+            config,
+            traps: Traps::new(),
+            jobs: Jobs::new(),
+        };
+        shell.load_rc().await;
+        Ok(shell)
+    }
+
+    /// Runs `~/.piebashrc` as the shell starts, so its aliases, exports and `cd`
+    /// take effect. Nothing is saved back to it; `source ~/.piebashrc` re-reads it.
+    async fn load_rc(&mut self) {
+        let rc = self.environment.get_home_dir().join(".piebashrc");
+        if !rc.is_file() {
+            return;
+        }
+        match std::fs::read_to_string(&rc) {
+            Ok(text) => {
+                self.run_lines(&rc.display().to_string(), &text).await;
+            }
+            Err(e) => eprintln!("{} {}: {}", "[WARN]".yellow().bold(), rc.display(), e),
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Runs one command line and returns its exit status.
+    ///
+    /// A command that runs and exits non-zero is `Ok(status)`; `Err` is reserved for
+    /// faults in piebash itself (parse errors, unknown commands, failed installs).
+    pub async fn execute(&mut self, input: &str) -> Result<i32> {
+        // Aliases apply to the command line, not to a heredoc body after it
+        let input = match input.split_once('\n') {
+            Some((line, body)) => format!("{}\n{}", parser::expand_aliases(line, self.environment.get_aliases()), body),
+            None => parser::expand_aliases(input, self.environment.get_aliases()),
+        };
+
+        let result = match self.parser.split_chain(&input) {
+            // Handle command chains (&&, ||, ;)
+            Ok(chain) => self.execute_command_chain(&chain).await,
+            Err(e) => Err(e),
+        };
+
+        let status = match &result {
+            Ok(status) => *status,
+            Err(e) => Self::fault_status(e),
+        };
+        self.environment.set_last_status(status);
+
+        self.run_caught_traps().await;
+
+        result
+    }
+
+    /// The variables the lexer expands: the environment plus `$?`, `$1`, `$#`, `$!`
+    /// and the like, taken afresh for each command.
+    fn expansion_vars(&self) -> HashMap<String, String> {
+        let mut env_map = self.environment.get_all_vars().clone();
+        env_map.insert("?".to_string(), self.environment.get_last_status().to_string());
+        let positional = self.environment.get_positional();
+        for (i, arg) in positional.iter().enumerate() {
+            env_map.insert((i + 1).to_string(), arg.clone());
+        }
+        env_map.insert("#".to_string(), positional.len().to_string());
+        env_map.insert("@".to_string(), positional.join(" "));
+        env_map.insert("*".to_string(), positional.join(" "));
+        if let Some(pid) = self.jobs.last_pid() {
+            env_map.insert("!".to_string(), pid.to_string());
+        }
+        // Like bash's $BASHOPTS, tells the lexer which `shopt` options are on
+        let shopts: Vec<&str> = SHOPT_OPTIONS.iter().copied().filter(|o| self.environment.has_option(o)).collect();
+        env_map.insert("BASHOPTS".to_string(), shopts.join(":"));
+        // and $SHELLOPTS which `set -o` ones, for noglob
+        let setopts: Vec<&str> = SHELL_OPTIONS.iter().copied().filter(|o| self.environment.has_option(o)).collect();
+        env_map.insert("SHELLOPTS".to_string(), setopts.join(":"));
+        // Arrays reach the lexer as `name[i]` entries, with `!name[@]` listing the indices
+        for (name, array) in self.environment.get_arrays() {
+            for (index, value) in array {
+                env_map.insert(format!("{}[{}]", name, index), value.clone());
+            }
+            let indices: Vec<String> = array.keys().map(|index| index.to_string()).collect();
+            env_map.insert(format!("!{}[@]", name), indices.join(" "));
+            match array.get(&0) {
+                Some(first) => env_map.insert(name.clone(), first.clone()),
+                None => env_map.remove(name),
+            };
+        }
+        env_map
+    }
+
+    /// Runs the trap of each signal caught while the last command line ran.
+    async fn run_caught_traps(&mut self) {
+        while let Some(signal) = self.traps.take_caught() {
+            match self.traps.get(signal).cloned() {
+                Some(action) => self.run_trap(&action).await,
+                // The trap was reset; a script dies the way it would have without one
+                None if !std::io::stdin().is_terminal() => {
+                    self.run_exit_trap().await;
+                    std::process::exit(128 + Traps::signal_number(signal));
+                }
+                None => {}
+            }
+        }
     }
 
-    pub async fn execute(&mut self, input: &str) -> Result<()> {
-        let env_map = self.environment.get_all_vars().clone();
-        let command = self.parser.parse_with_env(input, &env_map)?;
+    /// Runs the `trap ... EXIT` command, at most once.
+    pub async fn run_exit_trap(&mut self) {
+        if let Some(action) = self.traps.remove("EXIT") {
+            self.run_trap(&action).await;
+        }
+    }
 
-        // Handle command chains (&&, ||, ;)
-        self.execute_command_chain(&command).await
+    /// A trap runs like a typed command line but leaves `$?` as it found it.
+    async fn run_trap(&mut self, action: &str) {
+        // `trap '' SIG` ignores the signal
+        if action.is_empty() {
+            return;
+        }
+
+        let status = self.environment.get_last_status();
+        if let Err(e) = Box::pin(self.execute(action)).await {
+            eprintln!("{} {}", "Error:".red().bold(), e);
+        }
+        self.environment.set_last_status(status);
     }
 
-    async fn execute_command_chain(&mut self, command: &parser::Command) -> Result<()> {
-        let mut current_command = command;
-        let mut last_result: Result<()> = Ok(());
+    /// Runs a chain from `Parser::split_chain`, parsing each command as its turn comes.
+    async fn execute_command_chain(&mut self, chain: &[(String, Option<parser::ChainOperator>)]) -> Result<i32> {
+        let mut current = 0;
 
         loop {
-            // Execute the current command
-            last_result = self.execute_single_command(current_command).await;
-            
+            // Parse and execute the current command, with $? from the one before
+            let (text, chain_operator) = &chain[current];
+            let result = match self.parser.parse_with_env(text, &self.expansion_vars()) {
+                Ok(command) => Self::exit_status(self.execute_single_command(&command).await),
+                Err(e) => Err(e),
+            };
+
             // Check if there's a chained command
-            if let Some(ref next_cmd) = current_command.next_command {
-                match current_command.chain_operator {
-                    Some(parser::ChainOperator::And) => {
-                        // && - continue only if last succeeded
-                        if last_result.is_err() {
-                            return last_result;
-                        }
-                    }
-                    Some(parser::ChainOperator::Or) => {
-                        // || - continue only if last failed
-                        if last_result.is_ok() {
-                            return last_result;
-                        }
-                    }
+            if current + 1 == chain.len() {
+                // No more commands
+                return result;
+            }
+
+            let status = match result {
+                Ok(status) => status,
+                Err(e) => {
+                    // The rest of the chain still runs, so report the fault here
+                    eprintln!("{} {}", "Error:".red().bold(), e);
+                    Self::fault_status(&e)
+                }
+            };
+            self.environment.set_last_status(status);
+
+            // As in bash, set -e ignores a failure that a `&&` or `||` after it tests
+            let tested = matches!(
+                chain_operator,
+                Some(parser::ChainOperator::And | parser::ChainOperator::Or)
+            );
+
+            // Skip what the operators rule out; the status carries over the skipped
+            // commands, so in `a && b || c` a failed `a` still runs `c`
+            let mut operator = chain_operator.clone();
+            let mut candidate = current + 1;
+            loop {
+                let run = match operator {
+                    // && - continue only if last succeeded
+                    Some(parser::ChainOperator::And) => status == 0,
+                    // || - continue only if last failed
+                    Some(parser::ChainOperator::Or) => status != 0,
+                    // ; - always continue, unless set -e says a failure ends the line
                     Some(parser::ChainOperator::Semicolon) => {
-                        // ; - always continue (ignore last result)
-                    }
-                    None => {
-                        break;
+                        if status != 0 && !tested && self.environment.has_option("errexit") {
+                            return Ok(status);
+                        }
+                        true
                     }
+                    None => return Ok(status),
+                };
+                if run {
+                    break;
                 }
-                
-                // Move to next command
-                current_command = next_cmd;
-            } else {
-                // No more commands
-                break;
+                if candidate + 1 == chain.len() {
+                    return Ok(status);
+                }
+                operator = chain[candidate].1.clone();
+                candidate += 1;
             }
+
+            // Move to next command
+            current = candidate;
+        }
+    }
+
+    /// A command (and its pipeline stages) as `set -x` prints it.
+    fn xtrace_line(command: &parser::Command) -> String {
+        let mut stages = Vec::new();
+        let mut stage = Some(command);
+        while let Some(cmd) = stage {
+            let words: Vec<String> = std::iter::once(&cmd.name)
+                .chain(&cmd.args)
+                .map(|word| builtins::core::shell_quote(word))
+                .collect();
+            stages.push(words.join(" "));
+            stage = cmd.pipe_to.as_deref();
+        }
+        stages.join(" | ")
+    }
+
+    /// Folds a command's non-zero exit into a status; anything else stays an error.
+    fn exit_status(result: Result<()>) -> Result<i32> {
+        match result {
+            Ok(()) => Ok(0),
+            Err(e) => match e.downcast_ref::<PieBashError>() {
+                Some(PieBashError::ExitStatus(code)) => Ok(*code),
+                _ => Err(e),
+            },
+        }
+    }
+
+    /// The `$?` a shell fault leaves behind, following bash's conventions.
+    fn fault_status(e: &anyhow::Error) -> i32 {
+        match e.downcast_ref::<PieBashError>() {
+            Some(PieBashError::CommandNotFound(_)) => 127,
+            Some(PieBashError::ParseError(_)) => 2,
+            _ => 1,
         }
-        
-        last_result
     }
 
     async fn execute_single_command(&mut self, command: &parser::Command) -> Result<()> {
+        // set -x: echo each command to stderr before running it
+        if self.environment.has_option("xtrace") {
+            eprintln!("+ {}", Self::xtrace_line(command));
+        }
+
         // Handle pipes specially
         if command.name == "piebash" {
             anyhow::bail!("Cannot run piebash inside piebash. Use 'exit' to return to the parent shell.");
         }
 
+        // `command name args` runs name directly, skipping alias lookup
+        let unwrapped;
+        let command = if command.name == "command"
+            && command.args.first().is_some_and(|a| !a.starts_with('-'))
+        {
+            unwrapped = Self::shift_command(command);
+            &unwrapped
+        } else {
+            command
+        };
+
+        // `builtin name args` forces the builtin even if something shadows it
+        if command.name == "builtin" && !command.args.is_empty() {
+            let inner = Self::shift_command(command);
+            if !self.builtins.is_builtin(&inner.name) {
+                anyhow::bail!("builtin: {}: not a shell builtin", inner.name);
+            }
+            return self.execute_builtin(&inner).await;
+        }
+
+        // `find ... -exec cmd {} ;` runs cmd on what find matched
+        if command.name == "find" && command.args.iter().any(|a| a == "-exec") {
+            return self.execute_find_exec(command).await;
+        }
+
+        // `repeat [-e] N cmd args` runs cmd N times
+        if command.name == "repeat" {
+            return self.execute_repeat(command).await;
+        }
+
+        if command.name == "trap" {
+            return self.traps.trap_cmd(command);
+        }
+
+        if command.name == "fc" {
+            return self.execute_fc(command).await;
+        }
+
+        if command.name == "source" || command.name == "." {
+            return self.execute_source(command).await;
+        }
+
+        match command.name.as_str() {
+            "jobs" => return self.jobs.jobs_cmd(command),
+            "fg" => return self.jobs.fg_cmd(command).await,
+            "bg" => return self.jobs.bg_cmd(command),
+            _ => {}
+        }
+
+        // `name=@lang code` stores the program's output in a variable
+        if let Some((var, lang)) = command.name.split_once("=@") {
+            if Self::is_valid_var_name(var) {
+                return self.execute_capture_assignment(var, lang, command).await;
+            }
+        }
+
+        // `name=value`, `name[i]=value` and `name=(a b c)` assign in the shell itself
+        if let Some((target, value)) = command.name.split_once('=') {
+            let (var, subscript) = match target.strip_suffix(']').and_then(|t| t.split_once('[')) {
+                Some((var, subscript)) => (var, Some(subscript)),
+                None => (target, None),
+            };
+            let is_list = subscript.is_none() && value.starts_with('(');
+            if Self::is_valid_var_name(var) && (command.args.is_empty() || is_list) {
+                return self.execute_assignment(var, subscript, value, command);
+            }
+        }
+
+        if command.background {
+            return self.execute_background(command);
+        }
+
         // Handle pipes specially
         if command.pipe_to.is_some() {
             return self.execute_pipeline(&command).await
         }
         // Check if it's a built-in
         if self.builtins.is_builtin(&command.name) {
-            return self.execute_builtin(&command).await;
+            return self.execute_builtin(command).await;
         }
 
         // Check if it's code execution
@@ -117,205 +389,650 @@ impl Shell {
         }
 
         // Execute as external command
-        self.executor.execute(&command, &self.environment).await
+        self.executor.execute(command, &self.environment, &mut self.jobs).await
     }
 
-    async fn execute_pipeline(&mut self, command: &parser::Command) -> Result<()> {
-        // For built-in to built-in pipes, handle internally
-        if self.builtins.is_builtin(&command.name) {
-            if let Some(next_cmd) = &command.pipe_to {
-                if self.builtins.is_builtin(&next_cmd.name) {
-                    // Both are built-ins - handle internally
-                    let output = self.capture_builtin_output(&command)?;
-                    
-                    // Filter the output through the second command
-                    self.execute_builtin_with_input(next_cmd, &output).await?;
-                    return Ok(());
-                }
+    /// `cmd &`: starts the command as a job and returns straight away.
+    ///
+    /// Builtins run inside the shell, so a backgrounded one (`sleep 5 &`) runs the
+    /// program of the same name instead; one without such a program can't be a job.
+    fn execute_background(&mut self, command: &parser::Command) -> Result<()> {
+        let mut stage = Some(command);
+        while let Some(cmd) = stage {
+            let external = which::which(&cmd.name).is_ok();
+            if (self.builtins.is_builtin(&cmd.name) && !external) || self.is_code_execution(&cmd.name) {
+                anyhow::bail!("{}: only external commands can run in the background", cmd.name);
             }
+            stage = cmd.pipe_to.as_deref();
         }
 
-        // Fall back to external executor for other cases
-        self.executor.execute(&command, &self.environment).await
+        let children = self.executor.spawn_background(command, &self.environment)?;
+        self.jobs.add(command, children)
     }
 
-    async fn execute_builtin(&mut self, command: &parser::Command) -> Result<()> {
-    if let Some(redirect) = &command.redirect_stdout {
-        let output = match command.name.as_str() {
-            "echo" => command.args.join(" ") + "\n",
-            _ => self.capture_builtin_output(command)?,
-        };
-        let mut file = if redirect.append {
-            OpenOptions::new().create(true).append(true).open(&redirect.target)?
-        } else {
-            OpenOptions::new().create(true).write(true).truncate(true).open(&redirect.target)?
+    /// Runs `$PROMPT_COMMAND`, as bash does before each prompt, leaving `$?` as the
+    /// last command line set it.
+    pub async fn run_prompt_command(&mut self) {
+        let Some(command) = self.environment.get_var("PROMPT_COMMAND").filter(|c| !c.trim().is_empty()) else {
+            return;
         };
-        write!(file, "{}", output)?;
-        return Ok(());
+        let status = self.environment.get_last_status();
+        if let Err(e) = self.execute(&command).await {
+            eprintln!("{} {}", "Error:".red().bold(), e);
+        }
+        self.environment.set_last_status(status);
     }
 
-    // Pass runtime_manager to async execute
-    self.builtins
-        .execute_async(command, &mut self.environment, Some(&self.runtime_manager))
-        .await
-}
+    /// Reports background jobs that finished since the last prompt.
+    pub fn report_jobs(&mut self) {
+        self.jobs.report_finished();
+    }
 
-    fn capture_builtin_output(&mut self, command: &parser::Command) -> Result<String> {
-        match command.name.as_str() {
-            "echo" => {
-                Ok(command.args.join(" ") + "\n")
+    async fn execute_pipeline(&mut self, command: &parser::Command) -> Result<()> {
+        // `... | @python` / `... | python script.py`: what the upstream stages print
+        // becomes the program's stdin
+        let mut upstream = command.clone();
+        let mut stage = &mut upstream;
+        while stage.pipe_to.as_ref().is_some_and(|next| next.pipe_to.is_some()) {
+            stage = stage.pipe_to.as_deref_mut().unwrap();
+        }
+        if stage.pipe_to.as_ref().is_some_and(|last| self.is_code_execution(&last.name)) {
+            let mut program = *stage.pipe_to.take().unwrap();
+            let input = if upstream.pipe_to.is_none() && self.builtins.is_builtin(&upstream.name) {
+                let (output, result) = self.capture_builtin_output(&upstream).await?;
+                result?;
+                output
+            } else {
+                self.executor.capture(&upstream, &self.environment).await?
+            };
+            program.stdin_input = Some(input);
+            return self.execute_code(&program).await;
+        }
+
+        let mut stages = Vec::new();
+        let mut next = Some(command);
+        while let Some(stage) = next {
+            let mut alone = stage.clone();
+            alone.pipe_to = None;
+            stages.push(alone);
+            next = stage.pipe_to.as_deref();
+        }
+
+        // Only programs: one process pipeline, wired end to end
+        if !stages.iter().any(|stage| self.builtins.is_builtin(&stage.name)) {
+            return self.executor.execute(command, &self.environment, &mut self.jobs).await;
+        }
+        self.execute_builtin_pipeline(stages).await
+    }
+
+    /// Runs a pipeline with builtin stages. A builtin runs in the shell when it can
+    /// take its input as text (`text::reads_stdin`) or, first in line, with its output
+    /// captured; every other stage is a program. Text is handed between stages in the
+    /// shell, and each run of programs between them is one process pipeline fed the
+    /// text before it. A failing stage doesn't stop the ones after it, and the
+    /// statuses decide as they do for a pipeline of programs.
+    async fn execute_builtin_pipeline(&mut self, stages: Vec<parser::Command>) -> Result<()> {
+        // A heredoc on the first stage is the pipeline's input
+        let mut input = stages[0].stdin_input.clone();
+        let mut statuses = Vec::new();
+        let mut i = 0;
+
+        while i < stages.len() {
+            let stage = &stages[i];
+            let is_last = i + 1 == stages.len();
+            let is_builtin = self.builtins.is_builtin(&stage.name);
+
+            let in_shell = match input.as_deref() {
+                Some(text) if is_builtin && builtins::text::reads_stdin(stage) => {
+                    Some(builtins::text::filter(stage, text).map(|(output, success)| {
+                        // grep fails when nothing matched
+                        let result = if success { Ok(()) } else { Err(PieBashError::ExitStatus(1).into()) };
+                        (output, result)
+                    }))
+                }
+                _ if is_builtin && i == 0 => Some(self.capture_builtin_output(stage).await),
+                _ => None,
+            };
+            if let Some(ran) = in_shell {
+                let (output, result) = ran.unwrap_or_else(|e| (String::new(), Err(e)));
+                if is_last {
+                    let written = self.write_builtin_output(stage, &output);
+                    statuses.push(Self::stage_status(result.and(written)));
+                } else {
+                    statuses.push(Self::stage_status(result));
+                    input = Some(output);
+                }
+                i += 1;
+                continue;
             }
-            "pwd" => {
-                Ok(format!("{}\n", self.environment.get_cwd().display()))
+
+            // Programs up to the next stage the shell takes over, as one process pipeline
+            let end = (i + 1..stages.len())
+                .find(|&j| self.builtins.is_builtin(&stages[j].name) && builtins::text::reads_stdin(&stages[j]))
+                .unwrap_or(stages.len());
+            let mut run = stages[end - 1].clone();
+            for stage in stages[i..end - 1].iter().rev() {
+                let mut head = stage.clone();
+                head.pipe_to = Some(Box::new(run));
+                run = head;
             }
-            "ls" => {
-                self.capture_ls_output(command)
+            run.stdin_input = input.take();
+
+            if end == stages.len() {
+                let result = self.executor.execute(&run, &self.environment, &mut self.jobs).await;
+                statuses.push(Self::stage_status(result));
+                break;
             }
-            "cat" => {
-                self.capture_cat_output(command)
+            let (output, result) = self.executor
+                .capture_with_status(&run, &self.environment)
+                .await
+                .unwrap_or_else(|e| (String::new(), Err(e)));
+            statuses.push(Self::stage_status(result));
+            input = Some(output);
+            i = end;
+        }
+
+        // The last stage decides, unless pipefail asks for the rightmost failure
+        let failed = if self.environment.has_option("pipefail") {
+            statuses.iter().rev().find(|&&status| status != 0)
+        } else {
+            statuses.last().filter(|&&status| status != 0)
+        };
+        match failed {
+            Some(&status) => Err(PieBashError::ExitStatus(status).into()),
+            None => Ok(()),
+        }
+    }
+
+    /// A pipeline stage's status. The pipeline goes on past a fault, so it's
+    /// reported here.
+    fn stage_status(result: Result<()>) -> i32 {
+        Self::exit_status(result).unwrap_or_else(|e| {
+            eprintln!("{} {}", "Error:".red().bold(), e);
+            Self::fault_status(&e)
+        })
+    }
+
+    async fn execute_builtin(&mut self, command: &parser::Command) -> Result<()> {
+    if !command.has_redirects() {
+        return self.run_builtin(command).await;
+    }
+
+    // Opened first, so noclobber stops the builtin from running at all
+    let files = command.open_redirects(self.environment.has_option("noclobber"))?;
+    // Builtins print from inside the shell, so the shell's own stdout and stderr
+    // point at the files while it runs
+    let redirect = crate::platform::redirect_stdio(files.stdout.as_ref(), files.stderr.as_ref())?;
+    let result = self.run_builtin(command).await;
+    drop(redirect);
+
+    Self::redirect_fault(result, files.stderr)
+}
+
+    async fn run_builtin(&mut self, command: &parser::Command) -> Result<()> {
+        match &command.stdin_input {
+            Some(input) => self.execute_builtin_with_input(command, input).await,
+            // Pass runtime_manager to async execute
+            None => self.builtins
+                .execute_async(command, &mut self.environment, Some(&self.runtime_manager))
+                .await,
+        }
+    }
+
+    /// `2>` / `&>`: the shell's error message for a command goes to its file instead
+    /// of the terminal, leaving just the status.
+    fn redirect_fault(result: Result<()>, stderr: Option<std::fs::File>) -> Result<()> {
+        match (result, stderr) {
+            (Err(e), Some(mut file)) if !matches!(e.downcast_ref::<PieBashError>(), Some(PieBashError::ExitStatus(_))) => {
+                writeln!(file, "Error: {}", e)?;
+                Err(PieBashError::ExitStatus(Self::fault_status(&e)).into())
             }
-            "env" => {
-                let mut output = String::new();
-                let mut vars: Vec<_> = self.environment.get_all_vars().iter().collect();
-                vars.sort_by_key(|(k, _)| *k);
-                for (key, value) in vars {
-                    output.push_str(&format!("{}={}\n", key, value));
+            (result, _) => result,
+        }
+    }
+
+    /// What a builtin prints, read back from a temporary file its stdout pointed at,
+    /// along with how it ended. The output is kept either way.
+    async fn capture_builtin_output(&mut self, command: &parser::Command) -> Result<(String, Result<()>)> {
+        let mut file = tempfile::tempfile()?;
+        let redirect = crate::platform::redirect_stdio(Some(&file), None)?;
+        let result = self.execute_builtin(command).await;
+        drop(redirect);
+
+        let mut output = String::new();
+        file.rewind()?;
+        file.read_to_string(&mut output)?;
+        Ok((output, result))
+    }
+
+    async fn execute_builtin_with_input(&mut self, command: &parser::Command, input: &str) -> Result<()> {
+        if !builtins::text::reads_stdin(command) {
+            return self.builtins.execute(command, &mut self.environment);
+        }
+
+        let (output, success) = builtins::text::filter(command, input)?;
+        print!("{}", output);
+
+        // grep fails when nothing matched
+        if !success {
+            return Err(PieBashError::ExitStatus(1).into());
+        }
+        Ok(())
+    }
+
+    /// Prints what a builtin produced in the shell, or writes it to its `>` file.
+    fn write_builtin_output(&self, command: &parser::Command, output: &str) -> Result<()> {
+        match &command.redirect_stdout {
+            Some(redirect) => write!(redirect.open(self.environment.has_option("noclobber"))?, "{}", output)?,
+            None => print!("{}", output),
+        }
+        Ok(())
+    }
+
+    fn is_code_execution(&self, cmd: &str) -> bool {
+        resolve::is_runtime(cmd)
+    }
+
+    async fn execute_code(&mut self, command: &parser::Command) -> Result<()> {
+        let outputs = command.open_redirects(self.environment.has_option("noclobber"))?;
+        let result = self.run_code(command, &outputs).await;
+        Self::redirect_fault(result, outputs.stderr)
+    }
+
+    async fn run_code(&mut self, command: &parser::Command, outputs: &parser::RedirectFiles) -> Result<()> {
+        if command.name == "go" {
+            match command.args.first().map(String::as_str) {
+                // `go run main.go` - the executor supplies `run` itself
+                Some("run") => {
+                    let mut stripped = command.clone();
+                    stripped.args.remove(0);
+                    return self.code_executor.execute("go", &stripped, outputs).await;
                 }
-                Ok(output)
-            }
-            _ => {
-                self.builtins.execute(&command, &mut self.environment)?;
-                Ok(String::new())
+                // `go build`, `go mod tidy`, ... go straight to the toolchain
+                Some(arg) if !arg.ends_with(".go") => {
+                    return self.code_executor.execute_toolchain("go", &command.args, outputs).await;
+                }
+                _ => {}
             }
         }
+
+        // `python --stats script.py`: the flag is the executor's, the script comes after
+        let script = command.args.iter().find(|arg| *arg != "--stats");
+        let language = if command.name.starts_with('@') {
+            command.name[1..].to_string()
+        } else if let Some(script) = script {
+            self.language_detector.detect_from_file(script)?
+        } else if command.stdin_input.is_some() {
+            // `python <<EOF` - the program comes from stdin
+            command.name.clone()
+        } else {
+            anyhow::bail!("No code to execute");
+        };
+        let language = LanguageRegistry::canonical_name(&language);
+
+        self.code_executor.execute(language, command, outputs).await
     }
 
-    fn capture_ls_output(&self, command: &parser::Command) -> Result<String> {
-        use std::fs;
-        
-        let mut show_all = false;
-        let mut target_path = None;
+    /// `fc`: list (`-l`), re-run (`-s [old=new]`) or edit in `$FCEDIT` / `$EDITOR`
+    /// and re-run history entries. The newest entry is the `fc` line itself, so
+    /// it is never one of the targets.
+    async fn execute_fc(&mut self, command: &parser::Command) -> Result<()> {
+        let mut history = self.environment.get_history().to_vec();
+        history.pop();
 
-        for arg in &command.args {
-            if arg.starts_with('-') {
-                if arg.contains('a') {
-                    show_all = true;
+        let (mut list, mut numbered, mut reverse, mut substitute) = (false, true, false, false);
+        let mut editor = None;
+        let mut operands = Vec::new();
+        let mut args = command.args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-e" => editor = Some(args.next().ok_or_else(|| anyhow::anyhow!("fc: -e: option requires an argument"))?.clone()),
+                "-s" => substitute = true,
+                // A negative number is a history offset, not an option
+                flags if flags.starts_with('-') && flags.parse::<i64>().is_err() => {
+                    for flag in flags[1..].chars() {
+                        match flag {
+                            'l' => list = true,
+                            'n' => numbered = false,
+                            'r' => reverse = true,
+                            _ => anyhow::bail!("fc: -{}: invalid option", flag),
+                        }
+                    }
+                }
+                _ => operands.push(arg.as_str()),
+            }
+        }
+
+        if history.is_empty() {
+            anyhow::bail!("fc: no command found");
+        }
+
+        if list {
+            // The last 16 by default; out-of-range numbers are clamped when listing
+            let first = operands.first().map_or(Ok(history.len().saturating_sub(16)), |spec| Self::history_index(&history, spec, true))?;
+            let last = operands.get(1).map_or(Ok(history.len() - 1), |spec| Self::history_index(&history, spec, true))?;
+            let (low, high) = (first.min(last), first.max(last));
+            let mut entries: Vec<usize> = (low..=high).collect();
+            if reverse != (first > last) {
+                entries.reverse();
+            }
+            for i in entries {
+                if numbered {
+                    println!("{:>5}  {}", i + 1, history[i]);
+                } else {
+                    println!("       {}", history[i]);
                 }
-            } else {
-                target_path = Some(arg.as_str());
             }
+            return Ok(());
         }
 
-        let path = if let Some(p) = target_path {
-            self.environment.get_cwd().join(p)
+        // `fc -s [old=new] [cmd]` re-runs without an editor
+        let lines = if substitute {
+            let (replace, spec) = match operands.first() {
+                Some(first) if first.contains('=') => (first.split_once('='), operands.get(1)),
+                first => (None, first),
+            };
+            let index = spec.map_or(Ok(history.len() - 1), |spec| Self::history_index(&history, spec, false))?;
+            let line = match replace {
+                Some((old, new)) => history[index].replace(old, new),
+                None => history[index].clone(),
+            };
+            vec![line]
         } else {
-            self.environment.get_cwd().clone()
+            let first = operands.first().map_or(Ok(history.len() - 1), |spec| Self::history_index(&history, spec, false))?;
+            let last = operands.get(1).map_or(Ok(first), |spec| Self::history_index(&history, spec, false))?;
+            let mut chosen: Vec<&String> = history[first.min(last)..=first.max(last)].iter().collect();
+            if first > last {
+                chosen.reverse();
+            }
+            self.edit_in_editor(editor, &chosen)?
         };
 
-        if !path.exists() {
-            anyhow::bail!("ls: cannot access '{}': No such file or directory", path.display());
+        // Like bash, the commands that ran take the `fc` line's place in history
+        self.environment.pop_history();
+        let mut status = 0;
+        for line in lines.iter().filter(|line| !line.trim().is_empty()) {
+            println!("{}", line);
+            self.add_history(line);
+            status = match Box::pin(self.execute(line)).await {
+                Ok(status) => status,
+                // One failing line doesn't stop the rest, as at the prompt
+                Err(e) => {
+                    eprintln!("{} {}", "Error:".red().bold(), e);
+                    Self::fault_status(&e)
+                }
+            };
         }
 
-        let mut output = String::new();
-        let mut entries = Vec::new();
-        
-        for entry in fs::read_dir(&path)? {
-            let entry = entry?;
-            entries.push(entry);
+        if status != 0 {
+            return Err(PieBashError::ExitStatus(status).into());
+        }
+        Ok(())
+    }
+
+    /// Resolves an `fc` operand: `N` is entry N, `-N` the Nth most recent, and
+    /// anything else the newest entry starting with it.
+    fn history_index(history: &[String], spec: &str, clamp: bool) -> Result<usize> {
+        let Ok(n) = spec.parse::<i64>() else {
+            return history
+                .iter()
+                .rposition(|line| line.starts_with(spec))
+                .ok_or_else(|| anyhow::anyhow!("fc: {}: no command found", spec));
+        };
+
+        let index = if n < 0 { history.len() as i64 + n } else { n - 1 };
+        if (0..history.len() as i64).contains(&index) {
+            Ok(index as usize)
+        } else if clamp {
+            Ok(index.clamp(0, history.len() as i64 - 1) as usize)
+        } else {
+            anyhow::bail!("fc: history specification out of range")
         }
+    }
+
+    /// Opens `lines` in the editor (`-e`, then `$FCEDIT`, `$EDITOR`, `vi`) and
+    /// returns the file's lines once it exits successfully.
+    fn edit_in_editor(&self, editor: Option<String>, lines: &[&String]) -> Result<Vec<String>> {
+        use std::io::Write;
 
-        entries.sort_by_key(|e| e.file_name());
+        let editor = editor
+            .or_else(|| self.environment.get_var("FCEDIT").filter(|v| !v.is_empty()))
+            .or_else(|| self.environment.get_var("EDITOR").filter(|v| !v.is_empty()))
+            .unwrap_or_else(|| "vi".to_string());
 
-        for entry in entries {
-            let file_name = entry.file_name();
-            let name = file_name.to_string_lossy();
+        let mut file = tempfile::Builder::new().prefix("piebash-fc").suffix(".sh").tempfile()?;
+        for line in lines {
+            writeln!(file, "{}", line)?;
+        }
+        file.flush()?;
 
-            if !show_all && name.starts_with('.') {
+        // `$EDITOR` may carry its own arguments, as in `code --wait`
+        let mut words = editor.split_whitespace();
+        let program = words.next().ok_or_else(|| anyhow::anyhow!("fc: no editor"))?;
+        let status = std::process::Command::new(program)
+            .args(words)
+            .arg(file.path())
+            .status()
+            .map_err(|e| anyhow::anyhow!("fc: {}: {}", program, e))?;
+        if !status.success() {
+            return Err(PieBashError::from_status(&status).into());
+        }
+
+        Ok(std::fs::read_to_string(file.path())?.lines().map(String::from).collect())
+    }
+
+    /// `source file` / `. file`: runs the file's commands in this shell, so its
+    /// variables, aliases and `cd` stay in effect; a sourced file can source
+    /// another. The status is the last command's.
+    async fn execute_source(&mut self, command: &parser::Command) -> Result<()> {
+        let Some(file) = command.args.first() else {
+            anyhow::bail!("{}: filename argument required", command.name);
+        };
+        let path = match file.strip_prefix("~/") {
+            Some(rest) => self.environment.get_home_dir().join(rest),
+            None => self.environment.get_cwd().join(file),
+        };
+        let text = std::fs::read_to_string(&path).map_err(|e| anyhow::anyhow!("{}: {}: {}", command.name, file, e))?;
+
+        let status = Box::pin(self.run_lines(file, &text)).await;
+        if status != 0 {
+            return Err(PieBashError::ExitStatus(status).into());
+        }
+        Ok(())
+    }
+
+    /// Runs a script's lines one at a time, as if typed: blank lines and `#`
+    /// comments are skipped, and a heredoc takes its body from the lines after it.
+    /// A command that merely fails doesn't stop the script, but a fault (a parse
+    /// error, a command that doesn't exist) that no `||` handled is reported with
+    /// its line number and ends it.
+    async fn run_lines(&mut self, name: &str, text: &str) -> i32 {
+        let mut status = 0;
+        let mut lines = text.lines().enumerate();
+        while let Some((index, line)) = lines.next() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
                 continue;
             }
 
-            let metadata = entry.metadata()?;
-            if metadata.is_dir() {
-                output.push_str(&format!("{}/\n", name));
-            } else {
-                output.push_str(&format!("{}\n", name));
+            let mut input = line.to_string();
+            if let Some(heredoc) = parser::split_heredoc(line) {
+                for (_, body_line) in lines.by_ref() {
+                    input.push('\n');
+                    input.push_str(body_line);
+                    if heredoc.ends_at(body_line) {
+                        break;
+                    }
+                }
             }
+
+            status = match Box::pin(self.execute(&input)).await {
+                Ok(status) => status,
+                Err(e) => {
+                    eprintln!("{} {}: line {}: {}", "Error:".red().bold(), name, index + 1, e);
+                    return Self::fault_status(&e);
+                }
+            };
         }
-        
-        Ok(output)
+        status
     }
 
-    fn capture_cat_output(&self, command: &parser::Command) -> Result<String> {
-        use std::fs;
-        use std::path::Path;
-        
-        let mut output = String::new();
-        
-        for file in &command.args {
-            let path = Path::new(file.as_str());
-            if !path.exists() {
-                continue;
+    /// Drops a prefix word (`command ls -l` -> `ls -l`), keeping pipes and redirects.
+    fn shift_command(command: &parser::Command) -> parser::Command {
+        let mut shifted = command.clone();
+        shifted.name = shifted.args.remove(0);
+        shifted
+    }
+
+    async fn execute_repeat(&mut self, command: &parser::Command) -> Result<()> {
+        // -e stops at the first failing run, like `set -e`
+        let stop_on_failure = command.args.first().is_some_and(|a| a == "-e");
+        let mut inner = if stop_on_failure { Self::shift_command(command) } else { command.clone() };
+
+        if inner.args.len() < 2 {
+            anyhow::bail!("repeat: usage: repeat [-e] N command [args...]");
+        }
+        let count: usize = inner.args[0]
+            .parse()
+            .map_err(|_| anyhow::anyhow!("repeat: {}: invalid count", inner.args[0]))?;
+        inner = Self::shift_command(&Self::shift_command(&inner));
+
+        let mut last = Ok(());
+        for _ in 0..count {
+            last = Box::pin(self.execute_single_command(&inner)).await;
+            if let Err(e) = &last {
+                let failed_run = matches!(e.downcast_ref::<PieBashError>(), Some(PieBashError::ExitStatus(_)));
+                if stop_on_failure || !failed_run {
+                    break;
+                }
             }
-            output.push_str(&fs::read_to_string(path)?);
         }
-        
-        Ok(output)
+
+        last
     }
 
-    async fn execute_builtin_with_input(&mut self, command: &parser::Command, input: &str) -> Result<()> {
-        match command.name.as_str() {
-            "grep" => {
-                if command.args.is_empty() {
-                    anyhow::bail!("grep: missing pattern");
-                }
-                
-                let pattern = &command.args[0];
-                
-                for line in input.lines() {
-                    if line.contains(pattern) {
-                        println!("{}", line);
-                    }
-                }
-                Ok(())
+    async fn execute_find_exec(&mut self, command: &parser::Command) -> Result<()> {
+        let exec_at = command.args.iter().position(|a| a == "-exec").unwrap_or_default();
+        let rest = &command.args[exec_at + 1..];
+        let end = rest.iter()
+            .position(|a| a == ";" || a == "\\;" || a == "+")
+            .ok_or_else(|| anyhow::anyhow!("find: missing argument to `-exec'"))?;
+        let template = &rest[..end];
+        let batch = rest[end] == "+";
+        if template.is_empty() {
+            anyhow::bail!("find: missing argument to `-exec'");
+        }
+
+        let mut find = command.clone();
+        find.args = command.args[..exec_at].iter().chain(&rest[end + 1..]).cloned().collect();
+        let paths: Vec<String> = builtins::utils::find_paths(&find, &self.environment)?
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+
+        // `+` runs once with every path in place of `{}`; `;` runs once per path
+        let runs: Vec<Vec<String>> = if batch {
+            if paths.is_empty() {
+                return Ok(());
             }
-            _ => {
-                self.builtins.execute(&command, &mut self.environment)
+            vec![template.iter()
+                .flat_map(|a| if a == "{}" { paths.clone() } else { vec![a.clone()] })
+                .collect()]
+        } else {
+            paths.iter()
+                .map(|path| template.iter().map(|a| a.replace("{}", path)).collect())
+                .collect()
+        };
+
+        let mut last = Ok(());
+        for mut argv in runs {
+            let name = argv.remove(0);
+            let inner = parser::Command::new(name, argv);
+            last = Box::pin(self.execute_single_command(&inner)).await;
+
+            // Like find(1), a per-path command failing just means "no match"
+            if let Err(e) = &last {
+                if !matches!(e.downcast_ref::<PieBashError>(), Some(PieBashError::ExitStatus(_))) {
+                    return last;
+                }
             }
         }
+
+        if batch { last } else { Ok(()) }
     }
 
-    fn is_code_execution(&self, cmd: &str) -> bool {
-        let runtimes = [
-            "python", "python3", "python2",
-            "node", "nodejs",
-            "java", "javac",
-            "ruby", "rb",
-            "rust", "rustc", "cargo",
-            "go",
-            "php", "perl", "lua",
-        ];
+    async fn execute_capture_assignment(&mut self, var: &str, lang: &str, command: &parser::Command) -> Result<()> {
+        let language = LanguageRegistry::canonical_name(lang);
+        let inline = parser::Command::new(format!("@{}", lang), command.args.clone());
+
+        let output = self.code_executor.capture(language, &inline).await?;
 
-        runtimes.contains(&cmd) || cmd.starts_with('@')
+        // Like command substitution, drop trailing newlines
+        self.environment.set_var(var, output.trim_end_matches(['\n', '\r']))
     }
 
-    async fn execute_code(&mut self, command: &parser::Command) -> Result<()> {
-        let language = if command.name.starts_with('@') {
-            command.name[1..].to_string()
-        } else if !command.args.is_empty() {
-            self.language_detector.detect_from_file(&command.args[0])?
-        } else {
-            anyhow::bail!("No code to execute");
+    fn execute_assignment(&mut self, var: &str, subscript: Option<&str>, value: &str, command: &parser::Command) -> Result<()> {
+        if let Some(subscript) = subscript {
+            let index = arith::eval(subscript, &mut self.environment)?;
+            return self.environment.set_array_element(var, index, value);
+        }
+
+        let Some(first) = value.strip_prefix('(') else {
+            return self.environment.set_var(var, value);
+        };
+
+        // The lexer split `(a b c)` into words; gather them back up
+        let mut words: Vec<String> = std::iter::once(first.to_string()).chain(command.args.iter().cloned()).collect();
+        let last = words.pop().unwrap_or_default();
+        let Some(last) = last.strip_suffix(')') else {
+            return Err(PieBashError::ParseError(format!("{}: missing `)' in array assignment", var)).into());
         };
+        words.push(last.to_string());
+        words.retain(|word| !word.is_empty());
 
-        self.code_executor.execute(&language, command).await
+        self.environment.set_array(var, words)
     }
 
-    pub fn get_prompt(&self) -> String {
-        use colored::*;
-        
+    fn is_valid_var_name(name: &str) -> bool {
+        let mut chars = name.chars();
+        match chars.next() {
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+            }
+            _ => false,
+        }
+    }
+
+    /// The working directory with the home directory shown as `~`.
+    fn display_cwd(&self) -> String {
         let cwd = self.environment.get_cwd();
         let home = self.environment.get_home_dir();
 
+        if cwd == &home {
+            "~".to_string()
+        } else if let Ok(relative) = cwd.strip_prefix(&home) {
+            format!("~/{}", relative.display().to_string().replace('\\', "/"))
+        } else {
+            cwd.display().to_string().replace('\\', "/")
+        }
+    }
+
+    /// With `title = true`, names the terminal window after the working directory,
+    /// and the command line while one runs: `piebash: ~/src` or `make - piebash: ~/src`.
+    pub fn update_title(&self, command: Option<&str>) {
+        if !self.config.title {
+            return;
+        }
+        let dir = format!("piebash: {}", self.display_cwd());
+        match command.and_then(|line| line.lines().next()) {
+            Some(line) => crate::terminal::output::set_title(&format!("{} - {}", line.trim(), dir)),
+            None => crate::terminal::output::set_title(&dir),
+        }
+    }
+
+    pub fn get_prompt(&self) -> String {
+        use colored::*;
+
         let username = self.environment.get_var("USERNAME")
             .or_else(|| self.environment.get_var("USER"))
             .unwrap_or_else(|| "user".to_string());
@@ -324,25 +1041,78 @@ impl Shell {
             .or_else(|| self.environment.get_var("HOSTNAME"))
             .unwrap_or_else(|| "DESKTOP".to_string());
 
-        let path_display = if cwd == &home {
-            "~".to_string()
-        } else if let Ok(relative) = cwd.strip_prefix(&home) {
-            format!("~/{}", relative.display().to_string().replace('\\', "/"))
-        } else {
-            cwd.display().to_string().replace('\\', "/")
+        let path_display = self.display_cwd();
+
+        let path_style = self.environment.get_var("PIEBASH_PROMPT_PATH")
+            .or_else(|| self.config.prompt.path.clone());
+        let path_display = match path_style.as_deref() {
+            Some("full") => path_display,
+            Some("abbrev") | None => Self::abbreviate_path(&path_display),
+            Some("basename") => match path_display.rsplit('/').next() {
+                Some(base) if !base.is_empty() => base.to_string(),
+                _ => path_display,
+            },
+            // A typo in the setting keeps the default rather than breaking every prompt
+            Some(_) => Self::abbreviate_path(&path_display),
         };
 
+        let symbol = self.environment.get_var("PIEBASH_PROMPT_SYMBOL")
+            .or_else(|| self.config.prompt.symbol.clone())
+            .unwrap_or_else(|| if crate::platform::is_root() { "#" } else { "$" }.to_string());
+
         // Colored prompt - correct format
         format!(
-            "{} {}@{} {}\n$ ",
+            "{} {}@{} {}\n{} ",
             "[piebash]".yellow().bold(),
             username.green(),
             hostname.green(),
-            path_display.blue()
+            path_display.blue(),
+            symbol
         )
     }
 
+    /// Fish-style path: every component but the last shortened to one character
+    /// (two for dot-directories), e.g. `~/projects/foo` -> `~/p/foo`.
+    fn abbreviate_path(path: &str) -> String {
+        let parts: Vec<&str> = path.split('/').collect();
+        let last = parts.len().saturating_sub(1);
+
+        parts
+            .iter()
+            .enumerate()
+            .map(|(i, part)| {
+                if i == last || part.is_empty() || *part == "~" || part.ends_with(':') {
+                    part.to_string()
+                } else {
+                    let keep = if part.starts_with('.') { 2 } else { 1 };
+                    part.chars().take(keep).collect()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// The `complete -W` registrations, for the line editor's completer.
+    pub fn completions(&self) -> &std::collections::BTreeMap<String, Vec<String>> {
+        self.environment.get_completions()
+    }
+
+    /// What Tab offers for a command name: builtins and aliases.
+    pub fn command_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.builtins.commands().iter().filter(|name| !matches!(name.as_str(), "((" | ".")).cloned().collect();
+        names.extend(self.environment.get_aliases().keys().cloned());
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Records a command line for `history` and `fc`, as the line editor does:
+    /// an immediate repeat isn't kept twice, and only the last `histsize` lines are.
+    pub fn add_history(&mut self, line: &str) {
+        self.environment.add_history(line, self.config.histsize);
+    }
+
     pub fn get_history_file(&self) -> PathBuf {
-        self.environment.get_home_dir().join(".piebash_history")
+        crate::platform::adapter::paths().history_file.clone()
     }
 }
\ No newline at end of file
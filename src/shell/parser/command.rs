@@ -1,3 +1,7 @@
+use anyhow::Result;
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+
 #[derive(Debug, Clone)]
 pub struct Command {
     pub name: String,
@@ -5,14 +9,46 @@ pub struct Command {
     pub redirect_stdout: Option<Redirect>,
     pub redirect_stderr: Option<Redirect>,
     pub pipe_to: Option<Box<Command>>,
-    pub chain_operator: Option<ChainOperator>,  // NEW
-    pub next_command: Option<Box<Command>>,     // NEW
+    pub stdin_input: Option<String>,            // heredoc body fed to stdin
+    pub background: bool,                       // ends with `&`
 }
 
 #[derive(Debug, Clone)]
 pub struct Redirect {
     pub target: String,
     pub append: bool,
+    pub force: bool,  // `>|`: overwrite even with noclobber set
+}
+
+impl Redirect {
+    /// Opens the target for writing. With `noclobber`, a plain `>` refuses to
+    /// truncate an existing regular file.
+    pub fn open(&self, noclobber: bool) -> Result<File> {
+        if self.append {
+            return Ok(OpenOptions::new().create(true).append(true).open(&self.target)?);
+        }
+
+        if noclobber && !self.force && Path::new(&self.target).is_file() {
+            anyhow::bail!("{}: cannot overwrite existing file", self.target);
+        }
+        Ok(OpenOptions::new().create(true).write(true).truncate(true).open(&self.target)?)
+    }
+}
+
+/// The open files behind a command's `>` / `2>` / `&>` redirects.
+#[derive(Debug, Default)]
+pub struct RedirectFiles {
+    pub stdout: Option<File>,
+    pub stderr: Option<File>,
+}
+
+impl RedirectFiles {
+    pub fn try_clone(&self) -> Result<Self> {
+        Ok(Self {
+            stdout: self.stdout.as_ref().map(File::try_clone).transpose()?,
+            stderr: self.stderr.as_ref().map(File::try_clone).transpose()?,
+        })
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -22,6 +58,16 @@ pub enum ChainOperator {
     Semicolon // ;
 }
 
+impl ChainOperator {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::And => "&&",
+            Self::Or => "||",
+            Self::Semicolon => ";",
+        }
+    }
+}
+
 impl Command {
     pub fn new(name: String, args: Vec<String>) -> Self {
         Self {
@@ -30,24 +76,34 @@ impl Command {
             redirect_stdout: None,
             redirect_stderr: None,
             pipe_to: None,
-            chain_operator: None,
-            next_command: None,
+            stdin_input: None,
+            background: false,
         }
     }
 
     pub fn with_stdout_redirect(mut self, target: String, append: bool) -> Self {
-        self.redirect_stdout = Some(Redirect { target, append });
+        self.redirect_stdout = Some(Redirect { target, append, force: false });
         self
     }
 
-    pub fn with_pipe(mut self, next: Command) -> Self {
-        self.pipe_to = Some(Box::new(next));
-        self
+    /// Opens the redirect targets. When stdout and stderr go to the same file
+    /// (`&> file`), they share one handle so their output interleaves in order.
+    pub fn open_redirects(&self, noclobber: bool) -> Result<RedirectFiles> {
+        let stdout = self.redirect_stdout.as_ref().map(|r| r.open(noclobber)).transpose()?;
+        let stderr = match (&self.redirect_stderr, &self.redirect_stdout, &stdout) {
+            (Some(err), Some(out), Some(file)) if err.target == out.target => Some(file.try_clone()?),
+            (Some(err), _, _) => Some(err.open(noclobber)?),
+            _ => None,
+        };
+        Ok(RedirectFiles { stdout, stderr })
+    }
+
+    pub fn has_redirects(&self) -> bool {
+        self.redirect_stdout.is_some() || self.redirect_stderr.is_some()
     }
 
-    pub fn with_chain(mut self, operator: ChainOperator, next: Command) -> Self {
-        self.chain_operator = Some(operator);
-        self.next_command = Some(Box::new(next));
+    pub fn with_pipe(mut self, next: Command) -> Self {
+        self.pipe_to = Some(Box::new(next));
         self
     }
 }
\ No newline at end of file
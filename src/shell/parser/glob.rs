@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// The `shopt` and `set -o` options that change how patterns expand.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GlobOptions {
+    /// `**` matches any number of directories
+    pub globstar: bool,
+    /// Wildcard components match regardless of case
+    pub nocaseglob: bool,
+    /// `set -f`: patterns are never expanded
+    pub noglob: bool,
+}
+
+impl GlobOptions {
+    /// Reads the options from a `$BASHOPTS`-style `a:b:c` list.
+    pub fn from_bashopts(opts: &str) -> Self {
+        let on = |name: &str| opts.split(':').any(|o| o == name);
+        Self { globstar: on("globstar"), nocaseglob: on("nocaseglob"), noglob: false }
+    }
+
+    /// Reads `$BASHOPTS` and, for `noglob`, `$SHELLOPTS` from the lexer's variables.
+    pub fn from_env(env: &HashMap<String, String>) -> Self {
+        let mut options = env.get("BASHOPTS").map(|opts| Self::from_bashopts(opts)).unwrap_or_default();
+        options.noglob = env.get("SHELLOPTS").is_some_and(|opts| opts.split(':').any(|o| o == "noglob"));
+        options
+    }
+}
+
+pub fn has_glob_chars(word: &str) -> bool {
+    word.contains(['*', '?', '['])
+}
+
+/// Drops the backslashes that kept wildcards literal, for a word left as typed.
+pub fn unescape(word: &str) -> String {
+    let mut out = String::new();
+    let mut chars = word.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' && chars.peek().is_some_and(|next| "*?[".contains(*next)) {
+            continue;
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Pathname expansion of an unquoted word: the paths matching `pattern`, sorted.
+///
+/// Empty when nothing matches, in which case the word stays as typed, as in bash.
+/// With `globstar`, a `**` component matches any number of directories, so
+/// `src/**/*.rs` finds files at any depth; with `nocaseglob`, `*.JPG` finds
+/// `photo.jpg`. Hidden names only match a pattern that starts with `.`.
+pub fn expand(pattern: &str, options: GlobOptions) -> Vec<String> {
+    let components: Vec<&str> = pattern.split('/').filter(|c| !c.is_empty()).collect();
+    let mut paths = vec![if pattern.starts_with('/') { "/".to_string() } else { String::new() }];
+
+    for (i, component) in components.iter().enumerate() {
+        let last = i + 1 == components.len();
+        let mut next = Vec::new();
+
+        for base in &paths {
+            if options.globstar && *component == "**" {
+                next.extend(descendants(base, last));
+            } else if has_glob_chars(component) {
+                next.extend(children(base, component, last, options.nocaseglob));
+            } else {
+                let path = join(base, component);
+                if !last || Path::new(&path).symlink_metadata().is_ok() {
+                    next.push(path);
+                }
+            }
+        }
+
+        paths = next;
+    }
+
+    // `*/` matches directories only
+    if pattern.ends_with('/') && !components.is_empty() {
+        paths.retain(|path| Path::new(path).is_dir());
+        for path in &mut paths {
+            path.push('/');
+        }
+    }
+
+    if components.is_empty() {
+        return Vec::new();
+    }
+    paths.sort();
+    paths
+}
+
+/// Entries of `base` whose names match `pattern`; only directories unless `last`.
+fn children(base: &str, pattern: &str, last: bool, nocase: bool) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir_of(base)) else {
+        return Vec::new();
+    };
+
+    let fold = |s: &str| if nocase { s.to_lowercase() } else { s.to_string() };
+    let pattern: Vec<char> = fold(pattern).chars().collect();
+    let mut found = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') && pattern.first() != Some(&'.') {
+            continue;
+        }
+        if !matches(&pattern, &fold(&name).chars().collect::<Vec<_>>()) {
+            continue;
+        }
+        if last || entry.path().is_dir() {
+            found.push(join(base, &name));
+        }
+    }
+    found
+}
+
+/// What `**` matches under `base`: every file and directory when it is the last
+/// component, otherwise `base` itself and each directory below it.
+fn descendants(base: &str, last: bool) -> Vec<String> {
+    let dir = dir_of(base);
+    WalkDir::new(&dir)
+        .min_depth(usize::from(last))
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.'))
+        .flatten()
+        .filter(|entry| last || entry.file_type().is_dir())
+        .map(|entry| match entry.path().strip_prefix(&dir) {
+            Ok(rel) if !rel.as_os_str().is_empty() => join(base, &rel.to_string_lossy()),
+            _ => base.to_string(),
+        })
+        .collect()
+}
+
+fn join(base: &str, name: &str) -> String {
+    if base.is_empty() {
+        name.to_string()
+    } else if base.ends_with('/') {
+        format!("{}{}", base, name)
+    } else {
+        format!("{}/{}", base, name)
+    }
+}
+
+fn dir_of(base: &str) -> String {
+    if base.is_empty() { ".".to_string() } else { base.to_string() }
+}
+
+/// `*`, `?` and `[...]` (with ranges and `!`/`^` negation) matching of a whole name.
+/// A backslash makes the next character literal.
+fn matches(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('\\') if pattern.len() > 1 => name.first() == Some(&pattern[1]) && matches(&pattern[2..], &name[1..]),
+        Some('*') => (0..=name.len()).any(|skip| matches(&pattern[1..], &name[skip..])),
+        Some('?') => !name.is_empty() && matches(&pattern[1..], &name[1..]),
+        Some('[') => {
+            let Some(&c) = name.first() else {
+                return false;
+            };
+            match bracket(&pattern[1..], c) {
+                Some((matched, len)) => matched && matches(&pattern[1 + len..], &name[1..]),
+                // An unclosed `[` is just a character
+                None => c == '[' && matches(&pattern[1..], &name[1..]),
+            }
+        }
+        Some(c) => name.first() == Some(c) && matches(&pattern[1..], &name[1..]),
+    }
+}
+
+/// Matches `c` against the class that starts just after a `[`. Returns whether it
+/// matched and how many pattern characters the class used, or None if it's unclosed.
+fn bracket(class: &[char], c: char) -> Option<(bool, usize)> {
+    let (negate, mut i) = match class.first() {
+        Some('!' | '^') => (true, 1),
+        _ => (false, 0),
+    };
+    let start = i;
+    let mut matched = false;
+
+    while i < class.len() {
+        // A `]` right after the `[` is part of the class
+        if class[i] == ']' && i > start {
+            return Some((matched != negate, i + 1));
+        }
+        if class.get(i + 1) == Some(&'-') && class.get(i + 2).is_some_and(|end| *end != ']') {
+            matched |= class[i] <= c && c <= class[i + 2];
+            i += 3;
+        } else {
+            matched |= class[i] == c;
+            i += 1;
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::parser::lexer::Lexer;
+
+    /// A scratch directory holding empty files called `names`.
+    fn fixture(names: &[&str]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for name in names {
+            std::fs::write(dir.path().join(name), "").unwrap();
+        }
+        dir
+    }
+
+    /// Expands `pattern` inside `dir`, giving back the names relative to it.
+    fn expand_in(dir: &tempfile::TempDir, pattern: &str) -> Vec<String> {
+        let base = dir.path().to_string_lossy().to_string();
+        expand(&format!("{}/{}", base, pattern), GlobOptions::default())
+            .into_iter()
+            .map(|path| path[base.len() + 1..].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn star_matches_any_run_of_characters_sorted() {
+        let dir = fixture(&["b.rs", "a.rs", "c.txt"]);
+        assert_eq!(expand_in(&dir, "*.rs"), ["a.rs", "b.rs"]);
+        assert_eq!(expand_in(&dir, "*"), ["a.rs", "b.rs", "c.txt"]);
+    }
+
+    #[test]
+    fn question_mark_matches_exactly_one_character() {
+        let dir = fixture(&["ab1", "ab2", "ab", "ab12"]);
+        assert_eq!(expand_in(&dir, "ab?"), ["ab1", "ab2"]);
+    }
+
+    #[test]
+    fn character_classes_match_ranges_and_negation() {
+        let dir = fixture(&["a1", "b1", "c1", "d1"]);
+        assert_eq!(expand_in(&dir, "[a-c]1"), ["a1", "b1", "c1"]);
+        assert_eq!(expand_in(&dir, "[ad]1"), ["a1", "d1"]);
+        assert_eq!(expand_in(&dir, "[!a-c]1"), ["d1"]);
+    }
+
+    #[test]
+    fn hidden_files_only_match_a_leading_dot() {
+        let dir = fixture(&[".hidden.rs", "shown.rs"]);
+        assert_eq!(expand_in(&dir, "*.rs"), ["shown.rs"]);
+        assert_eq!(expand_in(&dir, "?hidden.rs"), Vec::<String>::new());
+        assert_eq!(expand_in(&dir, ".*.rs"), [".hidden.rs"]);
+    }
+
+    #[test]
+    fn no_match_expands_to_nothing() {
+        let dir = fixture(&["a.rs"]);
+        assert!(expand_in(&dir, "*.zz").is_empty());
+    }
+
+    #[test]
+    fn lexer_leaves_unmatched_and_quoted_patterns_as_typed() {
+        let dir = fixture(&["a.rs", "b.rs"]);
+        let base = dir.path().to_string_lossy();
+        let tokens = |input: String| Lexer::new().tokenize(&input).unwrap();
+
+        assert_eq!(tokens(format!("echo {}/*.rs", base)), ["echo".to_string(), format!("{}/a.rs", base), format!("{}/b.rs", base)]);
+        assert_eq!(tokens(format!("echo '{}/*.rs'", base)), ["echo".to_string(), format!("{}/*.rs", base)]);
+        assert_eq!(tokens(format!("echo \"{}/*.rs\"", base)), ["echo".to_string(), format!("{}/*.rs", base)]);
+        assert_eq!(tokens(format!("echo {}/*.zz", base)), ["echo".to_string(), format!("{}/*.zz", base)]);
+    }
+}
@@ -1,6 +1,9 @@
 use anyhow::Result;
 use std::collections::HashMap;
 
+use super::glob::{self, GlobOptions};
+use crate::shell::arith::{self, ReadOnlyVars};
+
 pub struct Lexer;
 
 impl Lexer {
@@ -17,6 +20,11 @@ impl Lexer {
         let mut current = String::new();
         let mut in_quotes = false;
         let mut quote_char = ' ';
+        // Whether `current` has unquoted wildcards, i.e. is a glob pattern
+        let mut globbing = false;
+        // Whether `current` has a `\*`-style wildcard kept literal
+        let mut escaped = false;
+        let glob_options = GlobOptions::from_env(env);
 
         let chars: Vec<char> = input.chars().collect();
         let mut i = 0;
@@ -27,7 +35,28 @@ impl Lexer {
             match ch {
                 '$' if !in_quotes || quote_char == '"' => {
                     // Variable expansion
-                    if i + 1 < chars.len() && chars[i + 1] == '{' {
+                    if chars[i + 1..].starts_with(&['(', '(']) {
+                        // $(( expr )) - arithmetic expansion
+                        let start = i + 3;
+                        let mut depth = 0;
+                        let mut end = start;
+                        while end < chars.len() {
+                            match chars[end] {
+                                '(' => depth += 1,
+                                ')' if depth == 0 && chars.get(end + 1) == Some(&')') => break,
+                                ')' => depth -= 1,
+                                _ => {}
+                            }
+                            end += 1;
+                        }
+                        if end >= chars.len() {
+                            anyhow::bail!("unexpected end of input: missing `))'");
+                        }
+                        let expr: String = chars[start..end].iter().collect();
+                        let value = arith::eval(&expr, &mut ReadOnlyVars(env))?;
+                        current.push_str(&value.to_string());
+                        i = end + 1;
+                    } else if i + 1 < chars.len() && chars[i + 1] == '{' {
                         // ${VAR} syntax
                         i += 2;
                         let mut var_name = String::new();
@@ -35,7 +64,19 @@ impl Lexer {
                             var_name.push(chars[i]);
                             i += 1;
                         }
-                        if let Some(value) = env.get(&var_name) {
+                        let mut words = Self::expand_braced(&var_name, env)?.into_iter();
+                        if let Some(first) = words.next() {
+                            current.push_str(&first);
+                        }
+                        // `${arr[@]}` is one word per element
+                        for word in words {
+                            tokens.push(std::mem::take(&mut current));
+                            current = word;
+                        }
+                    } else if i + 1 < chars.len() && "?#@*!".contains(chars[i + 1]) {
+                        // $? - last exit status; $# $@ $* - positional parameters; $! - last background job
+                        i += 1;
+                        if let Some(value) = env.get(&chars[i].to_string()) {
                             current.push_str(value);
                         }
                     } else if i + 1 < chars.len() {
@@ -59,8 +100,7 @@ impl Lexer {
                         if ch == quote_char {
                             in_quotes = false;
                             if !current.is_empty() {
-                                tokens.push(current.clone());
-                                current.clear();
+                                Self::push_word(&mut tokens, &mut current, &mut globbing, &mut escaped, glob_options);
                             }
                         } else {
                             current.push(ch);
@@ -74,11 +114,20 @@ impl Lexer {
                     if in_quotes {
                         current.push(ch);
                     } else if !current.is_empty() {
-                        tokens.push(current.clone());
-                        current.clear();
+                        Self::push_word(&mut tokens, &mut current, &mut globbing, &mut escaped, glob_options);
                     }
                 }
+                '\\' if !in_quotes && chars.get(i + 1).is_some_and(|next| "*?[".contains(*next)) => {
+                    // Kept escaped for the glob matcher, dropped if the word stays as typed
+                    current.push(ch);
+                    current.push(chars[i + 1]);
+                    escaped = true;
+                    i += 1;
+                }
                 _ => {
+                    if !in_quotes && "*?[".contains(ch) {
+                        globbing = true;
+                    }
                     current.push(ch);
                 }
             }
@@ -87,9 +136,155 @@ impl Lexer {
         }
 
         if !current.is_empty() {
-            tokens.push(current);
+            Self::push_word(&mut tokens, &mut current, &mut globbing, &mut escaped, glob_options);
         }
 
         Ok(tokens)
     }
+
+    /// Expands parameters and arithmetic in free text, as in the body of an unquoted
+    /// heredoc: `$VAR`, `${VAR}`, `$?` and `$((expr))`. Quotes are ordinary
+    /// characters and nothing is split or globbed; `\$`, `\\` and `` \` `` stay literal.
+    pub fn expand_text(&self, text: &str, env: &HashMap<String, String>) -> Result<String> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut out = String::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let ch = chars[i];
+            match (ch, chars.get(i + 1).copied()) {
+                ('\\', Some(next @ ('$' | '\\' | '`'))) => {
+                    out.push(next);
+                    i += 2;
+                }
+                ('$', Some('(')) if chars.get(i + 2) == Some(&'(') => {
+                    let start = i + 3;
+                    let mut depth = 0;
+                    let mut end = start;
+                    while end < chars.len() {
+                        match chars[end] {
+                            '(' => depth += 1,
+                            ')' if depth == 0 && chars.get(end + 1) == Some(&')') => break,
+                            ')' => depth -= 1,
+                            _ => {}
+                        }
+                        end += 1;
+                    }
+                    if end >= chars.len() {
+                        anyhow::bail!("unexpected end of input: missing `))'");
+                    }
+                    let expr: String = chars[start..end].iter().collect();
+                    out.push_str(&arith::eval(&expr, &mut ReadOnlyVars(env))?.to_string());
+                    i = end + 2;
+                }
+                ('$', Some('{')) => {
+                    let Some(len) = chars[i + 2..].iter().position(|c| *c == '}') else {
+                        anyhow::bail!("bad substitution: missing `}}'");
+                    };
+                    let expr: String = chars[i + 2..i + 2 + len].iter().collect();
+                    out.push_str(&Self::expand_braced(&expr, env)?.join(" "));
+                    i += len + 3;
+                }
+                ('$', Some(special)) if "?#@*!".contains(special) => {
+                    if let Some(value) = env.get(&special.to_string()) {
+                        out.push_str(value);
+                    }
+                    i += 2;
+                }
+                ('$', Some(next)) if next.is_alphanumeric() || next == '_' => {
+                    let len = chars[i + 1..].iter().take_while(|c| c.is_alphanumeric() || **c == '_').count();
+                    let name: String = chars[i + 1..i + 1 + len].iter().collect();
+                    if let Some(value) = env.get(&name).cloned().or_else(|| std::env::var(&name).ok()) {
+                        out.push_str(&value);
+                    }
+                    i += len + 1;
+                }
+                _ => {
+                    out.push(ch);
+                    i += 1;
+                }
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Ends the word in `current`, replacing a glob pattern with the paths it matches.
+    fn push_word(tokens: &mut Vec<String>, current: &mut String, globbing: &mut bool, escaped: &mut bool, options: GlobOptions) {
+        let word = std::mem::take(current);
+        if std::mem::take(globbing) && !options.noglob {
+            let paths = glob::expand(&word, options);
+            if !paths.is_empty() {
+                *escaped = false;
+                tokens.extend(paths);
+                return;
+            }
+        }
+        if std::mem::take(escaped) {
+            tokens.push(glob::unescape(&word));
+        } else {
+            tokens.push(word);
+        }
+    }
+
+    /// The words `${expr}` expands to: a variable, an array element (`${arr[i]}`), all
+    /// elements (`${arr[@]}` as separate words, `${arr[*]}` as one), their indices
+    /// (`${!arr[@]}`), or a length (`${#arr[@]}`, `${#var}`).
+    fn expand_braced(expr: &str, env: &HashMap<String, String>) -> Result<Vec<String>> {
+        let (length, expr) = match expr.strip_prefix('#') {
+            Some(rest) if !rest.is_empty() => (true, rest),
+            _ => (false, expr),
+        };
+
+        let Some((name, subscript)) = expr.strip_suffix(']').and_then(|e| e.split_once('[')) else {
+            let value = env.get(expr).cloned();
+            return Ok(match (length, value) {
+                (true, value) => vec![value.unwrap_or_default().chars().count().to_string()],
+                (false, value) => value.into_iter().collect(),
+            });
+        };
+
+        let indices: Vec<&str> = env
+            .get(&format!("!{}[@]", name))
+            .map(|list| list.split_whitespace().collect())
+            .unwrap_or_default();
+        let element = |index: &str| {
+            env.get(&format!("{}[{}]", name, index))
+                .or_else(|| if index == "0" { env.get(name) } else { None })
+                .cloned()
+        };
+
+        if subscript == "@" || subscript == "*" {
+            let values: Vec<String> = if let Some(name) = name.strip_prefix('!') {
+                env.get(&format!("!{}[@]", name))
+                    .map(|list| list.split_whitespace().map(String::from).collect())
+                    .unwrap_or_default()
+            } else if indices.is_empty() {
+                // A scalar is a one-element array
+                element("0").into_iter().collect()
+            } else {
+                indices.iter().filter_map(|index| element(index)).collect()
+            };
+
+            return Ok(if length {
+                vec![values.len().to_string()]
+            } else if subscript == "*" {
+                vec![values.join(" ")]
+            } else {
+                values
+            });
+        }
+
+        let mut index = arith::eval(subscript, &mut ReadOnlyVars(env))?;
+        if index < 0 {
+            let len = indices.last().and_then(|last| last.parse::<i64>().ok()).map_or(0, |last| last + 1);
+            index += len;
+        }
+        let value = if index < 0 { None } else { element(&index.to_string()) };
+
+        Ok(match (length, value) {
+            (true, value) => vec![value.unwrap_or_default().chars().count().to_string()],
+            (false, value) => value.into_iter().collect(),
+        })
+    }
 }
\ No newline at end of file
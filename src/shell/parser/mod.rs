@@ -1,10 +1,12 @@
 pub mod command;
+pub mod glob;
 pub mod lexer;
 
-pub use command::{Command, Redirect, ChainOperator};
+pub use command::{Command, Redirect, RedirectFiles, ChainOperator};
 use anyhow::Result;
 use self::lexer::Lexer;
 use std::collections::HashMap;
+use crate::utils::error::PieBashError;
 
 pub struct CommandParser {
     lexer: Lexer,
@@ -22,18 +24,25 @@ impl CommandParser {
     }
 
     pub fn parse_with_env(&self, input: &str, env: &HashMap<String, String>) -> Result<Command> {
-        // Check for command chaining (&&, ||, ;)
-        if let Some(cmd) = self.try_parse_chain(input, env)? {
-            return Ok(cmd);
+        // Heredoc: the body follows the command line
+        if let Some((line, body)) = input.split_once('\n') {
+            return self.parse_heredoc(line, body, env);
+        }
+
+        // `cmd &` / `a | b &` runs in the background
+        if let Some(line) = strip_background(input) {
+            let mut command = self.parse_with_env(line, env)?;
+            command.background = true;
+            return Ok(command);
         }
 
         // Check for pipes
-        if input.contains('|') {
+        if find_operator(input, "|").is_some() {
             return self.parse_pipeline_with_env(input, env);
         }
 
         // Check for redirects
-        if input.contains('>') {
+        if find_operator(input, ">").is_some() {
             return self.parse_with_redirect_env(input, env);
         }
 
@@ -41,45 +50,88 @@ impl CommandParser {
         self.parse_simple_with_env(input, env)
     }
 
-    fn try_parse_chain(&self, input: &str, env: &HashMap<String, String>) -> Result<Option<Command>> {
-        // Check for && (must check before single &)
-        if input.contains("&&") {
-            let parts: Vec<&str> = input.splitn(2, "&&").collect();
-            if parts.len() == 2 {
-                let first = self.parse_with_env(parts[0].trim(), env)?;
-                let second = self.parse_with_env(parts[1].trim(), env)?;
-                return Ok(Some(first.with_chain(ChainOperator::And, second)));
-            }
-        }
+    fn parse_heredoc(&self, line: &str, body: &str, env: &HashMap<String, String>) -> Result<Command> {
+        let heredoc = split_heredoc(line)
+            .ok_or_else(|| PieBashError::ParseError("unexpected newline in command".to_string()))?;
 
-        // Check for ||
-        if input.contains("||") {
-            let parts: Vec<&str> = input.splitn(2, "||").collect();
-            if parts.len() == 2 {
-                let first = self.parse_with_env(parts[0].trim(), env)?;
-                let second = self.parse_with_env(parts[1].trim(), env)?;
-                return Ok(Some(first.with_chain(ChainOperator::Or, second)));
+        let mut text = String::new();
+        for body_line in body.lines() {
+            if heredoc.ends_at(body_line) {
+                break;
             }
+            text.push_str(if heredoc.strip_tabs { body_line.trim_start_matches('\t') } else { body_line });
+            text.push('\n');
         }
+        // `<<EOF` expands `$VAR`, `${VAR}`, `$?` and `$((...))` in the body; `<<'EOF'` doesn't
+        if heredoc.expand {
+            text = self.lexer.expand_text(&text, env)?;
+        }
+
+        let mut command = self.parse_with_env(&heredoc.command, env)?;
+        command.stdin_input = Some(text);
+        Ok(command)
+    }
 
-        // Check for ;
-        if input.contains(';') {
-            let parts: Vec<&str> = input.splitn(2, ';').collect();
-            if parts.len() == 2 {
-                let first = self.parse_with_env(parts[0].trim(), env)?;
-                let second = self.parse_with_env(parts[1].trim(), env)?;
-                return Ok(Some(first.with_chain(ChainOperator::Semicolon, second)));
+    /// Splits `a && b || c; d` into the commands of its chain, each with the operator
+    /// after it, leaving their text unparsed: the shell parses each one just before
+    /// running it, so `x=1; echo $x` and `false; echo $?` see what ran before them.
+    /// A heredoc body goes with the command that has the `<<` marker.
+    pub fn split_chain(&self, input: &str) -> Result<Vec<(String, Option<ChainOperator>)>> {
+        let (line, mut body) = match input.split_once('\n') {
+            Some((line, body)) => (line, Some(body)),
+            None => (input, None),
+        };
+
+        let mut segments = chain_segments(line)?;
+        match segments.last_mut() {
+            // A trailing `;` just ends the line
+            Some((_, operator @ Some(ChainOperator::Semicolon))) => *operator = None,
+            Some((_, Some(operator))) => {
+                return Err(PieBashError::ParseError(format!(
+                    "syntax error: unexpected end of line after `{}'",
+                    operator.symbol()
+                )).into());
             }
+            _ => {}
         }
 
-        Ok(None)
+        let chain: Vec<(String, Option<ChainOperator>)> = segments
+            .into_iter()
+            .map(|(text, operator)| match body {
+                Some(lines) if split_heredoc(text).is_some() => {
+                    body = None;
+                    (format!("{}\n{}", text, lines), operator)
+                }
+                _ => (text.to_string(), operator),
+            })
+            .collect();
+        if body.is_some() {
+            return Err(PieBashError::ParseError("unexpected newline in command".to_string()).into());
+        }
+        Ok(chain)
     }
 
     fn parse_simple_with_env(&self, input: &str, env: &HashMap<String, String>) -> Result<Command> {
+        // `(( expr ))` keeps its expression as one raw argument for the evaluator
+        if let Some(expr) = input.trim().strip_prefix("((").and_then(|rest| rest.strip_suffix("))")) {
+            return Ok(Command::new("((".to_string(), vec![expr.trim().to_string()]));
+        }
+
+        // `cmd <<< word`: the expanded word and a newline become stdin
+        if let Some((cmd_line, word)) = split_here_string(input) {
+            if word.is_empty() {
+                return Err(PieBashError::ParseError("<<<: missing word".to_string()).into());
+            }
+            let mut command = self.parse_simple_with_env(&cmd_line, env)?;
+            let text = self.lexer.tokenize_with_env(&word, env)?.join(" ");
+            command.stdin_input = Some(text + "\n");
+            return Ok(command);
+        }
+
         let tokens = self.lexer.tokenize_with_env(input, env)?;
 
         if tokens.is_empty() {
-            anyhow::bail!("Empty command");
+            return Err(PieBashError::ParseError("empty command".to_string()).into());
         }
 
         let name = tokens[0].clone();
@@ -89,36 +141,52 @@ impl CommandParser {
     }
 
     fn parse_with_redirect_env(&self, input: &str, env: &HashMap<String, String>) -> Result<Command> {
-        let append = input.contains(">>");
-        let redirect_op = if append { ">>" } else { ">" };
-        
-        let parts: Vec<&str> = input.splitn(2, redirect_op).collect();
-        if parts.len() != 2 {
-            anyhow::bail!("Invalid redirect syntax");
-        }
+        let pos = find_operator(input, ">")
+            .ok_or_else(|| PieBashError::ParseError("invalid redirect syntax".to_string()))?;
+        let append = input[pos..].starts_with(">>");
+        let force = input[pos..].starts_with(">|");
+        let op_len = if append || force { 2 } else { 1 };
+        // `&> file` / `&>> file` take stderr along
+        let both = input[..pos].ends_with('&');
 
-        let cmd_part = parts[0].trim();
-        let file_part = parts[1].trim();
+        let cmd_part = input[..pos].trim_end_matches('&').trim();
+        let file_part = input[pos + op_len..].trim();
 
         let mut command = self.parse_simple_with_env(cmd_part, env)?;
-        command.redirect_stdout = Some(Redirect {
+        let redirect = Redirect {
             target: file_part.to_string(),
             append,
-        });
+            force,
+        };
+        if both {
+            command.redirect_stderr = Some(redirect.clone());
+        }
+        command.redirect_stdout = Some(redirect);
 
         Ok(command)
     }
 
     fn parse_pipeline_with_env(&self, input: &str, env: &HashMap<String, String>) -> Result<Command> {
-        let parts: Vec<&str> = input.split('|').map(|s| s.trim()).collect();
-        
+        let mut parts = Vec::new();
+        let mut rest = input;
+        while let Some(pos) = find_operator(rest, "|") {
+            parts.push(rest[..pos].trim());
+            rest = &rest[pos + 1..];
+        }
+        parts.push(rest.trim());
+
         if parts.len() < 2 {
-            anyhow::bail!("Invalid pipe syntax");
+            return Err(PieBashError::ParseError("invalid pipe syntax".to_string()).into());
         }
 
         let mut commands: Vec<Command> = Vec::new();
         for part in parts {
-            let cmd = self.parse_simple_with_env(part, env)?;
+            // Each stage takes its own redirects: `seq 3 | tr 1 x > out.txt`
+            let cmd = if find_operator(part, ">").is_some() {
+                self.parse_with_redirect_env(part, env)?
+            } else {
+                self.parse_simple_with_env(part, env)?
+            };
             commands.push(cmd);
         }
 
@@ -130,4 +198,389 @@ impl CommandParser {
 
         Ok(final_cmd)
     }
-}
\ No newline at end of file
+}
+
+/// The command line before a trailing `&`, if it has one. `&&`, `&>` and `>&` aren't it.
+fn strip_background(input: &str) -> Option<&str> {
+    let line = input.trim_end().strip_suffix('&')?;
+    if line.ends_with(['&', '>']) || line.trim().is_empty() {
+        return None;
+    }
+    Some(line.trim_end())
+}
+
+/// A `<<WORD` / `<<-WORD` heredoc marker, split out of its command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heredoc {
+    /// The command line without the marker
+    pub command: String,
+    pub delimiter: String,
+    /// `<<-`: leading tabs are stripped from body lines
+    pub strip_tabs: bool,
+    /// The delimiter was unquoted, so variables in the body expand
+    pub expand: bool,
+}
+
+impl Heredoc {
+    /// Whether `body_line` is the delimiter line that ends the body.
+    pub fn ends_at(&self, body_line: &str) -> bool {
+        let line = if self.strip_tabs { body_line.trim_start_matches('\t') } else { body_line };
+        line == self.delimiter
+    }
+}
+
+/// Finds the heredoc marker in `line`. A `<<` in arithmetic, `let x=1<<2` or
+/// `(( 1 << 2 ))`, is a shift rather than a heredoc.
+pub fn split_heredoc(line: &str) -> Option<Heredoc> {
+    let mut pos = 0;
+    loop {
+        pos += find_operator(&line[pos..], "<<")?;
+        if !in_let(&line[..pos]) {
+            break;
+        }
+        pos += 2;
+    }
+    let rest = &line[pos + 2..];
+    if rest.starts_with('<') {
+        return None;
+    }
+
+    let (strip_tabs, rest) = match rest.strip_prefix('-') {
+        Some(r) => (true, r),
+        None => (false, rest),
+    };
+    let rest = rest.trim_start();
+
+    // Quoted delimiters ('EOF' / "EOF" / \EOF) end at the closing quote and keep
+    // the body literal
+    let (delimiter, consumed, quoted) = match rest.chars().next() {
+        Some(q @ ('\'' | '"')) => {
+            let end = rest[1..].find(q)?;
+            (rest[1..end + 1].to_string(), end + 2, true)
+        }
+        first => {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || "|;&<>".contains(c))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            match first {
+                Some('\\') => (word[1..].to_string(), end, true),
+                _ => (word.to_string(), end, false),
+            }
+        }
+    };
+
+    if delimiter.is_empty() {
+        return None;
+    }
+
+    let cmd_line = format!("{} {}", line[..pos].trim_end(), rest[consumed..].trim_start());
+    Some(Heredoc { command: cmd_line.trim().to_string(), delimiter, strip_tabs, expand: !quoted })
+}
+
+/// Whether the end of `before` is inside a `let` command's arguments.
+fn in_let(before: &str) -> bool {
+    let command = before.rsplit([';', '&', '|']).next().unwrap_or(before);
+    let mut words = command.split_whitespace();
+    words.next() == Some("let") && words.next().is_some()
+}
+
+/// Splits a `<<< word` here-string out of `line`, returning the command line
+/// without it and the word, still quoted and unexpanded.
+fn split_here_string(line: &str) -> Option<(String, String)> {
+    let pos = find_operator(line, "<<<")?;
+    let rest = line[pos + 3..].trim_start();
+
+    // The word runs to the first unquoted blank or operator
+    let bytes = rest.as_bytes();
+    let mut quote: Option<u8> = None;
+    let mut end = 0;
+    while end < bytes.len() {
+        let b = bytes[end];
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) => {}
+            None if b == b'\\' => end += 1,
+            None if b == b'"' || b == b'\'' => quote = Some(b),
+            None if b.is_ascii_whitespace() || b"|;&<>".contains(&b) => break,
+            None => {}
+        }
+        end += 1;
+    }
+    let end = end.min(rest.len());
+
+    let cmd_line = format!("{} {}", line[..pos].trim_end(), rest[end..].trim_start());
+    Some((cmd_line.trim().to_string(), rest[..end].to_string()))
+}
+
+/// Replaces each aliased command word in `line` with its alias, as bash does.
+///
+/// An alias's text is expanded too, except for names already being expanded, so
+/// `alias ls='ls -F'` and `alias a=b b=a` stop instead of looping. A value ending
+/// in a blank makes the word after it a command word as well.
+pub fn expand_aliases(line: &str, aliases: &HashMap<String, String>) -> String {
+    expand_aliases_in(line, aliases, &mut Vec::new())
+}
+
+fn expand_aliases_in(line: &str, aliases: &HashMap<String, String>, expanding: &mut Vec<String>) -> String {
+    let mut out = String::new();
+    let mut command_word = true;
+    let mut rest = line;
+
+    loop {
+        let trimmed = rest.trim_start();
+        out.push_str(&rest[..rest.len() - trimmed.len()]);
+        rest = trimmed;
+        if rest.is_empty() {
+            return out;
+        }
+
+        // The word after an operator starts a new command
+        if let Some(op) = ["&&", "||", ";", "|"].into_iter().find(|op| rest.starts_with(op)) {
+            out.push_str(op);
+            rest = &rest[op.len()..];
+            command_word = true;
+            continue;
+        }
+
+        let end = word_end(rest);
+        let word = &rest[..end];
+        rest = &rest[end..];
+
+        let alias = aliases.get(word).filter(|_| command_word && !expanding.iter().any(|name| name == word));
+        match alias {
+            Some(value) => {
+                expanding.push(word.to_string());
+                out.push_str(&expand_aliases_in(value, aliases, expanding));
+                expanding.pop();
+                command_word = value.ends_with([' ', '\t']);
+            }
+            None => {
+                out.push_str(word);
+                command_word = false;
+            }
+        }
+    }
+}
+
+/// Length of the word `input` starts with: up to the first unquoted blank or
+/// `|`, `;` or `&&`, with `$( ... )` and `(( ... ))` kept whole.
+fn word_end(input: &str) -> usize {
+    let bytes = input.as_bytes();
+    let mut quote: Option<u8> = None;
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let b = bytes[i];
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) => {}
+            None if b == b'\\' => i += 1,
+            None if b == b'"' || b == b'\'' => quote = Some(b),
+            None if b == b'(' => depth += 1,
+            None if b == b')' => depth = depth.saturating_sub(1),
+            None if depth == 0 && (b.is_ascii_whitespace() || b == b'|' || b == b';' || bytes[i..].starts_with(b"&&")) => break,
+            None => {}
+        }
+        i += 1;
+    }
+    i.min(input.len())
+}
+
+/// Splits a command line at the `&&`, `||` and `;` outside quotes and parentheses,
+/// pairing each piece with the operator after it. A piece that's empty before an
+/// operator (`&& b`, `a ;; b`) is a syntax error, as in bash.
+fn chain_segments(input: &str) -> Result<Vec<(&str, Option<ChainOperator>)>> {
+    let bytes = input.as_bytes();
+    let mut segments = Vec::new();
+    let mut quote: Option<u8> = None;
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let b = bytes[i];
+        let operator = match quote {
+            Some(q) if b == q => {
+                quote = None;
+                None
+            }
+            Some(_) => None,
+            None if b == b'\\' => {
+                i += 1;
+                None
+            }
+            None if b == b'"' || b == b'\'' => {
+                quote = Some(b);
+                None
+            }
+            None if b == b'(' => {
+                depth += 1;
+                None
+            }
+            None if b == b')' => {
+                depth = depth.saturating_sub(1);
+                None
+            }
+            None if depth > 0 => None,
+            None if bytes[i..].starts_with(b"&&") => Some((ChainOperator::And, 2)),
+            // The `|` of `>|` is part of the redirect
+            None if bytes[i..].starts_with(b"||") && !(i > 0 && bytes[i - 1] == b'>') => Some((ChainOperator::Or, 2)),
+            None if b == b';' => Some((ChainOperator::Semicolon, 1)),
+            None => None,
+        };
+
+        match operator {
+            Some((operator, len)) => {
+                let text = input[start..i].trim();
+                if text.is_empty() {
+                    return Err(PieBashError::ParseError(format!(
+                        "syntax error near unexpected token `{}'",
+                        operator.symbol()
+                    )).into());
+                }
+                segments.push((text, Some(operator)));
+                i += len;
+                start = i;
+            }
+            None => i += 1,
+        }
+    }
+
+    let rest = input[start..].trim();
+    if !rest.is_empty() || segments.is_empty() {
+        segments.push((rest, None));
+    }
+    Ok(segments)
+}
+
+/// Byte offset of the first `op` outside quotes and parentheses (so `(( a > b ))` and
+/// `$(( x | 1 ))` stay whole), and not escaped with a backslash.
+fn find_operator(input: &str, op: &str) -> Option<usize> {
+    let bytes = input.as_bytes();
+    let mut quote: Option<u8> = None;
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let b = bytes[i];
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) => {}
+            None if b == b'\\' => i += 1,
+            None if b == b'"' || b == b'\'' => quote = Some(b),
+            None if b == b'(' => depth += 1,
+            None if b == b')' => depth = depth.saturating_sub(1),
+            // The `|` of `>|` is part of the redirect
+            None if op == "|" && b == b'|' && i > 0 && bytes[i - 1] == b'>' => {}
+            None if depth == 0 && bytes[i..].starts_with(op.as_bytes()) => return Some(i),
+            None => {}
+        }
+        i += 1;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn unquoted_heredoc_expands_variables() {
+        let env = vars(&[("NAME", "world"), ("?", "3")]);
+        let command = CommandParser::new().parse_with_env("cat <<EOF\nhi $NAME ${NAME} $? $((2+2)) \\$NAME\nEOF", &env).unwrap();
+        assert_eq!(command.name, "cat");
+        assert_eq!(command.stdin_input.as_deref(), Some("hi world world 3 4 $NAME\n"));
+    }
+
+    #[test]
+    fn quoted_heredoc_stays_literal() {
+        let env = vars(&[("NAME", "world")]);
+        for marker in ["<<'EOF'", "<<\"EOF\"", "<<\\EOF"] {
+            let input = format!("cat {}\nhi $NAME\nEOF", marker);
+            let command = CommandParser::new().parse_with_env(&input, &env).unwrap();
+            assert_eq!(command.stdin_input.as_deref(), Some("hi $NAME\n"), "{}", marker);
+        }
+    }
+
+    #[test]
+    fn shift_in_arithmetic_is_not_a_heredoc() {
+        assert_eq!(split_heredoc("let x=1<<2"), None);
+        assert_eq!(split_heredoc("(( x = 1 << 2 ))"), None);
+        assert_eq!(split_heredoc("echo $((1 << 2))"), None);
+        assert_eq!(split_heredoc("let x=1; cat <<EOF").map(|h| h.delimiter), Some("EOF".to_string()));
+    }
+
+    fn chain(input: &str) -> Vec<(String, Option<ChainOperator>)> {
+        CommandParser::new().split_chain(input).unwrap()
+    }
+
+    #[test]
+    fn chain_splits_on_each_operator() {
+        use ChainOperator::*;
+        assert_eq!(chain("a && b"), [("a".to_string(), Some(And)), ("b".to_string(), None)]);
+        assert_eq!(chain("a || b"), [("a".to_string(), Some(Or)), ("b".to_string(), None)]);
+        assert_eq!(chain("a; b"), [("a".to_string(), Some(Semicolon)), ("b".to_string(), None)]);
+    }
+
+    #[test]
+    fn mixed_chain_keeps_order_and_leaves_quotes_and_pipes_alone() {
+        use ChainOperator::*;
+        assert_eq!(
+            chain("a x && b 'y;z' || c | d; e \"&&\" >| f;"),
+            [
+                ("a x".to_string(), Some(And)),
+                ("b 'y;z'".to_string(), Some(Or)),
+                ("c | d".to_string(), Some(Semicolon)),
+                ("e \"&&\" >| f".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn leading_or_trailing_operator_is_a_syntax_error() {
+        for input in ["&& a", "|| a", "; a", "a &&", "a ||", "a && && b", "a ;; b"] {
+            let err = CommandParser::new().split_chain(input).unwrap_err();
+            assert!(
+                matches!(err.downcast_ref::<PieBashError>(), Some(PieBashError::ParseError(message)) if message.starts_with("syntax error")),
+                "{}: {}", input, err
+            );
+        }
+    }
+
+    #[test]
+    fn self_referential_alias_expands_once() {
+        let aliases = vars(&[("ls", "ls -l")]);
+        assert_eq!(expand_aliases("ls /tmp", &aliases), "ls -l /tmp");
+        assert_eq!(expand_aliases("ls && ls", &aliases), "ls -l && ls -l");
+    }
+
+    #[test]
+    fn mutually_recursive_aliases_stop_at_the_repeated_name() {
+        let aliases = vars(&[("a", "b x"), ("b", "a y")]);
+        assert_eq!(expand_aliases("a", &aliases), "a y x");
+        assert_eq!(expand_aliases("b", &aliases), "b x y");
+    }
+
+    #[test]
+    fn pipeline_stages_take_their_own_redirects() {
+        let command = CommandParser::new().parse("seq 3 | tr 1 x > out.txt").unwrap();
+        assert_eq!(command.name, "seq");
+        assert!(command.redirect_stdout.is_none());
+
+        let last = command.pipe_to.as_deref().unwrap();
+        assert_eq!(last.name, "tr");
+        assert_eq!(last.args, ["1", "x"]);
+        assert_eq!(last.redirect_stdout.as_ref().map(|r| r.target.as_str()), Some("out.txt"));
+
+        let command = CommandParser::new().parse("cat a &> err.log | wc -l").unwrap();
+        assert_eq!(command.redirect_stderr.as_ref().map(|r| r.target.as_str()), Some("err.log"));
+        assert_eq!(command.pipe_to.as_deref().map(|c| c.name.as_str()), Some("wc"));
+    }
+}
@@ -0,0 +1,79 @@
+use std::path::PathBuf;
+
+use super::builtins::Builtins;
+use super::environment::Environment;
+
+/// Names the shell hands to its own language runtimes rather than to `PATH`.
+pub const RUNTIMES: &[&str] = &[
+    "python", "python3",
+    "node", "nodejs",
+    "java", "javac",
+    "ruby", "rb",
+    "rust", "rustc", "cargo",
+    "go",
+    "php", "perl", "lua",
+];
+
+/// What a command name runs as. `type`, `command -v` and `which` all go through
+/// [`resolve`] / [`resolve_all`], so they report the order the shell dispatches in:
+/// alias, then builtin, then runtime, then `PATH`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    /// An alias, with the text it expands to
+    Alias(String),
+    Builtin,
+    /// `@lang`, or a language name like `python`, run through piebash's runtimes
+    Runtime,
+    /// A program found on `PATH`
+    File(PathBuf),
+}
+
+impl Resolution {
+    /// `type`'s wording: `ls is /usr/bin/ls`.
+    pub fn describe(&self, name: &str) -> String {
+        match self {
+            Self::Alias(value) => format!("{} is aliased to '{}'", name, value),
+            Self::Builtin => format!("{} is a shell builtin", name),
+            Self::Runtime => format!("{} is run by a piebash runtime", name),
+            Self::File(path) => format!("{} is {}", name, path.display()),
+        }
+    }
+}
+
+/// Whether `name` is code for a runtime: `@python ...`, `python script.py`, ...
+pub fn is_runtime(name: &str) -> bool {
+    RUNTIMES.contains(&name) || name.starts_with('@')
+}
+
+/// What `name` runs as, if anything.
+pub fn resolve(name: &str, env: &Environment, builtins: &Builtins) -> Option<Resolution> {
+    resolutions(name, env, builtins, false).into_iter().next()
+}
+
+/// Every way `name` resolves, the one that runs first; `type -a` lists them all.
+pub fn resolve_all(name: &str, env: &Environment, builtins: &Builtins) -> Vec<Resolution> {
+    resolutions(name, env, builtins, true)
+}
+
+fn resolutions(name: &str, env: &Environment, builtins: &Builtins, all: bool) -> Vec<Resolution> {
+    let mut found = Vec::new();
+    if let Some(value) = env.get_alias(name) {
+        found.push(Resolution::Alias(value));
+    }
+    if builtins.is_builtin(name) {
+        found.push(Resolution::Builtin);
+    }
+    if is_runtime(name) {
+        found.push(Resolution::Runtime);
+    }
+    if !all && !found.is_empty() {
+        return found;
+    }
+
+    if all {
+        found.extend(which::which_all(name).into_iter().flatten().map(Resolution::File));
+    } else if let Ok(path) = which::which(name) {
+        found.push(Resolution::File(path));
+    }
+    found
+}
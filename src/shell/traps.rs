@@ -0,0 +1,131 @@
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use tokio::sync::mpsc;
+
+use super::builtins::core::shell_quote;
+use super::parser::Command;
+
+/// What `trap` can catch: the shell exiting, Ctrl-C, and `kill`.
+pub const TRAP_SIGNALS: &[(&str, i32)] = &[("EXIT", 0), ("INT", 2), ("TERM", 15)];
+
+/// Commands registered with `trap`, and the signals caught for them.
+///
+/// A signal isn't listened for until a trap is set on it, so without traps Ctrl-C and
+/// SIGTERM keep their default effect. Caught signals are queued; the shell runs their
+/// traps once the foreground command has finished, as bash does.
+pub struct Traps {
+    handlers: HashMap<&'static str, String>,
+    listening: HashSet<&'static str>,
+    caught_tx: mpsc::UnboundedSender<&'static str>,
+    caught_rx: mpsc::UnboundedReceiver<&'static str>,
+}
+
+impl Traps {
+    pub fn new() -> Self {
+        let (caught_tx, caught_rx) = mpsc::unbounded_channel();
+        Self {
+            handlers: HashMap::new(),
+            listening: HashSet::new(),
+            caught_tx,
+            caught_rx,
+        }
+    }
+
+    /// `trap` lists the traps, `trap 'cmd' SIG...` sets them, `trap - SIG...` resets
+    /// them, and `trap '' SIG...` ignores the signals.
+    pub fn trap_cmd(&mut self, command: &Command) -> Result<()> {
+        let args: Vec<&String> = command.args.iter().filter(|a| *a != "--").collect();
+
+        if args.is_empty() || args[0] == "-p" {
+            for (name, _) in TRAP_SIGNALS {
+                if let Some(action) = self.handlers.get(name) {
+                    println!("trap -- {} {}", shell_quote(action), name);
+                }
+            }
+            return Ok(());
+        }
+
+        if args.len() < 2 {
+            anyhow::bail!("trap: usage: trap [-p] ['command' | -] signal...");
+        }
+
+        let action = args[0];
+        for spec in &args[1..] {
+            let name = Self::signal_name(spec).ok_or_else(|| {
+                anyhow::anyhow!("trap: {}: unsupported signal (EXIT, INT and TERM are)", spec)
+            })?;
+
+            if action == "-" {
+                self.handlers.remove(name);
+            } else {
+                self.handlers.insert(name, action.clone());
+                self.listen(name);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&String> {
+        self.handlers.get(name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        self.handlers.remove(name)
+    }
+
+    /// The next signal caught since the last call, if any.
+    pub fn take_caught(&mut self) -> Option<&'static str> {
+        self.caught_rx.try_recv().ok()
+    }
+
+    /// `INT`, `SIGINT`, `sigint` and `2` all name the same trap.
+    fn signal_name(spec: &str) -> Option<&'static str> {
+        let upper = spec.to_uppercase();
+        let bare = upper.strip_prefix("SIG").unwrap_or(&upper);
+        TRAP_SIGNALS
+            .iter()
+            .find(|(name, number)| *name == bare || number.to_string() == bare)
+            .map(|(name, _)| *name)
+    }
+
+    pub fn signal_number(name: &str) -> i32 {
+        TRAP_SIGNALS.iter().find(|(n, _)| *n == name).map(|(_, number)| *number).unwrap_or(0)
+    }
+
+    /// Starts forwarding `name` into the caught queue; once started, it stays on.
+    fn listen(&mut self, name: &'static str) {
+        if name == "EXIT" || !self.listening.insert(name) {
+            return;
+        }
+
+        let caught = self.caught_tx.clone();
+        match name {
+            "INT" => {
+                tokio::spawn(async move {
+                    while tokio::signal::ctrl_c().await.is_ok() {
+                        if caught.send(name).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            #[cfg(unix)]
+            "TERM" => {
+                use tokio::signal::unix::{signal, SignalKind};
+
+                let Ok(mut terminate) = signal(SignalKind::terminate()) else {
+                    return;
+                };
+                tokio::spawn(async move {
+                    while terminate.recv().await.is_some() {
+                        if caught.send(name).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            _ => {}
+        }
+    }
+}
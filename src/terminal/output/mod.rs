@@ -0,0 +1,87 @@
+use std::fmt::Display;
+use std::io::{IsTerminal, Write};
+use std::sync::Mutex;
+
+/// Serializes the shell's own status output.
+///
+/// Runtime installs and downloads print from tasks that run side by side, so a
+/// `println!` from one could land in the middle of another's progress line. Every
+/// write takes this lock, and the progress line is ended before anything else
+/// is printed below it.
+static OUTPUT: Mutex<OutputState> = Mutex::new(OutputState { progress_shown: false });
+
+struct OutputState {
+    /// A `\r` progress line is on screen without its newline yet
+    progress_shown: bool,
+}
+
+impl OutputState {
+    fn end_progress(&mut self, out: &mut impl Write) {
+        if self.progress_shown {
+            let _ = writeln!(out);
+            self.progress_shown = false;
+        }
+    }
+}
+
+fn lock() -> std::sync::MutexGuard<'static, OutputState> {
+    // A task that panicked mid-write leaves nothing worth protecting
+    OUTPUT.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Prints a whole line to stdout.
+pub fn line(text: impl Display) {
+    let mut state = lock();
+    let mut out = std::io::stdout().lock();
+    state.end_progress(&mut out);
+    let _ = writeln!(out, "{}", text);
+    let _ = out.flush();
+}
+
+/// Redraws the progress line in place.
+pub fn progress(text: impl Display) {
+    let mut state = lock();
+    let mut out = std::io::stdout().lock();
+    let _ = write!(out, "\r{}\x1b[K", text);
+    let _ = out.flush();
+    state.progress_shown = true;
+}
+
+/// Ends the progress line so the next output starts below it.
+pub fn finish_progress() {
+    let mut state = lock();
+    state.end_progress(&mut std::io::stdout().lock());
+}
+
+/// Sets the terminal window's title with the `ESC ] 0 ; ... BEL` escape, which
+/// xterm-like emulators and tmux understand. Nothing is written unless stdout is a
+/// terminal, and control characters are dropped so the text can't end the escape early.
+pub fn set_title(text: &str) {
+    let _state = lock();
+    let mut out = std::io::stdout().lock();
+    if !out.is_terminal() {
+        return;
+    }
+    let text: String = text.chars().filter(|c| !c.is_control()).collect();
+    let _ = write!(out, "\x1b]0;{}\x07", text);
+    let _ = out.flush();
+}
+
+/// Leaves the terminal ready for the prompt: everything flushed, any progress
+/// line ended, and the cursor back at the first column when a command's output
+/// didn't end with a newline.
+pub fn settle() {
+    let mut state = lock();
+    let mut out = std::io::stdout().lock();
+    state.end_progress(&mut out);
+    let _ = std::io::stderr().flush();
+
+    // zsh's trick, which needs no reply from the terminal (asking for the cursor
+    // position would swallow type-ahead): a line's worth of spaces only wraps when
+    // the cursor isn't at the first column, then `\r` and erase-line tidy up
+    if std::io::stdin().is_terminal() && out.is_terminal() {
+        let width = super::renderer::terminal_width();
+        let _ = write!(out, "{}\r\x1b[K", " ".repeat(width));
+    }
+    let _ = out.flush();
+}
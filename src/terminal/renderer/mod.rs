@@ -0,0 +1,49 @@
+/// Current terminal width in columns, or 80 when it can't be determined.
+pub fn terminal_width() -> usize {
+    crossterm::terminal::size()
+        .map(|(cols, _)| cols as usize)
+        .unwrap_or(80)
+}
+
+/// Lays out items column-major like GNU `ls`, using as many columns as fit in `width`.
+///
+/// Each item is `(display, visible_len)` so styled text can be padded by its
+/// on-screen length rather than its byte length.
+pub fn columns(items: &[(String, usize)], width: usize) -> String {
+    const GAP: usize = 2;
+
+    if items.is_empty() {
+        return String::new();
+    }
+
+    let mut layout = (items.len(), vec![0]);
+    for cols in (1..=items.len()).rev() {
+        let rows = items.len().div_ceil(cols);
+        let mut widths = vec![0; cols];
+        for (i, (_, len)) in items.iter().enumerate() {
+            let col = i / rows;
+            widths[col] = widths[col].max(*len);
+        }
+        let total: usize = widths.iter().sum::<usize>() + GAP * (cols - 1);
+        if total <= width || cols == 1 {
+            layout = (rows, widths);
+            break;
+        }
+    }
+
+    let (rows, widths) = layout;
+    let mut output = String::new();
+    for row in 0..rows {
+        let mut col = 0;
+        while let Some((display, len)) = items.get(col * rows + row) {
+            output.push_str(display);
+            let is_last = col + 1 == widths.len() || items.get((col + 1) * rows + row).is_none();
+            if !is_last {
+                output.push_str(&" ".repeat(widths[col] - len + GAP));
+            }
+            col += 1;
+        }
+        output.push('\n');
+    }
+    output
+}
@@ -8,9 +8,34 @@ pub enum PieBashError {
     #[error("Runtime error: {0}")]
     RuntimeError(String),
 
+    #[error("{0} runtime not available: {1}")]
+    RuntimeUnavailable(String, String),
+
     #[error("Parse error: {0}")]
     ParseError(String),
 
+    #[error("Process exited with code {0}")]
+    ExitStatus(i32),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
-}
\ No newline at end of file
+}
+
+impl PieBashError {
+    /// `ExitStatus` for a finished child: its exit code, or 128 + signal number when killed.
+    pub fn from_status(status: &std::process::ExitStatus) -> Self {
+        if let Some(code) = status.code() {
+            return PieBashError::ExitStatus(code);
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            if let Some(signal) = status.signal() {
+                return PieBashError::ExitStatus(128 + signal);
+            }
+        }
+
+        PieBashError::ExitStatus(1)
+    }
+}
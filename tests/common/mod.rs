@@ -0,0 +1,12 @@
+use assert_cmd::Command;
+
+/// Runs `script` through a non-interactive piebash in `dir`, with `dir` as its home.
+pub fn piebash(dir: &std::path::Path, script: &str) -> assert_cmd::assert::Assert {
+    Command::new(env!("CARGO_BIN_EXE_piebash"))
+        .args(["--no-banner", "--quiet"])
+        .current_dir(dir)
+        .env("HOME", dir)
+        .env_remove("PIEBASH_HOME")
+        .write_stdin(script)
+        .assert()
+}
@@ -0,0 +1,13 @@
+mod common;
+
+use common::piebash;
+use predicates::prelude::*;
+
+#[test]
+fn data_dir_is_not_exported_as_piebash_home() {
+    let dir = tempfile::tempdir().unwrap();
+
+    piebash(dir.path(), "echo \"[$PIEBASH_HOME]\"\nprintenv PIEBASH_HOME\necho status $?\n")
+        .success()
+        .stdout(predicate::str::starts_with("[]\nstatus 1\n"));
+}
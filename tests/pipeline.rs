@@ -0,0 +1,57 @@
+mod common;
+
+use common::piebash;
+use predicates::prelude::*;
+
+#[test]
+fn builtin_stages_pipe_text_through() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("file"), "x1\ny\nx2\nz\n").unwrap();
+
+    piebash(dir.path(), "cat file | grep x | wc -l\n")
+        .success()
+        .stdout(predicate::str::starts_with("2\n"));
+}
+
+#[test]
+fn last_stage_redirect_writes_its_file() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("file"), "x1\ny\nx2\nz\n").unwrap();
+
+    piebash(dir.path(), "cat file | grep x | wc -l > count.txt\ncat file | tr x X > upper.txt\n")
+        .success()
+        .stderr(predicate::str::contains("extra operand").not());
+
+    assert_eq!(std::fs::read_to_string(dir.path().join("count.txt")).unwrap().trim(), "2");
+    assert_eq!(std::fs::read_to_string(dir.path().join("upper.txt")).unwrap(), "X1\ny\nX2\nz\n");
+}
+
+#[test]
+fn captured_ls_takes_the_same_flags_as_terminal_ls() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("d1/sub")).unwrap();
+    std::fs::write(dir.path().join("d1/a"), "").unwrap();
+
+    piebash(dir.path(), "ls -d d1 | cat\nls -1 d1 | cat\nls -d d1 > listing.txt\n")
+        .success()
+        .stdout(predicate::str::starts_with("d1\na\nsub\n"));
+
+    assert_eq!(std::fs::read_to_string(dir.path().join("listing.txt")).unwrap(), "d1\n");
+}
+
+#[test]
+fn a_failing_builtin_stage_still_feeds_the_rest() {
+    let dir = tempfile::tempdir().unwrap();
+
+    piebash(dir.path(), "ls nothere | wc -l\necho status $?\nset -o pipefail\ngrep zz nothere | wc -l\necho status $?\n")
+        .stdout(predicate::str::starts_with("0\nstatus 0\n0\nstatus 1\n"));
+}
+
+#[test]
+fn every_first_stage_builtin_is_captured() {
+    let dir = tempfile::tempdir().unwrap();
+
+    piebash(dir.path(), "PIEBASH_TEST_VAR=1\nset | grep ^PIEBASH_TEST_VAR\n")
+        .success()
+        .stdout(predicate::str::starts_with("PIEBASH_TEST_VAR=1\n"));
+}
@@ -0,0 +1,18 @@
+mod common;
+
+use common::piebash;
+use predicates::prelude::*;
+
+#[test]
+fn every_builtin_writes_to_its_redirect_files() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("f.txt"), "a1\nb\na2\n").unwrap();
+
+    piebash(dir.path(), "grep a1 f.txt &> both.txt\nwc -l f.txt > count.txt\ngrep a nothere &> missing.txt\necho status $?\n")
+        .stdout(predicate::str::starts_with("status 1\n"))
+        .stderr(predicate::str::contains("nothere").not());
+
+    assert!(std::fs::read_to_string(dir.path().join("both.txt")).unwrap().contains("a1"));
+    assert!(std::fs::read_to_string(dir.path().join("count.txt")).unwrap().contains('3'));
+    assert!(std::fs::read_to_string(dir.path().join("missing.txt")).unwrap().contains("nothere"));
+}
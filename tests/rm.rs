@@ -0,0 +1,18 @@
+mod common;
+
+use common::piebash;
+use predicates::prelude::*;
+
+#[test]
+fn dot_and_dot_dot_operands_are_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("t1/sub")).unwrap();
+    std::fs::write(dir.path().join("t1/keep"), "").unwrap();
+
+    piebash(dir.path(), "rm -r t1/sub/..\necho status $?\nrm -rf t1/sub/. t1/sub/../ .\necho status $?\n")
+        .stdout(predicate::str::starts_with("status 1\nstatus 1\n"))
+        .stderr(predicate::str::contains("refusing to remove '.' or '..' directory: skipping 't1/sub/..'"));
+
+    assert!(dir.path().join("t1/keep").exists());
+    assert!(dir.path().join("t1/sub").exists());
+}
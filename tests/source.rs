@@ -0,0 +1,28 @@
+mod common;
+
+use common::piebash;
+use predicates::prelude::*;
+
+#[test]
+fn sourced_variables_stay_set() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("vars.sh"), "# settings\nGREETING=hello\nexport TARGET=world\n").unwrap();
+
+    piebash(dir.path(), "source vars.sh\necho $GREETING $TARGET\n. ./vars.sh && echo again\n")
+        .success()
+        .stdout(predicate::str::starts_with("hello world\nagain\n"));
+}
+
+#[test]
+fn sourcing_stops_at_an_unhandled_error() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("steps.sh"),
+        "echo one\nfalse\necho two\npiebash-no-such-command\necho three\n",
+    )
+    .unwrap();
+
+    piebash(dir.path(), "source steps.sh\necho status $?\n")
+        .stdout(predicate::str::starts_with("one\ntwo\nstatus 127\n"))
+        .stderr(predicate::str::contains("steps.sh: line 4"));
+}